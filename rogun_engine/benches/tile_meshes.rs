@@ -0,0 +1,94 @@
+//! Compares rebuilding every tile map's mesh each frame against the tile
+//! mesh cache, for a large world. Meshes here are the maps' draw lists, the
+//! part of the GL renderer's meshing that doesn't need a GL context, so only
+//! that CPU work is timed. The GL renderer also turns them into vertices and
+//! uploads them, which the cache skips too but isn't measured here.
+//!
+//! Run with `cargo bench -p engine --bench tile_meshes`.
+
+extern crate engine;
+
+use engine::common::color::RGBf32;
+use engine::common::vec::Vec2f32;
+use engine::renderer::draw_list::*;
+use engine::renderer::tile_mesh::TileMeshCache;
+use engine::tile::*;
+use std::time::Instant;
+
+const FRAMES: u32 = 100;
+
+/// A world of size x size tile maps, with every other tile filled.
+fn world(size: usize) -> Vec<TileMap16> {
+  let mut maps = Vec::new();
+  for ii in 0..size * size {
+    let mut map = TileMap16::new();
+    map.world_pos = Vec2f32((ii % size) as f32 * 256.0, (ii / size) as f32 * 256.0);
+    for t in 0..map.tiles.len() {
+      map.tiles[t] = (t % 2) as u16;
+    }
+    maps.push(map);
+  }
+  maps
+}
+
+/// Build a tile map's draw list, returning its length.
+fn build_mesh(t_map: &TileMap16, tile_bank: &TileBank) -> usize {
+  build_tile_map_draw_list(t_map, tile_bank).len()
+}
+
+/// Average milliseconds per frame of f over FRAMES frames.
+fn time_frames<F: FnMut(u32)>(mut f: F) -> f64 {
+  let start = Instant::now();
+  for frame in 0..FRAMES {
+    f(frame);
+  }
+  let elapsed = start.elapsed();
+  (elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1000000.0) / FRAMES as f64
+}
+
+fn main() {
+  let mut tile_bank = TileBank::new();
+  let mut tile = Tile::new(1);
+  tile.color = RGBf32::new(0.0, 1.0, 0.0);
+  tile_bank.register_tile(tile);
+
+  // Everything in view, or a 4 x 4 map window of the world
+  let view_all = (Vec2f32(-1.0, -1.0), Vec2f32(1.0e6, 1.0e6));
+  let view_window = (Vec2f32(0.0, 0.0), Vec2f32(1024.0, 1024.0));
+
+  println!("CPU meshing only, vertex buffer uploads aren't timed");
+  println!("{:>6} {:>8} {:>12} {:>12} {:>12}", "maps", "tiles", "rebuild ms", "cached ms", "culled ms");
+  for &size in &[4, 16, 32] {
+    let mut maps = world(size);
+
+    // Every map, every frame, like render_game used to
+    let mut vertices = 0;
+    let rebuild = time_frames(|_| {
+      for t_map in &maps {
+        vertices += build_mesh(t_map, &tile_bank);
+      }
+    });
+
+    // One tile changes each frame, so one map is rebuilt
+    let mut cache = TileMeshCache::new();
+    cache.update(&maps, &tile_bank, view_all, |t_map| build_mesh(t_map, &tile_bank));
+    let cached = time_frames(|frame| {
+      let ix = frame as usize % maps.len();
+      maps[ix].tiles[0] ^= 1;
+      cache.update(&maps, &tile_bank, view_all, |t_map| build_mesh(t_map, &tile_bank));
+    });
+    assert!(vertices > 0 && cache.get_rebuild_count() == (maps.len() as u64 + FRAMES as u64));
+
+    // Every map changes each frame, but only the ones in view are rebuilt
+    let mut cache = TileMeshCache::new();
+    let culled = time_frames(|_| {
+      for t_map in maps.iter_mut() {
+        t_map.tiles[0] ^= 1;
+      }
+      cache.update(&maps, &tile_bank, view_window, |t_map| build_mesh(t_map, &tile_bank));
+    });
+    assert!(cache.get_rebuild_count() == 16 * FRAMES as u64);
+
+    println!("{:>6} {:>8} {:>12.3} {:>12.3} {:>12.3}", maps.len(), maps.len() * 256, rebuild, cached, culled);
+  }
+}
@@ -1,4 +1,4 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct RGBf32 {
   pub r: f32,
   pub g: f32,
@@ -13,12 +13,28 @@ impl RGBf32 {
       b: b,
     }
   }
+
+  /// Add an alpha channel to this color.
+  pub fn with_alpha(&self, a: f32) -> RGBAf32 {
+    RGBAf32::new(self.r, self.g, self.b, a)
+  }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct RGBAf32 {
   pub r: f32,
   pub g: f32,
   pub b: f32,
   pub a: f32,
 }
+
+impl RGBAf32 {
+  pub fn new(r: f32, g: f32, b: f32, a: f32) -> RGBAf32 {
+    RGBAf32 {
+      r: r,
+      g: g,
+      b: b,
+      a: a,
+    }
+  }
+}
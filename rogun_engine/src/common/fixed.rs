@@ -0,0 +1,102 @@
+use std::ops::*;
+use common::scalar::{Scalar, Vec2};
+
+/// Number of fractional bits in a Fx.
+const FRAC_BITS: u32 = 16;
+const ONE: i64 = 1 << FRAC_BITS;
+
+/// Signed fixed point number with 16 fractional bits. Used by the
+/// deterministic physics mode, as integer maths gives the same results on
+/// every platform. Arithmetic saturates at the ends of the range rather than
+/// overflowing, and dividing by zero gives the largest value with the sign of
+/// the dividend.
+#[derive(Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Fx (pub i64);
+
+impl Fx {
+  pub fn from_int(v: i64) -> Fx { Fx(v << FRAC_BITS) }
+
+  /// Convert from a float, rounding to the nearest representable value.
+  pub fn from_f32(v: f32) -> Fx { Fx((v as f64 * ONE as f64).round() as i64) }
+
+  pub fn to_f32(self) -> f32 { (self.0 as f64 / ONE as f64) as f32 }
+
+  pub fn abs(self) -> Fx { Fx(self.0.saturating_abs()) }
+
+  /// Square root, rounded down. Negative numbers return 0.
+  pub fn sqrt(self) -> Fx {
+    if self.0 <= 0 { return Fx(0); }
+    // sqrt(v * 2^16) * 2^8 = sqrt(v * 2^32)
+    Fx(isqrt((self.0 as u128) << FRAC_BITS) as i64)
+  }
+}
+
+/// Integer square root, rounded down, with newton's method.
+fn isqrt(n: u128) -> u128 {
+  if n == 0 { return 0; }
+  let mut x = n;
+  let mut y = x / 2 + 1;
+  while y < x {
+    x = y;
+    y = (x + n / x) / 2;
+  }
+  x
+}
+
+/// Clamp a wide intermediate result into the range of a Fx.
+fn saturate(v: i128) -> Fx {
+  Fx(v.max(i64::min_value() as i128).min(i64::max_value() as i128) as i64)
+}
+
+impl Add for Fx {
+  type Output = Fx;
+  fn add(self, other: Fx) -> Fx { Fx(self.0.saturating_add(other.0)) }
+}
+
+impl Sub for Fx {
+  type Output = Fx;
+  fn sub(self, other: Fx) -> Fx { Fx(self.0.saturating_sub(other.0)) }
+}
+
+impl Mul for Fx {
+  type Output = Fx;
+  fn mul(self, other: Fx) -> Fx { saturate((self.0 as i128 * other.0 as i128) >> FRAC_BITS) }
+}
+
+impl Div for Fx {
+  type Output = Fx;
+  fn div(self, other: Fx) -> Fx {
+    if other.0 == 0 {
+      return saturate(self.0.signum() as i128 * i64::max_value() as i128);
+    }
+    saturate(((self.0 as i128) << FRAC_BITS) / other.0 as i128)
+  }
+}
+
+impl Neg for Fx {
+  type Output = Fx;
+  fn neg(self) -> Fx { Fx(self.0.saturating_neg()) }
+}
+
+impl Scalar for Fx {
+  fn from_f32(v: f32) -> Fx { Fx::from_f32(v) }
+  fn to_f32(self) -> f32 { Fx::to_f32(self) }
+  fn sqrt(self) -> Fx { Fx::sqrt(self) }
+  fn floor(self) -> Fx { Fx(self.0 & !(ONE - 1)) }
+  fn abs(self) -> Fx { Fx::abs(self) }
+
+  /// Doesn't saturate unless the length itself is out of range, unlike
+  /// squaring and adding x and y.
+  fn hypot(x: Fx, y: Fx) -> Fx {
+    let (x, y) = (x.0 as i128, y.0 as i128);
+    let len = isqrt((x * x) as u128 + (y * y) as u128);
+    Fx(len.min(i64::max_value() as u128) as i64)
+  }
+}
+
+/// 2D vector of fixed point numbers.
+pub type Vec2fx = Vec2<Fx>;
+
+/// Snap a float onto the fixed point grid, so that it survives a round trip
+/// through Fx unchanged.
+pub fn snap(v: f32) -> f32 { Fx::from_f32(v).to_f32() }
@@ -1,2 +1,6 @@
 pub mod color;
 pub mod vec;
+pub mod scalar;
+pub mod fixed;
+pub mod rng;
+pub mod png;
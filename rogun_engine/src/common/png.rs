@@ -0,0 +1,397 @@
+/// Signature at the start of every PNG file.
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Largest block of uncompressed data in a deflate stream.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Largest width or height decode_rgba accepts, the size of the biggest
+/// textures most GPUs support.
+pub const MAX_DIMENSION: u32 = 16384;
+
+/// CRC32 as used by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+  let mut crc = !0u32;
+  for &b in data {
+    crc ^= b as u32;
+    for _ in 0..8 {
+      crc = if crc & 1 != 0 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+/// Adler32 checksum as used by zlib streams.
+fn adler32(data: &[u8]) -> u32 {
+  let (mut a, mut b) = (1u32, 0u32);
+  for &d in data {
+    a = (a + d as u32) % 65521;
+    b = (b + a) % 65521;
+  }
+  (b << 16) | a
+}
+
+/// Wrap data in a zlib stream, without compressing it.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+  let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED_BLOCK * 5 + 11);
+  // Deflate, 32K window, no preset dictionary, fastest compression
+  out.push(0x78);
+  out.push(0x01);
+  let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+  while let Some(block) = blocks.next() {
+    out.push(if blocks.peek().is_none() { 1 } else { 0 });
+    let len = block.len() as u16;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(&(!len).to_le_bytes());
+    out.extend_from_slice(block);
+  }
+  out.extend_from_slice(&adler32(data).to_be_bytes());
+  out
+}
+
+/// Append a chunk to a PNG file.
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+  out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+  let start = out.len();
+  out.extend_from_slice(kind);
+  out.extend_from_slice(data);
+  let crc = crc32(&out[start..]);
+  out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encode 8 bit RGBA pixels, row by row from the top left, as a PNG file.
+/// The image data is stored uncompressed, to keep this simple. Width and
+/// height must be non-zero.
+pub fn encode_rgba(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+  assert!(width > 0 && height > 0);
+  assert!(pixels.len() == width as usize * height as usize * 4);
+  let mut out = Vec::new();
+  out.extend_from_slice(&SIGNATURE);
+
+  // 8 bits per channel, RGBA, default compression, filter and interlacing
+  let mut header = Vec::with_capacity(13);
+  header.extend_from_slice(&width.to_be_bytes());
+  header.extend_from_slice(&height.to_be_bytes());
+  header.extend_from_slice(&[8, 6, 0, 0, 0]);
+  write_chunk(&mut out, b"IHDR", &header);
+
+  // Each row starts with its filter type, 0 for none
+  let stride = width as usize * 4;
+  let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+  for row in pixels.chunks(stride) {
+    raw.push(0);
+    raw.extend_from_slice(row);
+  }
+  write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+  write_chunk(&mut out, b"IEND", &[]);
+  out
+}
+
+/// Base lengths and extra bits of deflate length codes 257 - 285.
+const LENGTH_BASE: [u16; 29] = [
+  3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67,
+  83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [
+  0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5,
+  5, 5, 0];
+
+/// Base distances and extra bits of deflate distance codes.
+const DIST_BASE: [u16; 30] = [
+  1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769,
+  1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [
+  0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11,
+  11, 12, 12, 13, 13];
+
+/// Order the code length code lengths are stored in, in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+  16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Reads a deflate stream a bit at a time, least significant bit first.
+struct BitReader<'a> {
+  data: &'a [u8],
+  pos: usize,
+  bit_buf: u32,
+  bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+  fn bits(&mut self, n: u32) -> Result<u32, String> {
+    while self.bit_count < n {
+      if self.pos >= self.data.len() {
+        return Err("Unexpected end of compressed data".to_owned());
+      }
+      self.bit_buf |= (self.data[self.pos] as u32) << self.bit_count;
+      self.pos += 1;
+      self.bit_count += 8;
+    }
+    let v = self.bit_buf & ((1u64 << n) - 1) as u32;
+    self.bit_buf >>= n;
+    self.bit_count -= n;
+    Ok(v)
+  }
+
+  /// Skip to the next byte boundary.
+  fn align(&mut self) {
+    self.bit_buf = 0;
+    self.bit_count = 0;
+  }
+}
+
+/// Canonical huffman code, stored as the number of codes of each length and
+/// the symbols in code order.
+struct Huffman {
+  counts: [u16; 16],
+  symbols: Vec<u16>,
+}
+
+impl Huffman {
+  /// Build a code from the code length of each symbol, 0 if it's unused.
+  fn new(lengths: &[u8]) -> Huffman {
+    let mut counts = [0u16; 16];
+    for &l in lengths {
+      counts[l as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut offsets = [0u16; 16];
+    for l in 1..15 {
+      offsets[l + 1] = offsets[l] + counts[l];
+    }
+    let mut symbols = vec![0; lengths.len()];
+    for (sym, &l) in lengths.iter().enumerate() {
+      if l != 0 {
+        symbols[offsets[l as usize] as usize] = sym as u16;
+        offsets[l as usize] += 1;
+      }
+    }
+    Huffman { counts: counts, symbols: symbols }
+  }
+
+  /// Decode a symbol, reading one bit at a time.
+  fn decode(&self, r: &mut BitReader) -> Result<u16, String> {
+    let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+    for l in 1..16 {
+      code |= r.bits(1)? as i32;
+      let count = self.counts[l] as i32;
+      if code - first < count {
+        return Ok(self.symbols[(index + code - first) as usize]);
+      }
+      index += count;
+      first = (first + count) << 1;
+      code <<= 1;
+    }
+    Err("Invalid huffman code".to_owned())
+  }
+}
+
+/// Decompress a block of huffman coded data.
+fn inflate_block(r: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman) -> Result<(), String> {
+  loop {
+    let sym = lit.decode(r)? as usize;
+    if sym < 256 {
+      out.push(sym as u8);
+    }
+    else if sym == 256 {
+      return Ok(());
+    }
+    else {
+      let sym = sym - 257;
+      if sym >= LENGTH_BASE.len() { return Err("Invalid length code".to_owned()); }
+      let len = LENGTH_BASE[sym] as usize + r.bits(LENGTH_EXTRA[sym] as u32)? as usize;
+      let d = dist.decode(r)? as usize;
+      if d >= DIST_BASE.len() { return Err("Invalid distance code".to_owned()); }
+      let d = DIST_BASE[d] as usize + r.bits(DIST_EXTRA[d] as u32)? as usize;
+      if d > out.len() { return Err("Distance too far back".to_owned()); }
+      // Copy a byte at a time, as the copy can overlap what it's writing
+      let start = out.len() - d;
+      for ii in 0..len {
+        let b = out[start + ii];
+        out.push(b);
+      }
+    }
+  }
+}
+
+/// Read the code lengths of a dynamic block, and build its codes.
+fn dynamic_codes(r: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+  let num_lit = r.bits(5)? as usize + 257;
+  let num_dist = r.bits(5)? as usize + 1;
+  let num_code = r.bits(4)? as usize + 4;
+
+  let mut code_lengths = [0u8; 19];
+  for ii in 0..num_code {
+    code_lengths[CODE_LENGTH_ORDER[ii]] = r.bits(3)? as u8;
+  }
+  let code = Huffman::new(&code_lengths);
+
+  let mut lengths = Vec::with_capacity(num_lit + num_dist);
+  while lengths.len() < num_lit + num_dist {
+    let sym = code.decode(r)?;
+    let (value, repeat) = match sym {
+      0..=15 => (sym as u8, 1),
+      16 => {
+        let prev = lengths.last().cloned();
+        if prev.is_none() { return Err("Repeated code length with no previous length".to_owned()); }
+        (prev.unwrap(), 3 + r.bits(2)?)
+      }
+      17 => (0, 3 + r.bits(3)?),
+      _ => (0, 11 + r.bits(7)?),
+    };
+    for _ in 0..repeat {
+      lengths.push(value);
+    }
+  }
+  if lengths.len() > num_lit + num_dist { return Err("Too many code lengths".to_owned()); }
+  Ok((Huffman::new(&lengths[..num_lit]), Huffman::new(&lengths[num_lit..])))
+}
+
+/// Decompress a zlib stream.
+fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+  if data.len() < 2 || data[0] & 0x0f != 8 || data[1] & 0x20 != 0
+    || ((data[0] as u16) << 8 | data[1] as u16) % 31 != 0 {
+      return Err("Invalid zlib header".to_owned());
+    }
+  let mut r = BitReader { data: &data[2..], pos: 0, bit_buf: 0, bit_count: 0 };
+  let mut out = Vec::new();
+  loop {
+    let last = r.bits(1)? == 1;
+    match r.bits(2)? {
+      0 => {
+        // Stored block
+        r.align();
+        if r.pos + 4 > r.data.len() { return Err("Unexpected end of compressed data".to_owned()); }
+        let len = r.data[r.pos] as usize | (r.data[r.pos + 1] as usize) << 8;
+        r.pos += 4;
+        if r.pos + len > r.data.len() { return Err("Unexpected end of compressed data".to_owned()); }
+        out.extend_from_slice(&r.data[r.pos..r.pos + len]);
+        r.pos += len;
+      }
+      1 => {
+        // Fixed huffman codes
+        let mut lengths = [0u8; 288];
+        for (ii, l) in lengths.iter_mut().enumerate() {
+          *l = match ii { 0..=143 => 8, 144..=255 => 9, 256..=279 => 7, _ => 8 };
+        }
+        let lit = Huffman::new(&lengths);
+        let dist = Huffman::new(&[5; 30]);
+        inflate_block(&mut r, &mut out, &lit, &dist)?;
+      }
+      2 => {
+        let (lit, dist) = dynamic_codes(&mut r)?;
+        inflate_block(&mut r, &mut out, &lit, &dist)?;
+      }
+      _ => return Err("Invalid deflate block type".to_owned()),
+    }
+    if last { break; }
+  }
+  Ok(out)
+}
+
+/// Paeth predictor used by PNG filter type 4.
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+  let p = a as i16 + b as i16 - c as i16;
+  let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+  if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+/// Decode a PNG file into 8 bit RGBA pixels, row by row from the top left.
+/// Returns (width, height, pixels). Supports 8 bit greyscale, RGB, palette,
+/// greyscale with alpha and RGBA images, without interlacing, up to
+/// MAX_DIMENSION pixels wide and high.
+pub fn decode_rgba(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+  if data.len() < 8 || data[..8] != SIGNATURE {
+    return Err("Not a PNG file".to_owned());
+  }
+
+  let (mut width, mut height, mut color_type) = (0, 0, 0);
+  let mut palette: Vec<[u8; 4]> = Vec::new();
+  let mut idat = Vec::new();
+  let mut pos = 8;
+  loop {
+    if pos + 12 > data.len() { return Err("Unexpected end of PNG file".to_owned()); }
+    let len = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+    let kind = &data[pos + 4..pos + 8];
+    if len > data.len() - pos - 12 { return Err("Unexpected end of PNG file".to_owned()); }
+    let body = &data[pos + 8..pos + 8 + len];
+    pos += 12 + len;
+
+    match kind {
+      b"IHDR" => {
+        if len != 13 { return Err("Invalid PNG header".to_owned()); }
+        width = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+        height = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+        color_type = body[9];
+        if body[8] != 8 || body[12] != 0 || ![0, 2, 3, 4, 6].contains(&color_type) {
+          return Err("Unsupported PNG format, only 8 bit non-interlaced images are supported".to_owned());
+        }
+      }
+      b"PLTE" => {
+        palette = body.chunks(3).filter(|c| c.len() == 3).map(|c| [c[0], c[1], c[2], 255]).collect();
+      }
+      b"tRNS" => {
+        for (p, &a) in palette.iter_mut().zip(body) {
+          p[3] = a;
+        }
+      }
+      b"IDAT" => idat.extend_from_slice(body),
+      b"IEND" => break,
+      _ => {}
+    }
+  }
+  if width == 0 || height == 0 { return Err("Invalid PNG size".to_owned()); }
+  if width > MAX_DIMENSION || height > MAX_DIMENSION {
+    return Err(format!("PNG is {}x{}, larger than the maximum of {}x{}",
+                       width, height, MAX_DIMENSION, MAX_DIMENSION));
+  }
+
+  let channels = match color_type { 0 | 3 => 1, 4 => 2, 2 => 3, _ => 4 };
+  let stride = width as usize * channels;
+  // Sizes of the image data with a filter byte on each row, and of the RGBA
+  // output, which is at least as big as the unfiltered data. Both can
+  // overflow a 32 bit usize.
+  let filtered_len = (stride + 1).checked_mul(height as usize);
+  let rgba_len = (width as usize * 4).checked_mul(height as usize);
+  if filtered_len.is_none() || rgba_len.is_none() { return Err("PNG is too large".to_owned()); }
+  let raw = zlib_decompress(&idat)?;
+  if raw.len() < filtered_len.unwrap() {
+    return Err("Not enough PNG image data".to_owned());
+  }
+
+  // Undo the filter on each row
+  let mut unfiltered = vec![0u8; stride * height as usize];
+  for y in 0..height as usize {
+    let filter = raw[y * (stride + 1)];
+    let row = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+    let (prev, cur) = unfiltered.split_at_mut(y * stride);
+    let prev = if y == 0 { None } else { Some(&prev[(y - 1) * stride..]) };
+    let cur = &mut cur[..stride];
+    for x in 0..stride {
+      let a = if x >= channels { cur[x - channels] } else { 0 };
+      let b = prev.map_or(0, |p| p[x]);
+      let c = if x >= channels { prev.map_or(0, |p| p[x - channels]) } else { 0 };
+      cur[x] = row[x].wrapping_add(match filter {
+        0 => 0,
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => paeth(a, b, c),
+        _ => return Err("Invalid PNG filter".to_owned()),
+      });
+    }
+  }
+
+  let mut pixels = Vec::with_capacity(rgba_len.unwrap());
+  for p in unfiltered.chunks(channels) {
+    match color_type {
+      0 => pixels.extend_from_slice(&[p[0], p[0], p[0], 255]),
+      2 => pixels.extend_from_slice(&[p[0], p[1], p[2], 255]),
+      3 => {
+        let c = palette.get(p[0] as usize);
+        if c.is_none() { return Err("PNG palette index out of range".to_owned()); }
+        pixels.extend_from_slice(c.unwrap());
+      }
+      4 => pixels.extend_from_slice(&[p[0], p[0], p[0], p[1]]),
+      _ => pixels.extend_from_slice(p),
+    }
+  }
+  Ok((width, height, pixels))
+}
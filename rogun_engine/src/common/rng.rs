@@ -0,0 +1,41 @@
+/// Small pseudo random number generator (xorshift64*). Not suitable for
+/// anything secure, but fast and the same on every platform, so a game
+/// seeded with the same seed produces the same numbers. Input recordings
+/// store the seed so replays match.
+#[derive(Clone)]
+pub struct Rng {
+  seed: u64,
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Rng {
+    // Scramble the seed, xorshift can't start from 0
+    let state = seed ^ 0x9e3779b97f4a7c15;
+    Rng { seed: seed, state: if state == 0 { 1 } else { state } }
+  }
+
+  /// The seed this generator was created with.
+  pub fn get_seed(&self) -> u64 { self.seed }
+
+  /// Current internal state, which changes every time a number is generated.
+  pub fn get_state(&self) -> u64 { self.state }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545f4914f6cdd1d)
+  }
+
+  /// Random float in [0, 1).
+  pub fn next_f32(&mut self) -> f32 {
+    // Top 24 bits fill the mantissa exactly
+    (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+  }
+
+  /// Random float in [min, max).
+  pub fn range(&mut self, min: f32, max: f32) -> f32 {
+    min + self.next_f32() * (max - min)
+  }
+}
@@ -0,0 +1,82 @@
+use std::ops::*;
+use common::vec::Vec2f32;
+
+/// Number type the physics runs on. f32 normally, or Fx (see common::fixed)
+/// in the deterministic step mode, so the same code does both.
+pub trait Scalar: Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self>
+  + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self> {
+  /// Convert from a float, rounding to the nearest representable value.
+  fn from_f32(v: f32) -> Self;
+
+  fn to_f32(self) -> f32;
+
+  fn sqrt(self) -> Self;
+
+  fn floor(self) -> Self;
+
+  /// Length of the vector (x, y).
+  fn hypot(x: Self, y: Self) -> Self;
+
+  fn zero() -> Self { Self::from_f32(0.0) }
+
+  fn abs(self) -> Self { if self < Self::zero() { -self } else { self } }
+
+  fn min(self, other: Self) -> Self { if other < self { other } else { self } }
+
+  fn max(self, other: Self) -> Self { if other > self { other } else { self } }
+}
+
+impl Scalar for f32 {
+  fn from_f32(v: f32) -> f32 { v }
+  fn to_f32(self) -> f32 { self }
+  fn sqrt(self) -> f32 { f32::sqrt(self) }
+  fn floor(self) -> f32 { f32::floor(self) }
+  fn hypot(x: f32, y: f32) -> f32 { (x.powi(2) + y.powi(2)).sqrt() }
+  fn abs(self) -> f32 { f32::abs(self) }
+  fn min(self, other: f32) -> f32 { f32::min(self, other) }
+  fn max(self, other: f32) -> f32 { f32::max(self, other) }
+}
+
+/// 2D vector of Scalars.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Vec2<S> (pub S, pub S);
+
+impl<S: Scalar> Vec2<S> {
+  pub fn from_vec2f32(v: Vec2f32) -> Vec2<S> { Vec2(S::from_f32(v.0), S::from_f32(v.1)) }
+
+  pub fn to_vec2f32(self) -> Vec2f32 { Vec2f32(self.0.to_f32(), self.1.to_f32()) }
+
+  pub fn len2(&self) -> S { self.0 * self.0 + self.1 * self.1 }
+
+  pub fn len(&self) -> S { S::hypot(self.0, self.1) }
+
+  pub fn dot(&self, other: Vec2<S>) -> S { self.0 * other.0 + self.1 * other.1 }
+
+  /// The vector scaled to a length of 1. Zero length vectors are returned as
+  /// they are.
+  pub fn nor(self) -> Vec2<S> {
+    let len = self.len();
+    if len == S::zero() { return self; }
+    Vec2(self.0 / len, self.1 / len)
+  }
+}
+
+impl<S: Scalar> Add for Vec2<S> {
+  type Output = Vec2<S>;
+  fn add(self, other: Vec2<S>) -> Vec2<S> { Vec2(self.0 + other.0, self.1 + other.1) }
+}
+
+impl<S: Scalar> Sub for Vec2<S> {
+  type Output = Vec2<S>;
+  fn sub(self, other: Vec2<S>) -> Vec2<S> { Vec2(self.0 - other.0, self.1 - other.1) }
+}
+
+impl<S: Scalar> Mul<S> for Vec2<S> {
+  type Output = Vec2<S>;
+  fn mul(self, amount: S) -> Vec2<S> { Vec2(self.0 * amount, self.1 * amount) }
+}
+
+impl<S: Scalar> Div<S> for Vec2<S> {
+  type Output = Vec2<S>;
+  fn div(self, amount: S) -> Vec2<S> { Vec2(self.0 / amount, self.1 / amount) }
+}
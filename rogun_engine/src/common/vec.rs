@@ -44,9 +44,10 @@ impl Vec2f32 {
   }
 
   /// Normalise the vector. Return a mutable reference to self, so you can
-  /// chain functions.
+  /// chain functions. Zero length vectors are left as they are.
   pub fn nor(&mut self) -> &mut Vec2f32 {
     let len = self.len();
+    if len == 0.0 { return self; }
     self.0 /= len;
     self.1 /= len;
     return self;
@@ -59,4 +60,16 @@ impl Vec2f32 {
     self.1 *= amount;
     return self;
   }
+
+  /// Dot product of this vector with another.
+  pub fn dot(&self, other: Vec2f32) -> f32 {
+    self.0 * other.0 + self.1 * other.1
+  }
+}
+
+impl Mul<f32> for Vec2f32 {
+  type Output = Vec2f32;
+  fn mul(self, amount: f32) -> Self::Output {
+    Vec2f32(self.0 * amount, self.1 * amount)
+  }
 }
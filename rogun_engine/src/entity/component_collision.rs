@@ -1,10 +1,12 @@
 use entity::{EntityID, Component};
 use common::vec::Vec2f32;
+use physics::trigger::TriggerEventKind;
 use std::ops::Fn;
 
 /// First param = this entity's entity ID,
 /// Second param = other entity's entity ID
-type CollCallback = Fn(EntityID, EntityID) -> ();
+/// Third param = whether the other entity entered or exited
+type CollCallback = Fn(EntityID, EntityID, TriggerEventKind) -> ();
 
 /// Component for AABB (rectangular) collision box
 /// Dependencies: 
@@ -16,10 +18,22 @@ pub struct CompCollAABB<'a> {
   pub offset: Vec2f32,
   pub size: Vec2f32,
 
-  /// Whether or not the physics engine reacts to this collision area
+  /// Whether or not the physics engine reacts to this collision area. Non
+  /// solid colliders are triggers, which emit enter / stay / exit events from
+  /// the PhysicsSystem instead.
   pub solid: bool,
 
-  /// Optional callback for collision
+  /// Collision layer bits this collider is on. Usually a single bit, see
+  /// PhysicsSystem::register_layer. Use physics::LAYER_DEFAULT if unsure.
+  pub layer: u32,
+
+  /// Collision layer bits this collider collides with. Two colliders only
+  /// interact if each one's layer is in the other's mask. Include
+  /// physics::LAYER_TILES to collide with tiles.
+  pub mask: u32,
+
+  /// Optional callback for triggers, called once when another collider
+  /// enters and once when it exits.
   pub callback: Option<&'a CollCallback>,
 }
 impl<'a> Component for CompCollAABB<'a> {
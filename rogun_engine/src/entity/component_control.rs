@@ -1,14 +1,18 @@
 use entity::{EntityID, Component};
-use glium::glutin::VirtualKeyCode;
+use common::vec::Vec2f32;
 
 /// Controller to control left / right movement control
 /// Dependencies:
 /// CompBody
 pub struct CompLRController {
   pub entity_id: EntityID,
-  pub key_l: VirtualKeyCode,
-  pub key_r: VirtualKeyCode,
 
+  /// Names of the input map actions to move left and right
+  pub action_l: String,
+  pub action_r: String,
+
+  /// Force applied while moving, see CompBody::apply_force. Scale this by the
+  /// body's mass to get the same acceleration whatever the mass.
   pub force: f32,
 }
 impl Component for CompLRController {
@@ -21,17 +25,144 @@ impl Component for CompLRController {
 /// CompCollAABB (For resetting jump counter)
 pub struct CompJumpController {
   pub entity_id: EntityID,
-  pub key_jump: VirtualKeyCode,
+  /// Name of the input map action to jump
+  pub action_jump: String,
+
+  /// Action to hold to drop through one way platforms, if any.
+  pub action_drop: Option<String>,
 
   /// Speed to set entity to on jump
-  pub jump_speed: VirtualKeyCode,
+  pub jump_speed: f32,
 
-  /// Number of jumps left - resets on touching the ground.
+  /// Number of jumps made since last touching the ground. Walking off a
+  /// ledge uses up the first jump once coyote_time runs out.
   pub num_jumps: u8,
 
-  /// Maximum jumps left. Set to 0 for infinite jumps.
+  /// Maximum jumps before touching the ground again. Set to 0 for infinite
+  /// jumps.
   pub max_jumps: u8,
+
+  /// Seconds after walking off a ledge that the first jump is still allowed.
+  pub coyote_time: f32,
+
+  /// Seconds that a jump press is remembered for, so pressing jump just
+  /// before landing still jumps.
+  pub jump_buffer: f32,
+
+  /// Upwards velocity is multiplied by this when jump is released
+  /// early, for variable jump height. Set to 1 to disable.
+  pub release_damping: f32,
+
+  /// Seconds since the body was last grounded. Managed by the input system.
+  pub time_since_grounded: f32,
+
+  /// Seconds since jump was pressed, or None if there's no buffered press.
+  /// Managed by the input system.
+  pub time_since_jump_pressed: Option<f32>,
+
+  /// True while jump is held after a jump. Managed by the input
+  /// system.
+  pub jump_held: bool,
 }
 impl Component for CompJumpController {
   fn get_entity_id(&self) -> EntityID { self.entity_id }
 }
+
+impl CompJumpController {
+  /// Create a jump controller with 0.1s of coyote time and jump buffer, and
+  /// jumps cut in half when jump is released early.
+  pub fn new(entity_id: EntityID, action_jump: &str,
+             jump_speed: f32, max_jumps: u8) -> CompJumpController {
+    CompJumpController {
+      entity_id: entity_id,
+      action_jump: action_jump.to_owned(),
+      action_drop: None,
+      jump_speed: jump_speed,
+      num_jumps: 0,
+      max_jumps: max_jumps,
+      coyote_time: 0.1,
+      jump_buffer: 0.1,
+      release_damping: 0.5,
+      time_since_grounded: 0.0,
+      time_since_jump_pressed: None,
+      jump_held: false,
+    }
+  }
+}
+
+/// Controller for top down movement in 4 or 8 directions, with an optional
+/// dash. Sets the body's velocity directly, accelerating towards the held
+/// direction and decelerating to a stop when nothing is held.
+/// Dependencies:
+/// CompBody (max_speed should allow for dash_speed)
+pub struct CompTopDownController {
+  pub entity_id: EntityID,
+
+  /// Names of the input map actions to move in each direction
+  pub action_up: String,
+  pub action_down: String,
+  pub action_left: String,
+  pub action_right: String,
+
+  /// Allow diagonal movement. Diagonals are normalized, so they're no faster
+  /// than straight movement. When false, the most recently pressed
+  /// direction wins.
+  pub eight_way: bool,
+
+  /// Top movement speed.
+  pub speed: f32,
+
+  /// Change in velocity per second when moving, and when stopping.
+  pub acceleration: f32,
+  pub deceleration: f32,
+
+  /// Action to dash, if any. Dashes go in the held direction, or the facing
+  /// direction if nothing is held.
+  pub action_dash: Option<String>,
+
+  /// Speed and duration in seconds of a dash.
+  pub dash_speed: f32,
+  pub dash_time: f32,
+
+  /// Seconds after a dash starts before the next dash.
+  pub dash_cooldown: f32,
+
+  /// Seconds left of the current dash. Managed by the input system.
+  pub dash_time_left: f32,
+
+  /// Seconds until the next dash is allowed. Managed by the input system.
+  pub dash_cooldown_left: f32,
+
+  /// Direction of the last movement, normalized. Managed by the input
+  /// system.
+  pub facing: Vec2f32,
+}
+impl Component for CompTopDownController {
+  fn get_entity_id(&self) -> EntityID { self.entity_id }
+}
+
+impl CompTopDownController {
+  /// Create an 8 directional controller which reaches full speed or stops in
+  /// a tenth of a second, facing down, with no dash.
+  pub fn new(entity_id: EntityID, action_up: &str, action_down: &str,
+             action_left: &str, action_right: &str, speed: f32) -> CompTopDownController {
+    CompTopDownController {
+      entity_id: entity_id,
+      action_up: action_up.to_owned(),
+      action_down: action_down.to_owned(),
+      action_left: action_left.to_owned(),
+      action_right: action_right.to_owned(),
+      eight_way: true,
+      speed: speed,
+      acceleration: speed * 10.0,
+      deceleration: speed * 10.0,
+      action_dash: None,
+      dash_speed: 0.0,
+      dash_time: 0.0,
+      dash_cooldown: 0.0,
+      dash_time_left: 0.0,
+      dash_cooldown_left: 0.0,
+      facing: Vec2f32(0.0, 1.0),
+    }
+  }
+}
@@ -1,5 +1,6 @@
 use entity::{EntityID, Component};
 use common::vec::Vec2f32;
+use std::f32;
 
 /// Position component for entity. Defines a 2D view position and size.
 #[derive(Clone)]
@@ -12,11 +13,25 @@ impl Component for CompAABB {
   fn get_entity_id(&self) -> EntityID { self.entity_id }
 }
 
+/// How a body is moved by the physics system.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BodyKind {
+  /// Never moves. Skipped by integration, and never pushed by collisions.
+  Static,
+  /// Moves only by its velocity, ignoring forces. Pushes dynamic bodies out
+  /// of the way without being pushed back, and carries dynamic bodies
+  /// standing on top of it (moving platforms).
+  Kinematic,
+  /// Responds to forces and collisions.
+  Dynamic,
+}
+
 /// Entity has a 'body' which responds to the environment
 /// Dependencies:
 /// CompAABB
 pub struct CompBody {
   pub entity_id: EntityID,
+  pub kind: BodyKind,
   pub vel: Vec2f32,
   pub acc: Vec2f32,
 
@@ -24,21 +39,78 @@ pub struct CompBody {
   /// every frame in order to get a constant acceleration.
   pub force: Vec2f32,
 
-  /// Used when applying forces.
+  /// Used when applying forces and impulses, and when two dynamic bodies
+  /// collide.
   pub mass: f32,
 
+  /// Bounciness, from 0 (no bounce) to 1 (perfectly elastic). When two
+  /// bodies collide, the larger of the two is used.
+  pub restitution: f32,
+
+  /// Coulomb friction coefficient. When two bodies collide, the geometric
+  /// mean of the two is used.
+  pub friction: f32,
+
   /// Maximum speed cap on body. 
   pub max_speed: f32,
+
+  /// Set by the physics system when the body was pushed upwards out of
+  /// something solid during the last update, i.e. it's standing on the
+  /// ground.
+  pub grounded: bool,
+
+  /// While set, the body falls through one way tiles. Set by the jump
+  /// controller's drop action.
+  pub drop_through: bool,
 }
 impl Component for CompBody {
   fn get_entity_id(&self) -> EntityID { self.entity_id }
 }
 
 impl CompBody {
+  /// Create a dynamic body at rest, with a mass of 1, no bounce or friction
+  /// and no speed cap.
+  pub fn new(entity_id: EntityID) -> CompBody {
+    CompBody {
+      entity_id: entity_id,
+      kind: BodyKind::Dynamic,
+      vel: Vec2f32(0.0, 0.0),
+      acc: Vec2f32(0.0, 0.0),
+      force: Vec2f32(0.0, 0.0),
+      mass: 1.0,
+      restitution: 0.0,
+      friction: 0.0,
+      max_speed: f32::INFINITY,
+      grounded: false,
+      drop_through: false,
+    }
+  }
+
+  /// Apply a force for this frame. The acceleration from this is force / mass,
+  /// so heavier bodies accelerate more slowly. For a mass independent
+  /// acceleration such as gravity, scale the force by the body's mass.
   pub fn apply_force(&mut self, force: Vec2f32) {
-    self.force.0 += self.mass * force.0;
-    self.force.1 += self.mass * force.1;
+    self.force.0 += force.0;
+    self.force.1 += force.1;
+  }
+
+  /// Apply an instantaneous change in momentum, for things like explosions
+  /// and knockback. Only affects dynamic bodies.
+  pub fn apply_impulse(&mut self, impulse: Vec2f32) {
+    self.vel += impulse * self.inv_mass();
   }
+
+  /// Inverse mass of the body, or 0 for bodies which can't be moved by
+  /// forces, impulses or collisions: static and kinematic bodies, and bodies
+  /// whose mass isn't a positive number.
+  pub fn inv_mass(&self) -> f32 {
+    if self.kind == BodyKind::Dynamic && self.mass > 0.0 && self.mass.is_finite() {
+      1.0 / self.mass
+    } else {
+      0.0
+    }
+  }
+
   pub fn clear_force(&mut self) {
     self.force.0 = 0.0;
     self.force.1 = 0.0;
@@ -1,7 +1,13 @@
-use common::color::RGBf32;
+use common::color::{RGBf32, RGBAf32};
+use common::vec::Vec2f32;
 use entity::{EntityID, Component};
+use renderer::atlas::AtlasRegion;
+use renderer::draw_list::RENDER_LAYER_SPRITES;
+use renderer::text::{BitmapFont, TextStyle};
+use std::rc::Rc;
 
-/// Draws a rectangle at this entity's position over everything else. 50% opacity.
+/// Draws a rectangle at this entity's position over everything else, on
+/// RENDER_LAYER_DEBUG. 50% opacity.
 /// Dependencies: 
 /// ComponentAABB
 #[derive(Clone)]
@@ -13,3 +19,94 @@ impl Component for CompDebugDraw {
   fn get_entity_id(&self) -> EntityID { self.entity_id }
 }
 
+
+/// Draws a region of the texture atlas at this entity's position.
+/// Dependencies:
+/// ComponentAABB
+#[derive(Clone)]
+pub struct CompSprite {
+  pub entity_id: EntityID,
+
+  /// Region of the atlas to draw.
+  pub region: AtlasRegion,
+
+  /// Position relative to the entity's AABB, and size in world units.
+  pub offset: Vec2f32,
+  pub size: Vec2f32,
+
+  /// Mirror the sprite horizontally / vertically.
+  pub flip_x: bool,
+  pub flip_y: bool,
+
+  /// Color to multiply the sprite by. White draws the sprite as is.
+  pub tint: RGBAf32,
+
+  /// Clockwise rotation in radians, around the centre of the sprite.
+  pub rotation: f32,
+
+  /// Render layer, e.g. RENDER_LAYER_SPRITES or RENDER_LAYER_FOREGROUND, and
+  /// order within the layer. Higher layers and z are drawn on top.
+  pub layer: i32,
+  pub z: i32,
+
+  /// Draw at the AABB's position in screen pixels, ignoring the camera. For
+  /// HUD elements on RENDER_LAYER_UI.
+  pub screen_space: bool,
+}
+impl Component for CompSprite {
+  fn get_entity_id(&self) -> EntityID { self.entity_id }
+}
+
+impl CompSprite {
+  /// Create a sprite drawing a region at its size in pixels, at the
+  /// entity's position, untinted and unrotated, on RENDER_LAYER_SPRITES.
+  pub fn new(entity_id: EntityID, region: AtlasRegion) -> CompSprite {
+    CompSprite {
+      entity_id: entity_id,
+      region: region,
+      offset: Vec2f32(0.0, 0.0),
+      size: Vec2f32(region.w as f32, region.h as f32),
+      flip_x: false,
+      flip_y: false,
+      tint: RGBAf32::new(1.0, 1.0, 1.0, 1.0),
+      rotation: 0.0,
+      layer: RENDER_LAYER_SPRITES,
+      z: 0,
+      screen_space: false,
+    }
+  }
+}
+
+
+/// Draws a string at this entity's position, in world space or, with
+/// style.screen_space, in screen pixels for HUD text.
+/// Dependencies:
+/// ComponentAABB
+#[derive(Clone)]
+pub struct CompText {
+  pub entity_id: EntityID,
+
+  /// Font to draw with. Its glyphs must be in the renderer's atlas.
+  pub font: Rc<BitmapFont>,
+  pub text: String,
+
+  /// Position of the top left of the text relative to the entity's AABB.
+  pub offset: Vec2f32,
+  pub style: TextStyle,
+}
+impl Component for CompText {
+  fn get_entity_id(&self) -> EntityID { self.entity_id }
+}
+
+impl CompText {
+  /// Create text at the entity's position, in the default TextStyle.
+  pub fn new(entity_id: EntityID, font: Rc<BitmapFont>, text: &str) -> CompText {
+    CompText {
+      entity_id: entity_id,
+      font: font,
+      text: text.to_owned(),
+      offset: Vec2f32(0.0, 0.0),
+      style: TextStyle::new(),
+    }
+  }
+}
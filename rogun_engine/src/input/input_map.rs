@@ -0,0 +1,227 @@
+use glium::glutin::{VirtualKeyCode, MouseButton};
+use input::key_names::*;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// A physical input which can be bound to an action.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Binding {
+  Key(VirtualKeyCode),
+  Mouse(MouseButton),
+}
+
+impl Binding {
+  /// Name of the binding in config files, e.g. "key:Space" or "mouse:Left".
+  pub fn to_config_string(&self) -> String {
+    match *self {
+      Binding::Key(k) => format!("key:{}", key_name(k)),
+      Binding::Mouse(b) => format!("mouse:{}", mouse_button_name(b)),
+    }
+  }
+
+  /// Parse a binding from to_config_string(). Returns None if it's not a
+  /// valid binding.
+  pub fn from_config_str(s: &str) -> Option<Binding> {
+    let s = s.trim();
+    if s.starts_with("key:") {
+      return key_from_name(&s[4..]).map(Binding::Key);
+    }
+    if s.starts_with("mouse:") {
+      return mouse_button_from_name(&s[6..]).map(Binding::Mouse);
+    }
+    return None;
+  }
+}
+
+/// A named action and the inputs bound to it.
+struct Action {
+  name: String,
+  bindings: Vec<Binding>,
+}
+
+/// A named axis, going from -1 when a negative binding is held to 1 when a
+/// positive binding is held.
+struct Axis {
+  name: String,
+  negative: Vec<Binding>,
+  positive: Vec<Binding>,
+}
+
+/// Maps named actions ("move_left", "jump", "fire") and axes to one or more
+/// keys or mouse buttons. Controllers refer to actions rather than keys, so
+/// controls can be remapped in one place.
+pub struct InputMap {
+  actions: Vec<Action>,
+  axes: Vec<Axis>,
+}
+
+/// Add a binding to a list, if it's not already in it.
+fn add_binding(list: &mut Vec<Binding>, binding: Binding) {
+  if !list.contains(&binding) {
+    list.push(binding);
+  }
+}
+
+/// Parse a comma separated list of bindings.
+fn parse_bindings(s: &str) -> Option<Vec<Binding>> {
+  let mut bindings = Vec::new();
+  for b in s.split(',') {
+    if b.trim().is_empty() { continue; }
+    let binding = Binding::from_config_str(b);
+    if binding.is_none() { return None; }
+    bindings.push(binding.unwrap());
+  }
+  Some(bindings)
+}
+
+/// Format a list of bindings as a comma separated list.
+fn format_bindings(bindings: &[Binding]) -> String {
+  bindings.iter().map(|b| b.to_config_string()).collect::<Vec<_>>().join(", ")
+}
+
+impl InputMap {
+  pub fn new() -> InputMap {
+    InputMap { actions: Vec::new(), axes: Vec::new() }
+  }
+
+  fn get_action_mut(&mut self, action: &str) -> &mut Action {
+    let ix = self.actions.iter().position(|a| a.name == action);
+    if ix.is_none() {
+      self.actions.push(Action { name: action.to_owned(), bindings: Vec::new() });
+      return self.actions.last_mut().unwrap();
+    }
+    &mut self.actions[ix.unwrap()]
+  }
+
+  fn get_axis_mut(&mut self, axis: &str) -> &mut Axis {
+    let ix = self.axes.iter().position(|a| a.name == axis);
+    if ix.is_none() {
+      self.axes.push(Axis { name: axis.to_owned(), negative: Vec::new(), positive: Vec::new() });
+      return self.axes.last_mut().unwrap();
+    }
+    &mut self.axes[ix.unwrap()]
+  }
+
+  /// Bind an input to an action, creating the action if it doesn't exist.
+  /// An action can have any number of bindings.
+  pub fn bind(&mut self, action: &str, binding: Binding) {
+    add_binding(&mut self.get_action_mut(action).bindings, binding);
+  }
+
+  /// Remove a binding from an action.
+  pub fn unbind(&mut self, action: &str, binding: Binding) {
+    self.get_action_mut(action).bindings.retain(|b| *b != binding);
+  }
+
+  /// Replace all of an action's bindings with a single binding.
+  pub fn rebind(&mut self, action: &str, binding: Binding) {
+    let a = self.get_action_mut(action);
+    a.bindings.clear();
+    a.bindings.push(binding);
+  }
+
+  /// Get the bindings of an action. Empty if the action doesn't exist.
+  pub fn get_bindings(&self, action: &str) -> &[Binding] {
+    for a in &self.actions {
+      if a.name == action {
+        return &a.bindings;
+      }
+    }
+    return &[];
+  }
+
+  /// List the names of all the actions.
+  pub fn action_names(&self) -> Vec<&str> {
+    self.actions.iter().map(|a| &a.name[..]).collect()
+  }
+
+  /// Bind a pair of inputs to an axis, creating the axis if it doesn't exist.
+  pub fn bind_axis(&mut self, axis: &str, negative: Binding, positive: Binding) {
+    let a = self.get_axis_mut(axis);
+    add_binding(&mut a.negative, negative);
+    add_binding(&mut a.positive, positive);
+  }
+
+  /// Get the (negative, positive) bindings of an axis. Empty if the axis
+  /// doesn't exist.
+  pub fn get_axis_bindings(&self, axis: &str) -> (&[Binding], &[Binding]) {
+    for a in &self.axes {
+      if a.name == axis {
+        return (&a.negative, &a.positive);
+      }
+    }
+    return (&[], &[]);
+  }
+
+  /// Write the bindings in the config file format, one action or axis per
+  /// line:
+  /// action jump = key:Space, key:W
+  /// axis move_x = key:A, key:Left / key:D, key:Right
+  pub fn to_config_string(&self) -> String {
+    let mut s = String::new();
+    for a in &self.actions {
+      s.push_str(&format!("action {} = {}\n", a.name, format_bindings(&a.bindings)));
+    }
+    for a in &self.axes {
+      s.push_str(&format!("axis {} = {} / {}\n", a.name,
+                          format_bindings(&a.negative), format_bindings(&a.positive)));
+    }
+    s
+  }
+
+  /// Parse bindings from the config file format. Blank lines and lines
+  /// starting with '#' are ignored. Returns an error message with the line
+  /// number on failure.
+  pub fn from_config_str(config: &str) -> Result<InputMap, String> {
+    let mut map = InputMap::new();
+    for (ii, line) in config.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') { continue; }
+      let err = || format!("Invalid input binding on line {}: {}", ii + 1, line);
+
+      let eq = line.find('=');
+      if eq.is_none() { return Err(err()); }
+      let (lhs, rhs) = (line[..eq.unwrap()].trim(), &line[eq.unwrap() + 1..]);
+      let mut words = lhs.split_whitespace();
+      let (kind, name) = (words.next(), words.next());
+      if name.is_none() || words.next().is_some() { return Err(err()); }
+      let name = name.unwrap();
+
+      match kind.unwrap() {
+        "action" => {
+          let bindings = parse_bindings(rhs);
+          if bindings.is_none() { return Err(err()); }
+          let action = map.get_action_mut(name);
+          for b in bindings.unwrap() { add_binding(&mut action.bindings, b); }
+        }
+        "axis" => {
+          let mut sides = rhs.split('/');
+          let (neg, pos) = (sides.next(), sides.next());
+          if pos.is_none() || sides.next().is_some() { return Err(err()); }
+          let neg = parse_bindings(neg.unwrap());
+          let pos = parse_bindings(pos.unwrap());
+          if neg.is_none() || pos.is_none() { return Err(err()); }
+          let axis = map.get_axis_mut(name);
+          for b in neg.unwrap() { add_binding(&mut axis.negative, b); }
+          for b in pos.unwrap() { add_binding(&mut axis.positive, b); }
+        }
+        _ => return Err(err()),
+      }
+    }
+    Ok(map)
+  }
+
+  /// Save the bindings to a config file.
+  pub fn save(&self, path: &Path) -> io::Result<()> {
+    let mut f = File::create(path)?;
+    f.write_all(self.to_config_string().as_bytes())
+  }
+
+  /// Load bindings from a config file.
+  pub fn load(path: &Path) -> io::Result<InputMap> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    InputMap::from_config_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+}
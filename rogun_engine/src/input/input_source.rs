@@ -0,0 +1,87 @@
+use glium::glutin::{Event, ElementState, VirtualKeyCode, MouseButton, MouseScrollDelta, TouchPhase};
+use glium::backend::glutin_backend::GlutinFacade;
+
+/// A source of window input events for the InputSystem. Implemented for the
+/// glutin window, and by ScriptedInput for driving input without a window.
+pub trait InputSource {
+  /// Returns all the events since the last poll. Called once per frame.
+  fn poll_events(&mut self) -> Vec<Event>;
+}
+
+impl InputSource for GlutinFacade {
+  fn poll_events(&mut self) -> Vec<Event> {
+    GlutinFacade::poll_events(self).collect()
+  }
+}
+
+/// Input source which plays back a script of events, each tagged with the
+/// frame (number of polls) it happens on. Used to test input handling
+/// headlessly.
+pub struct ScriptedInput {
+  /// Scripted events and their frames, sorted by frame.
+  events: Vec<(u32, Event)>,
+
+  /// Current frame, incremented on every poll.
+  frame: u32,
+}
+
+impl ScriptedInput {
+  pub fn new() -> ScriptedInput {
+    ScriptedInput { events: Vec::new(), frame: 0 }
+  }
+
+  /// Frame that will be returned by the next poll.
+  pub fn get_frame(&self) -> u32 { self.frame }
+
+  /// Returns true once every scripted event has been polled.
+  pub fn is_finished(&self) -> bool {
+    self.events.iter().all(|e| e.0 < self.frame)
+  }
+
+  /// Add an event on the given frame. Events on the same frame are returned
+  /// in the order they're added.
+  pub fn push_event(&mut self, frame: u32, event: Event) {
+    let ix = self.events.iter().position(|e| e.0 > frame).unwrap_or(self.events.len());
+    self.events.insert(ix, (frame, event));
+  }
+
+  pub fn key_press(&mut self, frame: u32, key_code: VirtualKeyCode) {
+    self.push_event(frame, Event::KeyboardInput(ElementState::Pressed, 0, Some(key_code)));
+  }
+
+  pub fn key_release(&mut self, frame: u32, key_code: VirtualKeyCode) {
+    self.push_event(frame, Event::KeyboardInput(ElementState::Released, 0, Some(key_code)));
+  }
+
+  /// Press a key on one frame and release it a number of frames later.
+  pub fn key_tap(&mut self, frame: u32, key_code: VirtualKeyCode, frames_held: u32) {
+    self.key_press(frame, key_code);
+    self.key_release(frame + frames_held, key_code);
+  }
+
+  pub fn mouse_press(&mut self, frame: u32, button: MouseButton) {
+    self.push_event(frame, Event::MouseInput(ElementState::Pressed, button));
+  }
+
+  pub fn mouse_release(&mut self, frame: u32, button: MouseButton) {
+    self.push_event(frame, Event::MouseInput(ElementState::Released, button));
+  }
+
+  /// Move the cursor to a position in window pixels.
+  pub fn mouse_move(&mut self, frame: u32, x: i32, y: i32) {
+    self.push_event(frame, Event::MouseMoved(x, y));
+  }
+
+  /// Scroll the mouse wheel by a number of lines. Positive y is scrolling up.
+  pub fn mouse_wheel(&mut self, frame: u32, x: f32, y: f32) {
+    self.push_event(frame, Event::MouseWheel(MouseScrollDelta::LineDelta(x, y), TouchPhase::Moved));
+  }
+}
+
+impl InputSource for ScriptedInput {
+  fn poll_events(&mut self) -> Vec<Event> {
+    let frame = self.frame;
+    self.frame += 1;
+    self.events.iter().filter(|e| e.0 == frame).map(|e| e.1.clone()).collect()
+  }
+}
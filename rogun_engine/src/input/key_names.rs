@@ -0,0 +1,79 @@
+use glium::glutin::{VirtualKeyCode, MouseButton};
+use glium::glutin::VirtualKeyCode as K;
+
+/// Every virtual key code, used to parse key names.
+const KEYS: [VirtualKeyCode; 150] = [
+  K::Key1, K::Key2, K::Key3, K::Key4, K::Key5, K::Key6, K::Key7, K::Key8,
+  K::Key9, K::Key0, K::A, K::B, K::C, K::D, K::E, K::F, K::G, K::H, K::I, K::J,
+  K::K, K::L, K::M, K::N, K::O, K::P, K::Q, K::R, K::S, K::T, K::U, K::V, K::W,
+  K::X, K::Y, K::Z, K::Escape, K::F1, K::F2, K::F3, K::F4, K::F5, K::F6, K::F7,
+  K::F8, K::F9, K::F10, K::F11, K::F12, K::F13, K::F14, K::F15, K::Snapshot,
+  K::Scroll, K::Pause, K::Insert, K::Home, K::Delete, K::End, K::PageDown,
+  K::PageUp, K::Left, K::Up, K::Right, K::Down, K::Back, K::Return, K::Space,
+  K::Compose, K::Numlock, K::Numpad0, K::Numpad1, K::Numpad2, K::Numpad3,
+  K::Numpad4, K::Numpad5, K::Numpad6, K::Numpad7, K::Numpad8, K::Numpad9,
+  K::AbntC1, K::AbntC2, K::Add, K::Apostrophe, K::Apps, K::At, K::Ax,
+  K::Backslash, K::Calculator, K::Capital, K::Colon, K::Comma, K::Convert,
+  K::Decimal, K::Divide, K::Equals, K::Grave, K::Kana, K::Kanji, K::LAlt,
+  K::LBracket, K::LControl, K::LMenu, K::LShift, K::LWin, K::Mail,
+  K::MediaSelect, K::MediaStop, K::Minus, K::Multiply, K::Mute, K::MyComputer,
+  K::NavigateForward, K::NavigateBackward, K::NextTrack, K::NoConvert,
+  K::NumpadComma, K::NumpadEnter, K::NumpadEquals, K::OEM102, K::Period,
+  K::PlayPause, K::Power, K::PrevTrack, K::RAlt, K::RBracket, K::RControl,
+  K::RMenu, K::RShift, K::RWin, K::Semicolon, K::Slash, K::Sleep, K::Stop,
+  K::Subtract, K::Sysrq, K::Tab, K::Underline, K::Unlabeled, K::VolumeDown,
+  K::VolumeUp, K::Wake, K::WebBack, K::WebFavorites, K::WebForward, K::WebHome,
+  K::WebRefresh, K::WebSearch, K::WebStop, K::Yen,
+];
+
+/// Get the name of a key, as used in input config files. This is the same as
+/// the key code's variant name, e.g. "A", "Space", "LShift".
+pub fn key_name(key: VirtualKeyCode) -> String {
+  format!("{:?}", key)
+}
+
+/// Parse a key name from key_name(). Returns None if it isn't a key.
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+  for k in KEYS.iter() {
+    if key_name(*k) == name {
+      return Some(*k);
+    }
+  }
+  return None;
+}
+
+/// Get a key's number, used to store keys compactly in input recordings.
+pub fn key_code_number(key: VirtualKeyCode) -> u8 {
+  key as u8
+}
+
+/// Get a key from key_code_number(). Returns None if it isn't a key.
+pub fn key_from_number(number: u8) -> Option<VirtualKeyCode> {
+  for k in KEYS.iter() {
+    if *k as u8 == number {
+      return Some(*k);
+    }
+  }
+  return None;
+}
+
+/// Get the name of a mouse button, as used in input config files. Either
+/// "Left", "Right", "Middle", or the button number for other buttons.
+pub fn mouse_button_name(button: MouseButton) -> String {
+  match button {
+    MouseButton::Left => "Left".to_owned(),
+    MouseButton::Right => "Right".to_owned(),
+    MouseButton::Middle => "Middle".to_owned(),
+    MouseButton::Other(n) => n.to_string(),
+  }
+}
+
+/// Parse a mouse button name from mouse_button_name().
+pub fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+  match name {
+    "Left" => Some(MouseButton::Left),
+    "Right" => Some(MouseButton::Right),
+    "Middle" => Some(MouseButton::Middle),
+    _ => name.parse::<u8>().ok().map(MouseButton::Other),
+  }
+}
@@ -1,10 +1,42 @@
 use state::GameState;
-use glium::glutin::{Event, ElementState, VirtualKeyCode};
-use glium::backend::glutin_backend::GlutinFacade;
+use glium::glutin::{Event, ElementState, VirtualKeyCode, MouseButton, MouseScrollDelta};
 use common::vec::Vec2f32;
+use common::rng::Rng;
+use std::f32;
+use time;
+
+/// Names of keys and mouse buttons, as used in input config files
+pub mod key_names;
+
+/// Mapping of named actions to keys and mouse buttons
+pub mod input_map;
+
+/// Sources of input events, the window or a script
+pub mod input_source;
+
+/// Recording and playback of input
+pub mod recording;
+
+/// Detection of sequences of actions, like combos
+pub mod sequence;
+
+use self::input_map::{InputMap, Binding};
+use self::input_source::InputSource;
+use self::recording::{InputRecording, FrameInput, ENGINE_VERSION};
+use self::sequence::{InputSequence, SequenceTracker};
+use std::mem;
+
+/// Pixels of smooth scrolling (from touchpads) counted as one line of the
+/// mouse wheel.
+const PIXELS_PER_WHEEL_LINE: f32 = 20.0;
+
+/// Shortest auto-repeat interval in seconds. Shorter intervals, including 0
+/// and negative ones, are clamped to this, so keys repeat at most every
+/// frame.
+const MIN_KEY_REPEAT_INTERVAL: f32 = 0.001;
 
 /// Struct representing a key currently pressed on the keyboard.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct KeyDown {
   pub key_code : VirtualKeyCode,
 
@@ -12,16 +44,123 @@ pub struct KeyDown {
   /// the InputSystem, all just_pressed values are set to false. So this should
   /// be true for 1 frame.
   pub just_pressed : bool,
+
+  /// True on the frame the key is pressed, and on every auto-repeat while
+  /// it's held. See InputSystem::set_key_repeat.
+  pub repeated : bool,
+
+  /// Seconds the key has been held for, 0 on the frame it's pressed.
+  pub hold_time : f32,
+
+  /// Frames the key has been held for, 0 on the frame it's pressed.
+  pub hold_frames : u32,
+}
+
+/// Struct representing a key released this frame.
+#[derive(Copy, Clone, PartialEq)]
+pub struct KeyUp {
+  pub key_code : VirtualKeyCode,
+
+  /// How long the key was held before it was released, in seconds and
+  /// frames.
+  pub hold_time : f32,
+  pub hold_frames : u32,
+}
+
+/// Struct representing a mouse button currently pressed.
+#[derive(Copy, Clone, PartialEq)]
+pub struct MouseButtonDown {
+  pub button : MouseButton,
+
+  /// True if this has just been pressed, see KeyDown::just_pressed.
+  pub just_pressed : bool,
+
+  /// Seconds and frames the button has been held for, see KeyDown.
+  pub hold_time : f32,
+  pub hold_frames : u32,
+}
+
+/// Struct representing a mouse button released this frame.
+#[derive(Copy, Clone, PartialEq)]
+pub struct MouseButtonUp {
+  pub button : MouseButton,
+
+  /// How long the button was held before it was released, see KeyUp.
+  pub hold_time : f32,
+  pub hold_frames : u32,
 }
 
 pub struct InputSystem {
   /// List of keys currently pressed. Maybe a bit slow?
   keys_down : Vec<KeyDown>,
+
+  /// List of keys released this frame.
+  keys_released : Vec<KeyUp>,
+
+  /// Auto-repeat delay and interval in seconds, or None if keys don't
+  /// repeat.
+  key_repeat : Option<(f32, f32)>,
+
+  /// List of mouse buttons currently pressed.
+  mouse_buttons_down : Vec<MouseButtonDown>,
+
+  /// List of mouse buttons released this frame.
+  mouse_buttons_released : Vec<MouseButtonUp>,
+
+  /// Cursor position in pixels, relative to the top left of the window.
+  mouse_pos : Vec2f32,
+
+  /// Mouse wheel movement this frame, in lines. Positive y is scrolling up.
+  wheel_delta : Vec2f32,
+
+  /// World position at the top left of the window, and window pixels per
+  /// world unit. Used to convert the cursor position to world coordinates.
+  view_origin : Vec2f32,
+  view_scale : f32,
+
+  /// Bindings of named actions and axes, used by the controllers.
+  pub input_map : InputMap,
+
+  /// Action to bind to the next key or mouse button pressed, if any.
+  rebind_action : Option<String>,
+
+  /// Registered action sequences and their progress.
+  sequences : Vec<SequenceTracker>,
+
+  /// True once the window has been closed.
+  quit_requested : bool,
+
+  /// New size of the window if it was resized this frame.
+  resized : Option<(u32, u32)>,
+
+  /// Recording being made of every frame's input, if any.
+  recording : Option<InputRecording>,
+
+  /// Recording being played back instead of polling for input, and the
+  /// index of the next frame to play.
+  playback : Option<(InputRecording, usize)>,
 }
 
 impl InputSystem {
   pub fn new() -> InputSystem { 
-    InputSystem { keys_down: Vec::with_capacity(6) }
+    InputSystem {
+      keys_down: Vec::with_capacity(6),
+      keys_released: Vec::with_capacity(6),
+      key_repeat: Some((0.5, 1.0 / 30.0)),
+      mouse_buttons_down: Vec::with_capacity(3),
+      mouse_buttons_released: Vec::with_capacity(3),
+      mouse_pos: Vec2f32(0.0, 0.0),
+      wheel_delta: Vec2f32(0.0, 0.0),
+      view_origin: Vec2f32(0.0, 0.0),
+      view_scale: 1.0,
+      input_map: InputMap::new(),
+      rebind_action: None,
+      sequences: Vec::new(),
+      quit_requested: false,
+      resized: None,
+      recording: None,
+      playback: None,
+    }
   }
 
   /// Checks if a key is down. Returns the 'KeyDown' struct if the key is down,
@@ -37,24 +176,373 @@ impl InputSystem {
     return None;
   }
 
-  /// Removes a key_down entry from the list of keys_down
-  fn remove_key_down(&mut self, key_code: VirtualKeyCode) {
+  /// Checks if a key was released this frame. Returns the 'KeyUp' struct if
+  /// it was, or None if not.
+  pub fn is_key_just_released(&self, key_code: VirtualKeyCode) -> Option<KeyUp> {
+    self.keys_released.iter().find(|k| k.key_code == key_code).cloned()
+  }
+
+  /// Checks if a key was pressed or auto-repeated this frame, for things like
+  /// scrolling through menus while a key is held.
+  pub fn is_key_repeated(&self, key_code: VirtualKeyCode) -> bool {
+    self.is_key_down(key_code).map_or(false, |k| k.repeated)
+  }
+
+  /// Set how held keys auto-repeat. Keys first repeat after being held for
+  /// delay seconds, then every interval seconds after that. Defaults to a
+  /// 0.5s delay, repeating 30 times a second. Negative delays count as 0,
+  /// and intervals are clamped to at least a millisecond.
+  pub fn set_key_repeat(&mut self, delay: f32, interval: f32) {
+    self.key_repeat = Some((delay.max(0.0), interval.max(MIN_KEY_REPEAT_INTERVAL)));
+  }
+
+  /// Stop keys auto-repeating, so KeyDown::repeated is only set when a key
+  /// is first pressed.
+  pub fn disable_key_repeat(&mut self) {
+    self.key_repeat = None;
+  }
+
+  /// Checks if a mouse button is down, like is_key_down.
+  pub fn is_mouse_button_down(&self, button: MouseButton) -> Option<MouseButtonDown> {
+    for b in &self.mouse_buttons_down {
+      if b.button == button {
+        return Some(*b);
+      }
+    }
+    return None;
+  }
+
+  /// Checks if a mouse button was released this frame, like
+  /// is_key_just_released.
+  pub fn is_mouse_button_just_released(&self, button: MouseButton) -> Option<MouseButtonUp> {
+    self.mouse_buttons_released.iter().find(|b| b.button == button).cloned()
+  }
+
+  /// Cursor position in pixels, relative to the top left of the window.
+  pub fn get_mouse_pos(&self) -> Vec2f32 {
+    self.mouse_pos
+  }
+
+  /// Cursor position in world coordinates, see set_view.
+  pub fn get_mouse_world_pos(&self) -> Vec2f32 {
+    Vec2f32(self.view_origin.0 + self.mouse_pos.0 / self.view_scale,
+            self.view_origin.1 + self.mouse_pos.1 / self.view_scale)
+  }
+
+  /// Mouse wheel movement this frame, in lines. Positive y is scrolling up,
+  /// positive x is scrolling right.
+  pub fn get_wheel_delta(&self) -> Vec2f32 {
+    self.wheel_delta
+  }
+
+  /// Set the view used to convert the cursor position to world coordinates.
+  /// origin is the world position shown at the top left of the window, and
+  /// scale is the number of window pixels per world unit.
+  pub fn set_view(&mut self, origin: Vec2f32, scale: f32) {
+    self.view_origin = origin;
+    self.view_scale = scale;
+  }
+
+  /// Checks if a binding is down. Returns Some(just_pressed) if it is, or
+  /// None if not.
+  fn is_binding_down(&self, binding: Binding) -> Option<bool> {
+    match binding {
+      Binding::Key(k) => self.is_key_down(k).map(|k| k.just_pressed),
+      Binding::Mouse(b) => self.is_mouse_button_down(b).map(|b| b.just_pressed),
+    }
+  }
+
+  /// Checks if any of an action's bindings are down. False if the action
+  /// doesn't exist.
+  pub fn is_action_down(&self, action: &str) -> bool {
+    self.input_map.get_bindings(action).iter().any(|b| self.is_binding_down(*b).is_some())
+  }
+
+  /// Checks if an action has just been pressed this frame. Pressing a second
+  /// binding while another is already held doesn't count.
+  pub fn is_action_just_pressed(&self, action: &str) -> bool {
+    let bindings = self.input_map.get_bindings(action);
+    bindings.iter().any(|b| self.is_binding_down(*b) == Some(true))
+      && !bindings.iter().any(|b| self.is_binding_down(*b) == Some(false))
+  }
+
+  /// Checks if an action was released this frame. Releasing one binding
+  /// while another is still held doesn't count.
+  pub fn is_action_just_released(&self, action: &str) -> bool {
+    let bindings = self.input_map.get_bindings(action);
+    !self.is_action_down(action) && bindings.iter().any(|b| match *b {
+      Binding::Key(k) => self.is_key_just_released(k).is_some(),
+      Binding::Mouse(m) => self.is_mouse_button_just_released(m).is_some(),
+    })
+  }
+
+  /// Seconds an action has been held for, the longest of its held bindings.
+  /// On the frame the action is released, this is how long it was held, for
+  /// things like charge shots. None if the action isn't held or released.
+  pub fn get_action_hold_time(&self, action: &str) -> Option<f32> {
+    let held = self.is_action_down(action);
+    let mut hold_time: Option<f32> = None;
+    for b in self.input_map.get_bindings(action) {
+      let t = match (*b, held) {
+        (Binding::Key(k), true) => self.is_key_down(k).map(|k| k.hold_time),
+        (Binding::Key(k), false) => self.is_key_just_released(k).map(|k| k.hold_time),
+        (Binding::Mouse(m), true) => self.is_mouse_button_down(m).map(|b| b.hold_time),
+        (Binding::Mouse(m), false) => self.is_mouse_button_just_released(m).map(|b| b.hold_time),
+      };
+      if t.is_some() && (hold_time.is_none() || t > hold_time) {
+        hold_time = t;
+      }
+    }
+    return hold_time;
+  }
+
+  /// Value of an axis, -1 if only negative bindings are held, 1 if only
+  /// positive bindings are held, otherwise 0.
+  pub fn get_axis(&self, axis: &str) -> f32 {
+    let (neg, pos) = self.input_map.get_axis_bindings(axis);
+    let neg = neg.iter().any(|b| self.is_binding_down(*b).is_some());
+    let pos = pos.iter().any(|b| self.is_binding_down(*b).is_some());
+    match (neg, pos) {
+      (true, false) => -1.0,
+      (false, true) => 1.0,
+      _ => 0.0,
+    }
+  }
+
+  /// Rebind an action to the next key or mouse button pressed, replacing its
+  /// existing bindings. The press that rebinds the action isn't seen by
+  /// anything else that frame.
+  pub fn rebind_next(&mut self, action: &str) {
+    self.rebind_action = Some(action.to_owned());
+  }
+
+  /// Returns the action waiting to be rebound by rebind_next, if any.
+  pub fn get_rebind_action(&self) -> Option<&str> {
+    self.rebind_action.as_ref().map(|a| &a[..])
+  }
+
+  /// If an action is waiting to be rebound, bind it to the given binding.
+  /// Returns true if the binding was used up.
+  fn try_rebind(&mut self, binding: Binding) -> bool {
+    if let Some(action) = self.rebind_action.take() {
+      self.input_map.rebind(&action, binding);
+      return true;
+    }
+    return false;
+  }
+
+  /// True once the window has been closed, and the game should quit.
+  pub fn is_quit_requested(&self) -> bool { self.quit_requested }
+
+  /// New size of the window in pixels, if it was resized this frame.
+  pub fn get_resize(&self) -> Option<(u32, u32)> { self.resized }
+
+  /// Register a sequence of actions. If a sequence with the same name is
+  /// already registered, it's replaced.
+  pub fn add_sequence(&mut self, sequence: InputSequence) {
+    self.remove_sequence(&sequence.name);
+    self.sequences.push(SequenceTracker::new(sequence));
+  }
+
+  /// Unregister a sequence of actions.
+  pub fn remove_sequence(&mut self, name: &str) {
+    self.sequences.retain(|s| s.sequence.name != name);
+  }
+
+  /// Checks if a sequence was completed this frame. False if there's no
+  /// sequence with that name.
+  pub fn is_sequence_triggered(&self, name: &str) -> bool {
+    self.sequences.iter().any(|s| s.sequence.name == name && s.is_triggered())
+  }
+
+  /// Start recording input. The game's RNG is reseeded with a new seed, which
+  /// is stored in the recording.
+  pub fn start_recording(&mut self, g_state: &mut GameState) {
+    let seed = time::precise_time_ns();
+    g_state.rng = Rng::new(seed);
+    self.recording = Some(InputRecording::new(seed));
+  }
+
+  /// Stop recording input, returning the recording if there was one.
+  pub fn stop_recording(&mut self) -> Option<InputRecording> {
+    self.recording.take()
+  }
+
+  pub fn is_recording(&self) -> bool { self.recording.is_some() }
+
+  /// Play back a recording. While playing, each frame's input comes from the
+  /// recording rather than the input source, and the game's RNG is reseeded
+  /// with the recording's seed. Returns an error if the recording was made
+  /// with a different engine version.
+  pub fn start_playback(&mut self, recording: InputRecording, g_state: &mut GameState) -> Result<(), String> {
+    if recording.engine_version != ENGINE_VERSION {
+      return Err(format!("Recording is from engine version {}, this is version {}",
+                         recording.engine_version, ENGINE_VERSION));
+    }
+    g_state.rng = Rng::new(recording.seed);
+    self.clear_input_state();
+    self.playback = Some((recording, 0));
+    Ok(())
+  }
+
+  /// Stop playing back a recording, returning to the input source.
+  pub fn stop_playback(&mut self) {
+    self.playback = None;
+  }
+
+  /// True while a recording is being played. Playback stops by itself after
+  /// the last frame.
+  pub fn is_playing_back(&self) -> bool { self.playback.is_some() }
+
+  /// Snapshot of this frame's input state. Hold times and releases aren't
+  /// stored, they're worked out again when playing back.
+  fn frame_input(&self, dt: f32) -> FrameInput {
+    FrameInput {
+      dt: dt,
+      keys_down: self.keys_down.iter().map(|k| (k.key_code, k.just_pressed)).collect(),
+      mouse_buttons_down: self.mouse_buttons_down.iter().map(|b| (b.button, b.just_pressed)).collect(),
+      mouse_pos: self.mouse_pos,
+      wheel_delta: self.wheel_delta,
+    }
+  }
+
+  /// Move the input state on to a recorded frame, pressing and releasing
+  /// keys and buttons to match it.
+  fn apply_frame_input(&mut self, f: &FrameInput) {
+    self.begin_frame(f.dt);
+
+    // Release anything not held in the frame, or pressed again in it
+    let released: Vec<VirtualKeyCode> = self.keys_down.iter()
+      .filter(|k| !f.keys_down.contains(&(k.key_code, false)))
+      .map(|k| k.key_code).collect();
+    for k in released { self.release_key(k); }
+    let released: Vec<MouseButton> = self.mouse_buttons_down.iter()
+      .filter(|b| !f.mouse_buttons_down.contains(&(b.button, false)))
+      .map(|b| b.button).collect();
+    for b in released { self.release_mouse_button(b); }
+
+    for &(k, _) in &f.keys_down { self.press_key(k); }
+    for &(b, _) in &f.mouse_buttons_down { self.press_mouse_button(b); }
+    self.mouse_pos = f.mouse_pos;
+    self.wheel_delta = f.wheel_delta;
+  }
+
+  /// Release every key and mouse button without reporting them as released.
+  fn clear_input_state(&mut self) {
+    self.keys_down.clear();
+    self.keys_released.clear();
+    self.mouse_buttons_down.clear();
+    self.mouse_buttons_released.clear();
+    self.wheel_delta = Vec2f32(0.0, 0.0);
+  }
+
+  /// Start a new frame of input. Clears the just pressed and released
+  /// state from the last frame, and advances hold times and auto-repeat.
+  fn begin_frame(&mut self, dt: f32) {
+    // Number of repeats a key held for t seconds has had
+    let repeat = self.key_repeat;
+    let repeats = |t: f32| match repeat {
+      Some((delay, interval)) if t >= delay => (((t - delay) / interval) as u32).saturating_add(1),
+      _ => 0,
+    };
+
+    for k in &mut self.keys_down {
+      k.just_pressed = false;
+      k.hold_time += dt;
+      k.hold_frames += 1;
+      k.repeated = repeats(k.hold_time) > repeats(k.hold_time - dt);
+    }
+    for b in &mut self.mouse_buttons_down {
+      b.just_pressed = false;
+      b.hold_time += dt;
+      b.hold_frames += 1;
+    }
+    self.keys_released.clear();
+    self.mouse_buttons_released.clear();
+    self.wheel_delta = Vec2f32(0.0, 0.0);
+  }
+
+  /// Add a key to keys_down, if it isn't already down.
+  fn press_key(&mut self, key_code: VirtualKeyCode) {
+    if self.is_key_down(key_code).is_some() { return; }
+    self.keys_down.push(KeyDown {
+      key_code: key_code,
+      just_pressed: true,
+      repeated: true,
+      hold_time: 0.0,
+      hold_frames: 0,
+    });
+  }
+
+  /// Removes a key_down entry from the list of keys_down, and adds it to
+  /// keys_released.
+  fn release_key(&mut self, key_code: VirtualKeyCode) {
     for ii in 0..self.keys_down.len() {
       if self.keys_down[ii].key_code == key_code {
-        self.keys_down.remove(ii);
+        let k = self.keys_down.remove(ii);
+        self.keys_released.push(KeyUp {
+          key_code: key_code,
+          hold_time: k.hold_time,
+          hold_frames: k.hold_frames,
+        });
         break;
       }
     }
   }
 
-  /// Updates keys_down and other input system state
-  fn update_input_state(&mut self, display: &GlutinFacade) {
-    // Loop over all current KeyDowns and set just_pressed to false.
-    for k in &mut self.keys_down {
-      k.just_pressed = false;
+  /// Add a button to mouse_buttons_down, if it isn't already down.
+  fn press_mouse_button(&mut self, button: MouseButton) {
+    if self.is_mouse_button_down(button).is_some() { return; }
+    self.mouse_buttons_down.push(MouseButtonDown {
+      button: button,
+      just_pressed: true,
+      hold_time: 0.0,
+      hold_frames: 0,
+    });
+  }
+
+  /// Move a button from mouse_buttons_down to mouse_buttons_released.
+  fn release_mouse_button(&mut self, button: MouseButton) {
+    for ii in 0..self.mouse_buttons_down.len() {
+      if self.mouse_buttons_down[ii].button == button {
+        let b = self.mouse_buttons_down.remove(ii);
+        self.mouse_buttons_released.push(MouseButtonUp {
+          button: button,
+          hold_time: b.hold_time,
+          hold_frames: b.hold_frames,
+        });
+        break;
+      }
     }
+  }
+
+  /// Handle events about the window rather than input. Returns true if the
+  /// event was one of these.
+  fn handle_window_event(&mut self, e: &Event) -> bool {
+    match *e {
+      Event::Closed => self.quit_requested = true,
+      Event::Resized(w, h) => self.resized = Some((w, h)),
+      Event::Focused(false) => {
+        // We won't hear about keys released while unfocused, so release
+        // everything now rather than leaving keys stuck down
+        if self.playback.is_none() {
+          let keys: Vec<VirtualKeyCode> = self.keys_down.iter().map(|k| k.key_code).collect();
+          for k in keys { self.release_key(k); }
+          let buttons: Vec<MouseButton> = self.mouse_buttons_down.iter().map(|b| b.button).collect();
+          for b in buttons { self.release_mouse_button(b); }
+        }
+      }
+      _ => return false,
+    }
+    return true;
+  }
+
+  /// Updates keys_down and other input system state
+  fn update_input_state<S: InputSource>(&mut self, source: &mut S, dt: f32) {
+    self.begin_frame(dt);
 
-    'Outer: for e in display.poll_events() {
+    'Outer: for e in source.poll_events() {
+      if self.handle_window_event(&e) { continue 'Outer; }
       match e {
         Event::KeyboardInput(state, _, key_code) => {
           // No idea why the key code would be none, not documented in glium
@@ -62,33 +550,92 @@ impl InputSystem {
           let key_code = key_code.unwrap();
           if state == ElementState::Pressed {
             // Add to list
-            if self.is_key_down(key_code).is_none() {
-              self.keys_down.push(KeyDown {
-                key_code: key_code,
-                just_pressed: true,
-              });
+            if self.is_key_down(key_code).is_none()
+              && !self.try_rebind(Binding::Key(key_code)) {
+              self.press_key(key_code);
             }
           }
           else if state == ElementState::Released {
             // Remove from list
-            self.remove_key_down(key_code);
+            self.release_key(key_code);
+          }
+        }
+        Event::MouseInput(state, button) => {
+          if state == ElementState::Pressed {
+            if self.is_mouse_button_down(button).is_none()
+              && !self.try_rebind(Binding::Mouse(button)) {
+                self.press_mouse_button(button);
+              }
+          }
+          else if state == ElementState::Released {
+            self.release_mouse_button(button);
           }
         }
+        Event::MouseMoved(x, y) => {
+          self.mouse_pos = Vec2f32(x as f32, y as f32);
+        }
+        Event::MouseWheel(delta, _) => {
+          let delta = match delta {
+            MouseScrollDelta::LineDelta(x, y) => Vec2f32(x, y),
+            MouseScrollDelta::PixelDelta(x, y) =>
+              Vec2f32(x / PIXELS_PER_WHEEL_LINE, y / PIXELS_PER_WHEEL_LINE),
+          };
+          self.wheel_delta = self.wheel_delta + delta;
+        }
         _ => continue 'Outer,
       }
     }
   }
 
-  pub fn process_input(&mut self, g_state: &mut GameState, display: &GlutinFacade) {
-    // Process new keyboard input
-    self.update_input_state(display);
+  /// Process input for this frame, and apply controllers to their bodies.
+  /// Events are polled from the source, normally the window. dt param is
+  /// update delta in seconds, used for jump timing.
+  /// Returns the delta the rest of the frame should use. This is dt, unless a
+  /// recording is playing back, in which case it's the recorded delta.
+  pub fn process_input<S: InputSource>(&mut self, g_state: &mut GameState, source: &mut S, dt: f32) -> f32 {
+    let mut dt = dt;
+    self.resized = None;
+    let frame = self.playback.as_ref().map(|p| p.0.frames.get(p.1).cloned());
+    match frame {
+      Some(Some(f)) => {
+        // Still poll the source for window events, but ignore input
+        for e in source.poll_events() {
+          self.handle_window_event(&e);
+        }
+        self.apply_frame_input(&f);
+        dt = f.dt;
+        self.playback.as_mut().unwrap().1 += 1;
+      }
+      Some(None) => {
+        // Finished playing back, release everything the recording held
+        self.playback = None;
+        self.clear_input_state();
+        self.update_input_state(source, dt);
+      }
+      None => {
+        // Process new input events
+        self.update_input_state(source, dt);
+      }
+    }
+    if self.recording.is_some() {
+      let f = self.frame_input(dt);
+      self.recording.as_mut().unwrap().frames.push(f);
+    }
+
+    // Advance sequences. Taken out of self while updating, as they need to
+    // query the rest of the input state.
+    let mut sequences = mem::replace(&mut self.sequences, Vec::new());
+    for s in &mut sequences {
+      s.update(self, dt);
+    }
+    self.sequences = sequences;
 
-    // Check l/r controller keys (have they been pressed?
+    // Check l/r controller actions (have they been pressed?
     for c in &g_state.comp_lr_controller {
-      let key_l_down = self.is_key_down(c.key_l);
-      let key_r_down = self.is_key_down(c.key_r);
-      // Check if key_l is down
-      if key_r_down.is_some() && key_l_down.is_none() {
+      let l_down = self.is_action_down(&c.action_l);
+      let r_down = self.is_action_down(&c.action_r);
+      // Check if action_r is down
+      if r_down && !l_down {
         // Find entity body 
         let body = g_state.comp_body.get_component_mut(c.entity_id);
         if body.is_none() { continue; }
@@ -97,7 +644,7 @@ impl InputSystem {
         // acceleration
         body.apply_force(Vec2f32(c.force, 0.0));
       }
-      else if key_l_down.is_some() && key_r_down.is_none() {
+      else if l_down && !r_down {
         // Find entity body 
         let body = g_state.comp_body.get_component_mut(c.entity_id);
         if body.is_none() { continue; }
@@ -107,5 +654,114 @@ impl InputSystem {
         body.apply_force(Vec2f32(-c.force, 0.0));
       }
     }
+
+    // Jump controllers
+    for c in &mut g_state.comp_jump_controller {
+      let body = g_state.comp_body.get_component_mut(c.entity_id);
+      if body.is_none() { continue; }
+      let body = body.unwrap();
+
+      // Hold the drop action to fall through one way platforms
+      body.drop_through = c.action_drop.as_ref().map_or(false, |a| self.is_action_down(a));
+
+      if body.grounded {
+        c.time_since_grounded = 0.0;
+        c.num_jumps = 0;
+      }
+      else {
+        c.time_since_grounded += dt;
+      }
+
+      // Buffer jump presses for a short time
+      let jump_down = self.is_action_down(&c.action_jump);
+      if self.is_action_just_pressed(&c.action_jump) {
+        c.time_since_jump_pressed = Some(0.0);
+      }
+      else if let Some(t) = c.time_since_jump_pressed {
+        c.time_since_jump_pressed = if t + dt > c.jump_buffer { None } else { Some(t + dt) };
+      }
+
+      // Walking off a ledge uses up the ground jump once coyote time is over
+      let on_ground = c.time_since_grounded <= c.coyote_time;
+      if !on_ground && c.num_jumps == 0 {
+        c.num_jumps = 1;
+      }
+
+      if c.time_since_jump_pressed.is_some()
+        && (on_ground || c.max_jumps == 0 || c.num_jumps < c.max_jumps) {
+          body.vel.1 = -c.jump_speed;
+          c.num_jumps = c.num_jumps.saturating_add(1);
+          c.time_since_jump_pressed = None;
+          // No more coyote time until we touch the ground again
+          c.time_since_grounded = f32::INFINITY;
+          c.jump_held = true;
+        }
+
+      // Variable jump height, cut the jump short if the key is released
+      if c.jump_held && !jump_down {
+        c.jump_held = false;
+        if body.vel.1 < 0.0 {
+          body.vel.1 *= c.release_damping;
+        }
+      }
+    }
+
+    // Top down controllers
+    for c in &mut g_state.comp_top_down_controller {
+      let body = g_state.comp_body.get_component_mut(c.entity_id);
+      if body.is_none() { continue; }
+      let body = body.unwrap();
+
+      // Direction held on an axis, and how long it's been held. Holding both
+      // directions cancels out.
+      let axis = |neg: &str, pos: &str| {
+        let neg = if self.is_action_down(neg) { self.get_action_hold_time(neg) } else { None };
+        let pos = if self.is_action_down(pos) { self.get_action_hold_time(pos) } else { None };
+        match (neg, pos) {
+          (Some(t), None) => Some((-1.0, t)),
+          (None, Some(t)) => Some((1.0, t)),
+          _ => None,
+        }
+      };
+      let x = axis(&c.action_left, &c.action_right);
+      let y = axis(&c.action_up, &c.action_down);
+      let mut dir = Vec2f32(x.map_or(0.0, |x| x.0), y.map_or(0.0, |y| y.0));
+      if !c.eight_way && x.is_some() && y.is_some() {
+        // Keep the most recently pressed direction
+        if x.unwrap().1 <= y.unwrap().1 { dir.1 = 0.0; } else { dir.0 = 0.0; }
+      }
+      dir.nor();
+      let moving = dir.len2() > 0.0;
+      if moving {
+        c.facing = dir;
+      }
+
+      c.dash_cooldown_left = (c.dash_cooldown_left - dt).max(0.0);
+      let dash_pressed = c.action_dash.as_ref().map_or(false, |a| self.is_action_just_pressed(a));
+      if dash_pressed && c.dash_cooldown_left <= 0.0 {
+        body.vel = c.facing * c.dash_speed;
+        c.dash_time_left = c.dash_time;
+        c.dash_cooldown_left = c.dash_cooldown;
+      }
+      // Keep the dash velocity until the dash is over
+      if c.dash_time_left > 0.0 {
+        c.dash_time_left -= dt;
+        continue;
+      }
+
+      // Accelerate towards the target velocity
+      let target = dir * c.speed;
+      let step = if moving { c.acceleration } else { c.deceleration } * dt;
+      let mut diff = target - body.vel;
+      if diff.len() <= step {
+        body.vel = target;
+      }
+      else {
+        diff.nor().scale(step);
+        body.vel += diff;
+      }
+    }
+
+    dt
   }
 }
@@ -0,0 +1,217 @@
+use glium::glutin::{VirtualKeyCode, MouseButton};
+use common::vec::Vec2f32;
+use input::key_names::*;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Version of the engine, stored in recordings. Recordings made with a
+/// different version may not play back the same.
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Start of every recording file, followed by the format version.
+const MAGIC: &[u8] = b"RGNREC";
+const FORMAT_VERSION: u8 = 2;
+
+/// Flags at the start of each frame, saying what changed since the previous
+/// frame. Anything that didn't change isn't written.
+const KEYS_CHANGED: u8 = 1 << 0;
+const BUTTONS_CHANGED: u8 = 1 << 1;
+const MOUSE_MOVED: u8 = 1 << 2;
+const WHEEL_MOVED: u8 = 1 << 3;
+const DT_CHANGED: u8 = 1 << 4;
+
+/// Input state for a single frame. Releases and hold times follow from the
+/// keys held each frame, so they aren't stored.
+#[derive(Clone, PartialEq, Default)]
+pub struct FrameInput {
+  /// Update delta of the frame in seconds.
+  pub dt: f32,
+  /// Keys held, and whether they were just pressed.
+  pub keys_down: Vec<(VirtualKeyCode, bool)>,
+  /// Mouse buttons held, and whether they were just pressed.
+  pub mouse_buttons_down: Vec<(MouseButton, bool)>,
+  pub mouse_pos: Vec2f32,
+  pub wheel_delta: Vec2f32,
+}
+
+/// Recorded input for a run of the game, for reproducing bugs. Along with the
+/// input of every frame, this stores the seed of the game's RNG and the
+/// engine version it was recorded with.
+pub struct InputRecording {
+  pub engine_version: String,
+  pub seed: u64,
+  pub frames: Vec<FrameInput>,
+}
+
+fn write_u8(out: &mut Vec<u8>, v: u8) { out.push(v); }
+fn write_u32(out: &mut Vec<u8>, v: u32) { out.extend_from_slice(&v.to_le_bytes()); }
+fn write_u64(out: &mut Vec<u8>, v: u64) { out.extend_from_slice(&v.to_le_bytes()); }
+fn write_f32(out: &mut Vec<u8>, v: f32) { write_u32(out, v.to_bits()); }
+
+fn write_button(out: &mut Vec<u8>, button: MouseButton) {
+  match button {
+    MouseButton::Left => write_u8(out, 0),
+    MouseButton::Right => write_u8(out, 1),
+    MouseButton::Middle => write_u8(out, 2),
+    MouseButton::Other(n) => { write_u8(out, 3); write_u8(out, n); }
+  }
+}
+
+/// Reads values from a recording, returning None if it runs out of data.
+struct Reader<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+    if self.pos + n > self.data.len() { return None; }
+    self.pos += n;
+    Some(&self.data[self.pos - n..self.pos])
+  }
+
+  fn u8(&mut self) -> Option<u8> { self.bytes(1).map(|b| b[0]) }
+
+  fn u32(&mut self) -> Option<u32> {
+    self.bytes(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+  }
+
+  fn u64(&mut self) -> Option<u64> {
+    let lo = self.u32()? as u64;
+    let hi = self.u32()? as u64;
+    Some(lo | hi << 32)
+  }
+
+  fn f32(&mut self) -> Option<f32> { self.u32().map(f32::from_bits) }
+
+  fn vec2(&mut self) -> Option<Vec2f32> { Some(Vec2f32(self.f32()?, self.f32()?)) }
+
+  fn button(&mut self) -> Option<MouseButton> {
+    match self.u8()? {
+      0 => Some(MouseButton::Left),
+      1 => Some(MouseButton::Right),
+      2 => Some(MouseButton::Middle),
+      3 => self.u8().map(MouseButton::Other),
+      _ => None,
+    }
+  }
+}
+
+impl InputRecording {
+  /// Create an empty recording for the current engine version.
+  pub fn new(seed: u64) -> InputRecording {
+    InputRecording {
+      engine_version: ENGINE_VERSION.to_owned(),
+      seed: seed,
+      frames: Vec::new(),
+    }
+  }
+
+  /// Encode the recording. Each frame only stores what changed since the
+  /// previous frame, so frames where nothing happens take a single byte.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    write_u8(&mut out, FORMAT_VERSION);
+    let version = &self.engine_version.as_bytes()[..self.engine_version.len().min(255)];
+    write_u8(&mut out, version.len() as u8);
+    out.extend_from_slice(version);
+    write_u64(&mut out, self.seed);
+    write_u32(&mut out, self.frames.len() as u32);
+
+    let mut prev = FrameInput::default();
+    for f in &self.frames {
+      let mut flags = 0;
+      if f.keys_down != prev.keys_down { flags |= KEYS_CHANGED; }
+      if f.mouse_buttons_down != prev.mouse_buttons_down { flags |= BUTTONS_CHANGED; }
+      if f.mouse_pos != prev.mouse_pos { flags |= MOUSE_MOVED; }
+      if f.wheel_delta != Vec2f32(0.0, 0.0) { flags |= WHEEL_MOVED; }
+      if f.dt.to_bits() != prev.dt.to_bits() { flags |= DT_CHANGED; }
+      write_u8(&mut out, flags);
+
+      if flags & KEYS_CHANGED != 0 {
+        write_u8(&mut out, f.keys_down.len() as u8);
+        for &(k, just_pressed) in &f.keys_down {
+          write_u8(&mut out, key_code_number(k));
+          write_u8(&mut out, just_pressed as u8);
+        }
+      }
+      if flags & BUTTONS_CHANGED != 0 {
+        write_u8(&mut out, f.mouse_buttons_down.len() as u8);
+        for &(b, just_pressed) in &f.mouse_buttons_down {
+          write_button(&mut out, b);
+          write_u8(&mut out, just_pressed as u8);
+        }
+      }
+      if flags & MOUSE_MOVED != 0 {
+        write_f32(&mut out, f.mouse_pos.0);
+        write_f32(&mut out, f.mouse_pos.1);
+      }
+      if flags & WHEEL_MOVED != 0 {
+        write_f32(&mut out, f.wheel_delta.0);
+        write_f32(&mut out, f.wheel_delta.1);
+      }
+      if flags & DT_CHANGED != 0 {
+        write_f32(&mut out, f.dt);
+      }
+      prev = f.clone();
+    }
+    out
+  }
+
+  /// Decode a recording from to_bytes(). Returns None if the data isn't a
+  /// valid recording.
+  pub fn from_bytes(data: &[u8]) -> Option<InputRecording> {
+    let mut r = Reader { data: data, pos: 0 };
+    if r.bytes(MAGIC.len())? != MAGIC || r.u8()? != FORMAT_VERSION { return None; }
+    let version_len = r.u8()? as usize;
+    let engine_version = String::from_utf8(r.bytes(version_len)?.to_vec()).ok()?;
+    let seed = r.u64()?;
+    let num_frames = r.u32()?;
+
+    let mut frames = Vec::new();
+    let mut prev = FrameInput::default();
+    for _ in 0..num_frames {
+      let flags = r.u8()?;
+      let mut f = prev.clone();
+      f.wheel_delta = Vec2f32(0.0, 0.0);
+
+      if flags & KEYS_CHANGED != 0 {
+        f.keys_down.clear();
+        for _ in 0..r.u8()? {
+          let key_code = key_from_number(r.u8()?)?;
+          f.keys_down.push((key_code, r.u8()? != 0));
+        }
+      }
+      if flags & BUTTONS_CHANGED != 0 {
+        f.mouse_buttons_down.clear();
+        for _ in 0..r.u8()? {
+          let button = r.button()?;
+          f.mouse_buttons_down.push((button, r.u8()? != 0));
+        }
+      }
+      if flags & MOUSE_MOVED != 0 { f.mouse_pos = r.vec2()?; }
+      if flags & WHEEL_MOVED != 0 { f.wheel_delta = r.vec2()?; }
+      if flags & DT_CHANGED != 0 { f.dt = r.f32()?; }
+      frames.push(f.clone());
+      prev = f;
+    }
+
+    Some(InputRecording { engine_version: engine_version, seed: seed, frames: frames })
+  }
+
+  /// Save the recording to a file.
+  pub fn save(&self, path: &Path) -> io::Result<()> {
+    let mut f = File::create(path)?;
+    f.write_all(&self.to_bytes())
+  }
+
+  /// Load a recording from a file.
+  pub fn load(path: &Path) -> io::Result<InputRecording> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    InputRecording::from_bytes(&data).ok_or_else(
+      || io::Error::new(io::ErrorKind::InvalidData, "Invalid input recording"))
+  }
+}
@@ -0,0 +1,71 @@
+use input::InputSystem;
+
+/// A named sequence of actions pressed in order, like a double tap to dash
+/// or a fighting game motion input. Register with
+/// InputSystem::add_sequence, then check is_sequence_triggered.
+#[derive(Clone)]
+pub struct InputSequence {
+  pub name: String,
+
+  /// Actions to press in order, and the most seconds allowed since the
+  /// previous step. The first step's time is ignored.
+  pub steps: Vec<(String, f32)>,
+}
+
+impl InputSequence {
+  /// Create a sequence from (action, seconds since previous step) pairs.
+  pub fn new(name: &str, steps: &[(&str, f32)]) -> InputSequence {
+    InputSequence {
+      name: name.to_owned(),
+      steps: steps.iter().map(|s| (s.0.to_owned(), s.1)).collect(),
+    }
+  }
+}
+
+/// Progress through a registered sequence.
+pub struct SequenceTracker {
+  pub sequence: InputSequence,
+
+  /// Number of steps completed so far.
+  progress: usize,
+
+  /// Seconds since the last completed step.
+  time_since_step: f32,
+
+  /// True on the frame the last step is completed.
+  triggered: bool,
+}
+
+impl SequenceTracker {
+  pub fn new(sequence: InputSequence) -> SequenceTracker {
+    SequenceTracker { sequence: sequence, progress: 0, time_since_step: 0.0, triggered: false }
+  }
+
+  pub fn is_triggered(&self) -> bool { self.triggered }
+
+  /// Advance the sequence with this frame's input.
+  pub fn update(&mut self, input: &InputSystem, dt: f32) {
+    self.triggered = false;
+    if self.sequence.steps.is_empty() { return; }
+
+    // Too slow, start again
+    self.time_since_step += dt;
+    if self.progress > 0 && self.time_since_step > self.sequence.steps[self.progress].1 {
+      self.progress = 0;
+    }
+
+    if input.is_action_just_pressed(&self.sequence.steps[self.progress].0) {
+      self.progress += 1;
+      self.time_since_step = 0.0;
+      if self.progress == self.sequence.steps.len() {
+        self.triggered = true;
+        self.progress = 0;
+      }
+    }
+    else if self.progress > 0 && input.is_action_just_pressed(&self.sequence.steps[0].0) {
+      // Pressing the first step again restarts the sequence from here
+      self.progress = 1;
+      self.time_since_step = 0.0;
+    }
+  }
+}
@@ -49,15 +49,19 @@ pub fn init<'a>() -> Option<LibState<'a>> {
   }
   let (w, h) = win_size;
 
+  let mut g_state = state::GameState::new();
+  g_state.camera.set_viewport(w, h);
+
   Some(LibState {
     renderer: renderer::Renderer::new(&display, w, h),
     input_system: input::InputSystem::new(),
     physics_system: physics::PhysicsSystem::new(),
     display: display,
     engine_logger: logger::Logger::new(),
-    curr_g_state: Some(state::GameState::new()),
+    curr_g_state: Some(g_state),
 
     tile_bank: tile::TileBank::new(),
+    atlas: None,
 
     last_update_nanos: time::precise_time_ns(),
     frame_delta: 0,
@@ -66,7 +70,7 @@ pub fn init<'a>() -> Option<LibState<'a>> {
 
 /// State of the library. Holds state of the systems, like the game renderer.
 pub struct LibState<'a> {
-  pub renderer : renderer::Renderer<'a>,
+  pub renderer : renderer::Renderer,
   pub input_system: input::InputSystem,
   pub physics_system: physics::PhysicsSystem,
   pub display: glium::backend::glutin_backend::GlutinFacade,
@@ -76,6 +80,9 @@ pub struct LibState<'a> {
   /// Bank of tiles used when rendering.
   tile_bank: tile::TileBank,
 
+  /// Texture atlas sprites and tiles are drawn from, if any.
+  atlas: Option<renderer::atlas::TextureAtlas>,
+
   /// System time of the last update in nanoseconds. Performance counter time,
   /// NOT time since UNIX epoch! Don't use for current human time!
   last_update_nanos: u64,
@@ -95,16 +102,50 @@ impl<'a> LibState<'a> {
     self.tile_bank.register_tile(tile);
   }
 
-  pub fn update(&mut self) {
+  /// Set the texture atlas sprites and tiles are drawn from, uploading it to
+  /// the renderer.
+  pub fn set_atlas(&mut self, atlas: renderer::atlas::TextureAtlas) {
+    self.renderer.set_atlas(&self.display, &atlas);
+    self.atlas = Some(atlas);
+  }
+
+  /// Get the texture atlas, for looking up regions by name.
+  pub fn atlas(&self) -> Option<&renderer::atlas::TextureAtlas> {
+    self.atlas.as_ref()
+  }
+
+  /// Get the tile bank, for passing to physics queries like raycasts.
+  pub fn tile_bank(&self) -> &tile::TileBank {
+    &self.tile_bank
+  }
+
+  /// Update and render a frame. Returns false once the game should quit,
+  /// e.g. when the window is closed.
+  pub fn update(&mut self) -> bool {
     self.update_delta();
 
     if self.curr_g_state.is_some() {
-      // Process input
-      self.input_system.process_input(self.curr_g_state.as_mut().unwrap(), &self.display);
+      // Process input, remember to convert nano second delta into seconds.
+      // When playing back an input recording, this gives the recorded delta.
+      let dt = self.input_system.process_input(self.curr_g_state.as_mut().unwrap(), &mut self.display,
+                                               self.frame_delta as f32 / 1000000000.0);
+
+      if let Some((w, h)) = self.input_system.get_resize() {
+        self.renderer.resize(w, h);
+        self.curr_g_state.as_mut().unwrap().camera.set_viewport(w, h);
+      }
 
-      // Update physics, remember to convert nano second delta into seconds
+      // Update physics
       self.physics_system.update_physics(self.curr_g_state.as_mut().unwrap(), 
-                                         self.frame_delta as f32 / 1000000000.0);
+                                         &self.tile_bank, dt);
+
+      // Move the camera after physics, so it follows where things are drawn.
+      // The cursor is converted with the view shown this frame.
+      {
+        let g_state = self.curr_g_state.as_mut().unwrap();
+        g_state.camera.update(&g_state.comp_aabb, dt);
+        self.input_system.set_view(g_state.camera.get_pos(), g_state.camera.get_zoom());
+      }
 
       // Render
       use glium::Surface;
@@ -113,5 +154,7 @@ impl<'a> LibState<'a> {
       self.renderer.render_game(&self.display, &mut target, self.curr_g_state.as_ref().unwrap(), &self.tile_bank);
       let _ = target.finish();
     }
+
+    return !self.input_system.is_quit_requested();
   }
 }
@@ -0,0 +1,470 @@
+use common::fixed::Fx;
+use common::scalar::{Scalar, Vec2};
+use common::vec::Vec2f32;
+use entity::EntityID;
+use entity::component_core::BodyKind;
+use physics::{PhysicsSystem, StepMode, LAYER_TILES};
+use state::GameState;
+use tile::{TileBank, TileMap16, TileShape};
+use std::cmp::Ordering;
+
+/// Copy of the data we need from a CompCollAABB, so that the AABBs and bodies
+/// can be borrowed mutably while resolving.
+#[derive(Copy, Clone)]
+struct Collider<S> {
+  entity_id: EntityID,
+  offset: Vec2<S>,
+  size: Vec2<S>,
+  layer: u32,
+  mask: u32,
+  /// Kind of the entity's body, or None if it doesn't have one
+  kind: Option<BodyKind>,
+  /// World position of the collider before this update's integration
+  prev_pos: Vec2<S>,
+  /// Whether the body was grounded after the last update
+  was_grounded: bool,
+  /// Whether the body is falling through one way tiles
+  drop_through: bool,
+}
+
+impl<S> Collider<S> {
+  /// Only dynamic bodies get pushed around by collisions.
+  fn movable(&self) -> bool {
+    self.kind == Some(BodyKind::Dynamic)
+  }
+}
+
+/// How close (in world units) the bottom of a dynamic body has to be to the
+/// top of a kinematic body to be carried by it.
+const CARRY_EPSILON: f32 = 0.5;
+
+/// How fast (in world units per second) a body can move up relative to a
+/// kinematic body it's standing on and still be carried. Covers rounding in
+/// the contact impulse, which matches the rider's speed to the platform's.
+const CARRY_SPEED_EPSILON: f32 = 1.0;
+
+/// How far (in world units) above a one way tile's top the bottom of a body
+/// has to have been last update to land on it.
+const ONE_WAY_EPSILON: f32 = 0.5;
+
+/// Returns true if two colliders' layers and masks let them interact. Each
+/// collider's layer must be in the other's mask.
+pub fn layers_interact(a_layer: u32, a_mask: u32, b_layer: u32, b_mask: u32) -> bool {
+  a_layer & b_mask != 0 && b_layer & a_mask != 0
+}
+
+/// Returns true if two boxes overlap. Touching boxes don't count as
+/// overlapping.
+pub fn aabbs_overlap<S: Scalar>(a_min: Vec2<S>, a_size: Vec2<S>,
+                                b_min: Vec2<S>, b_size: Vec2<S>) -> bool {
+  a_min.0 < b_min.0 + b_size.0 && b_min.0 < a_min.0 + a_size.0
+    && a_min.1 < b_min.1 + b_size.1 && b_min.1 < a_min.1 + a_size.1
+}
+
+/// Find the minimum translation needed to push box a out of box b. Returns
+/// None if the boxes don't overlap. Touching boxes don't count as
+/// overlapping.
+pub fn aabb_penetration<S: Scalar>(a_min: Vec2<S>, a_size: Vec2<S>,
+                                   b_min: Vec2<S>, b_size: Vec2<S>) -> Option<Vec2<S>> {
+  // Distance to push a right / left, down / up
+  let right = b_min.0 + b_size.0 - a_min.0;
+  let left = a_min.0 + a_size.0 - b_min.0;
+  let down = b_min.1 + b_size.1 - a_min.1;
+  let up = a_min.1 + a_size.1 - b_min.1;
+  let zero = S::zero();
+  if right <= zero || left <= zero || down <= zero || up <= zero { return None; }
+
+  let px = if right < left { right } else { -left };
+  let py = if down < up { down } else { -up };
+  if px.abs() < py.abs() { Some(Vec2(px, zero)) } else { Some(Vec2(zero, py)) }
+}
+
+/// Velocity and material of one side of a contact.
+#[derive(Copy, Clone)]
+struct ContactBody<S> {
+  vel: Vec2<S>,
+  inv_mass: S,
+  restitution: S,
+  friction: S,
+}
+
+/// Get the contact properties of an entity's body, or None if it doesn't have
+/// one.
+fn contact_body<S: Scalar>(g_state: &GameState, entity_id: EntityID) -> Option<ContactBody<S>> {
+  g_state.comp_body.get_component(entity_id).map(|b| ContactBody {
+    vel: Vec2::from_vec2f32(b.vel),
+    inv_mass: S::from_f32(b.inv_mass()),
+    restitution: S::from_f32(b.restitution),
+    friction: S::from_f32(b.friction),
+  })
+}
+
+/// Move an entity's AABB by delta.
+fn move_aabb<S: Scalar>(g_state: &mut GameState, entity_id: EntityID, delta: Vec2<S>) {
+  let aabb = g_state.comp_aabb.get_component_mut(entity_id).unwrap();
+  aabb.pos = (Vec2::from_vec2f32(aabb.pos) + delta).to_vec2f32();
+}
+
+/// Change the velocity of an entity's body by delta.
+fn change_vel<S: Scalar>(g_state: &mut GameState, entity_id: EntityID, delta: Vec2<S>) {
+  let body = g_state.comp_body.get_component_mut(entity_id).unwrap();
+  body.vel = (Vec2::from_vec2f32(body.vel) + delta).to_vec2f32();
+}
+
+/// Separate two overlapping entities and apply a collision impulse between
+/// them. b is None when colliding with a tile. push is the minimum
+/// translation to move a out of b, and is split between the two by inverse
+/// mass, so momentum is conserved.
+fn resolve_contact<S: Scalar>(g_state: &mut GameState, a: EntityID, b: Option<EntityID>,
+                              push: Vec2<S>) {
+  let ca = contact_body::<S>(g_state, a);
+  let cb = b.and_then(|b| contact_body::<S>(g_state, b));
+  if ca.is_none() && cb.is_none() { return; }
+  let zero = S::zero();
+  let inv_a = ca.map_or(zero, |c| c.inv_mass);
+  let inv_b = cb.map_or(zero, |c| c.inv_mass);
+  let inv_sum = inv_a + inv_b;
+  if inv_sum == zero { return; }
+
+  // Positional correction
+  if inv_a > zero {
+    move_aabb(g_state, a, push * (inv_a / inv_sum));
+  }
+  if inv_b > zero {
+    move_aabb(g_state, b.unwrap(), push * (-inv_b / inv_sum));
+  }
+
+  // Being pushed up means standing on something. Y is down, so up is -y.
+  if inv_a > zero && push.1 < zero {
+    g_state.comp_body.get_component_mut(a).unwrap().grounded = true;
+  }
+  if inv_b > zero && push.1 > zero {
+    g_state.comp_body.get_component_mut(b.unwrap()).unwrap().grounded = true;
+  }
+
+  // Combine materials. If only one side has a body, use its material.
+  let (restitution, friction) = match (ca, cb) {
+    (Some(ca), Some(cb)) => (ca.restitution.max(cb.restitution),
+                             (ca.friction * cb.friction).sqrt()),
+    (Some(c), None) | (None, Some(c)) => (c.restitution, c.friction),
+    (None, None) => unreachable!(),
+  };
+
+  // Normal impulse, only if the bodies are moving towards each other
+  let va = ca.map_or(Vec2(zero, zero), |c| c.vel);
+  let vb = cb.map_or(Vec2(zero, zero), |c| c.vel);
+  let n = push.nor();
+  let v_rel = va - vb;
+  let vn = v_rel.dot(n);
+  if vn >= zero { return; }
+  let j = -(S::from_f32(1.0) + restitution) * vn / inv_sum;
+  let mut impulse = n * j;
+
+  // Friction impulse along the tangent, clamped by the normal impulse
+  let tangent = v_rel - n * vn;
+  if tangent.len2() > zero {
+    let t = tangent.nor();
+    let jt = (-v_rel.dot(t) / inv_sum).max(-friction * j).min(friction * j);
+    impulse = impulse + t * jt;
+  }
+
+  if inv_a > zero {
+    change_vel(g_state, a, impulse * inv_a);
+  }
+  if inv_b > zero {
+    change_vel(g_state, b.unwrap(), impulse * -inv_b);
+  }
+}
+
+/// Get the world space top left of a collider, or None if the entity has no
+/// CompAABB.
+fn collider_pos<S: Scalar>(g_state: &GameState, c: &Collider<S>) -> Option<Vec2<S>> {
+  g_state.comp_aabb.get_component(c.entity_id).map(|aabb| Vec2::from_vec2f32(aabb.pos) + c.offset)
+}
+
+/// Gather all the solid colliders in the game state. prev_pos is a list of
+/// (entity, AABB position) from before integration, sorted by entity.
+fn solid_colliders<S: Scalar>(g_state: &GameState,
+                              prev_pos: &[(EntityID, Vec2f32)]) -> Vec<Collider<S>> {
+  let mut colliders = Vec::with_capacity(g_state.comp_coll_aabb.len());
+  for c in &g_state.comp_coll_aabb {
+    if !c.solid { continue; }
+    let body = g_state.comp_body.get_component(c.entity_id);
+    let pos = g_state.comp_aabb.get_component(c.entity_id).map_or(Vec2f32(0.0, 0.0), |a| a.pos);
+    let prev = prev_pos.binary_search_by_key(&c.entity_id, |p| p.0)
+      .map(|ix| prev_pos[ix].1).unwrap_or(pos);
+    let offset = Vec2::from_vec2f32(c.offset);
+    colliders.push(Collider {
+      entity_id: c.entity_id,
+      offset: offset,
+      size: Vec2::from_vec2f32(c.size),
+      layer: c.layer,
+      mask: c.mask,
+      kind: body.map(|b| b.kind),
+      prev_pos: Vec2::from_vec2f32(prev) + offset,
+      was_grounded: body.map_or(false, |b| b.grounded),
+      drop_through: body.map_or(false, |b| b.drop_through),
+    });
+  }
+  colliders
+}
+
+/// World space top left of a tile, as TileMap16::tile_pos but in S.
+fn tile_pos<S: Scalar>(map: &TileMap16, x: usize, y: usize) -> Vec2<S> {
+  let ts = S::from_f32(map.tile_size);
+  Vec2(S::from_f32(map.world_pos.0) + S::from_f32(x as f32) * ts,
+       S::from_f32(map.world_pos.1) + S::from_f32(y as f32) * ts)
+}
+
+/// Range of tiles covered by a world space rectangle, as TileMap16::tile_rect
+/// but in S.
+fn tile_rect<S: Scalar>(map: &TileMap16, min: Vec2<S>, max: Vec2<S>)
+                        -> Option<(usize, usize, usize, usize)> {
+  let ts = S::from_f32(map.tile_size);
+  let world = Vec2::<S>::from_vec2f32(map.world_pos);
+  let size = S::from_f32(16.0) * ts;
+  if max.0 < world.0 || max.1 < world.1 || min.0 > world.0 + size || min.1 > world.1 + size {
+    return None;
+  }
+  let clamp = |v: S| -> usize { (v.floor().to_f32() as i32).max(0).min(15) as usize };
+  Some((clamp((min.0 - world.0) / ts),
+        clamp((min.1 - world.1) / ts),
+        clamp((max.0 - world.0) / ts),
+        clamp((max.1 - world.1) / ts)))
+}
+
+impl PhysicsSystem {
+  /// Find dynamic bodies standing on top of kinematic bodies, which should be
+  /// carried along when the kinematic body moves. Returns a list of
+  /// (rider, platform) pairs.
+  pub fn find_riders(&self, g_state: &GameState) -> Vec<(EntityID, EntityID)> {
+    match self.step_mode {
+      StepMode::Variable => find_riders::<f32>(g_state),
+      StepMode::Deterministic { .. } => find_riders::<Fx>(g_state),
+    }
+  }
+
+  /// Push solid colliders with dynamic bodies out of each other and out of
+  /// solid tiles, applying restitution and friction. Colliders without a
+  /// CompBody, or with a static or kinematic body, never get pushed. Layers
+  /// and masks are respected. Sets CompBody::grounded on bodies pushed
+  /// upwards. prev_pos is a list of (entity, AABB position) from before
+  /// integration sorted by entity, used for one way tiles.
+  pub fn resolve_collisions(&self, g_state: &mut GameState, tile_bank: &TileBank,
+                            prev_pos: &[(EntityID, Vec2f32)]) {
+    match self.step_mode {
+      StepMode::Variable => resolve_collisions::<f32>(g_state, tile_bank, prev_pos),
+      StepMode::Deterministic { .. } => resolve_collisions::<Fx>(g_state, tile_bank, prev_pos),
+    }
+  }
+}
+
+/// PhysicsSystem::find_riders, with maths done in S.
+pub(super) fn find_riders<S: Scalar>(g_state: &GameState) -> Vec<(EntityID, EntityID)> {
+  let colliders = solid_colliders::<S>(g_state, &[]);
+  let mut riders = Vec::new();
+  for p in &colliders {
+    if p.kind != Some(BodyKind::Kinematic) { continue; }
+    let p_pos = collider_pos(g_state, p);
+    if p_pos.is_none() { continue; }
+    let p_pos = p_pos.unwrap();
+    let p_vel = Vec2::<S>::from_vec2f32(g_state.comp_body.get_component(p.entity_id).unwrap().vel);
+
+    for r in &colliders {
+      if !r.movable() { continue; }
+      if !layers_interact(p.layer, p.mask, r.layer, r.mask) { continue; }
+      let r_pos = collider_pos(g_state, r);
+      if r_pos.is_none() { continue; }
+      let r_pos = r_pos.unwrap();
+
+      // Rider's feet must be on the platform's top, overlapping horizontally
+      if (r_pos.1 + r.size.1 - p_pos.1).abs() > S::from_f32(CARRY_EPSILON) { continue; }
+      if r_pos.0 >= p_pos.0 + p.size.0 || p_pos.0 >= r_pos.0 + r.size.0 { continue; }
+
+      // Don't carry bodies moving up off the platform (jumping). Bodies
+      // resting on a platform which starts sinking are still carried.
+      let r_vel = Vec2::<S>::from_vec2f32(g_state.comp_body.get_component(r.entity_id).unwrap().vel);
+      if r_vel.1 < p_vel.1.min(S::zero()) - S::from_f32(CARRY_SPEED_EPSILON) { continue; }
+
+      riders.push((r.entity_id, p.entity_id));
+    }
+  }
+  riders
+}
+
+/// PhysicsSystem::resolve_collisions, with maths done in S.
+pub(super) fn resolve_collisions<S: Scalar>(g_state: &mut GameState, tile_bank: &TileBank,
+                                            prev_pos: &[(EntityID, Vec2f32)]) {
+  let colliders = solid_colliders::<S>(g_state, prev_pos);
+  for body in &mut g_state.comp_body {
+    body.grounded = false;
+  }
+
+  // Colliders against each other
+  for ii in 0..colliders.len() {
+    for jj in ii+1..colliders.len() {
+      let (a, b) = (colliders[ii], colliders[jj]);
+      if !a.movable() && !b.movable() { continue; }
+      if !layers_interact(a.layer, a.mask, b.layer, b.mask) { continue; }
+      let a_pos = collider_pos(g_state, &a);
+      let b_pos = collider_pos(g_state, &b);
+      if a_pos.is_none() || b_pos.is_none() { continue; }
+      let push = aabb_penetration(a_pos.unwrap(), a.size, b_pos.unwrap(), b.size);
+      if push.is_none() { continue; }
+      resolve_contact(g_state, a.entity_id, Some(b.entity_id), push.unwrap());
+    }
+  }
+
+  // Colliders against tiles. Done last so that tiles always win.
+  for c in &colliders {
+    if !c.movable() || c.mask & LAYER_TILES == 0 { continue; }
+    resolve_tiles(g_state, tile_bank, c);
+  }
+}
+
+/// Push a single collider out of any solid tiles it overlaps.
+fn resolve_tiles<S: Scalar>(g_state: &mut GameState, tile_bank: &TileBank, c: &Collider<S>) {
+  let zero = S::zero();
+  for map_ix in 0..g_state.tile_maps.len() {
+    let on_slope = resolve_slopes(g_state, tile_bank, c, map_ix);
+
+    let pos = collider_pos(g_state, c);
+    if pos.is_none() { return; }
+    let pos = pos.unwrap();
+
+    // Find overlapping box shaped tiles, and resolve the biggest overlaps
+    // first. This stops bodies catching on the seams between tiles.
+    let mut overlaps = Vec::new();
+    {
+      let map = &g_state.tile_maps[map_ix];
+      let ts = S::from_f32(map.tile_size);
+      let rect = tile_rect(map, pos, pos + c.size);
+      if rect.is_none() { continue; }
+      let (x0, y0, x1, y1) = rect.unwrap();
+      for y in y0..y1 + 1 {
+        for x in x0..x1 + 1 {
+          let shape = tile_bank.get_tile_shape(map.tiles[y * 16 + x]);
+          let bounds = shape.bounds();
+          if bounds.is_none() { continue; }
+          let (lo, hi) = bounds.unwrap();
+          let t_min = tile_pos::<S>(map, x, y) + Vec2::from_vec2f32(lo) * ts;
+          let t_max = tile_pos::<S>(map, x, y) + Vec2::from_vec2f32(hi) * ts;
+          let w = (pos.0 + c.size.0).min(t_max.0) - pos.0.max(t_min.0);
+          let h = (pos.1 + c.size.1).min(t_max.1) - pos.1.max(t_min.1);
+          if w <= zero || h <= zero { continue; }
+          overlaps.push((w * h, t_min, t_max - t_min, shape));
+        }
+      }
+    }
+    overlaps.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    for &(_, t_pos, t_size, shape) in &overlaps {
+      let pos = collider_pos(g_state, c).unwrap();
+      let push = if shape == TileShape::OneWayTop {
+        // Only land on one way tiles when coming from above
+        let bottom = pos.1 + c.size.1;
+        if c.drop_through || c.prev_pos.1 + c.size.1 > t_pos.1 + S::from_f32(ONE_WAY_EPSILON)
+          || bottom <= t_pos.1 {
+            continue;
+          }
+        Vec2(zero, t_pos.1 - bottom)
+      }
+      else {
+        let push = aabb_penetration(pos, c.size, t_pos, t_size);
+        if push.is_none() { continue; }
+        push.unwrap()
+      };
+      // When standing on a slope, the slope is the floor. Ignore floor
+      // tiles and tiles at the top of the slope within half a tile of the
+      // body's feet, otherwise the body's corners catch on them.
+      if on_slope && (push.1 < zero || pos.1 + c.size.1 - t_pos.1 <= t_size.0 * S::from_f32(0.5)) {
+        continue;
+      }
+      resolve_contact(g_state, c.entity_id, None, push);
+    }
+  }
+}
+
+/// Put a collider's bottom centre on the floor of any slope tile under it.
+/// Bodies which were grounded last update and aren't moving up are also
+/// snapped down onto slopes a short distance below them, so they walk down
+/// slopes smoothly rather than bouncing. The same goes for flat floors when
+/// walking off the bottom of a slope. Returns true if the body is on a
+/// slope.
+fn resolve_slopes<S: Scalar>(g_state: &mut GameState, tile_bank: &TileBank,
+                             c: &Collider<S>, map_ix: usize) -> bool {
+  let pos = collider_pos(g_state, c);
+  if pos.is_none() { return false; }
+  let pos = pos.unwrap();
+  let vel = Vec2::<S>::from_vec2f32(g_state.comp_body.get_component(c.entity_id).unwrap().vel);
+  let (zero, two) = (S::zero(), S::from_f32(2.0));
+
+  let floor;
+  {
+    let map = &g_state.tile_maps[map_ix];
+    let ts = S::from_f32(map.tile_size);
+    let foot = Vec2(pos.0 + c.size.0 / two, pos.1 + c.size.1);
+    let snap = if c.was_grounded && vel.1 >= zero { ts * S::from_f32(0.5) } else { zero };
+
+    // Find the highest slope floor in the column under the foot
+    let rect = tile_rect(map, Vec2(foot.0, pos.1), Vec2(foot.0, foot.1 + snap));
+    if rect.is_none() { return false; }
+    let (x, y0, _, y1) = rect.unwrap();
+    let mut best: Option<S> = None;
+    for y in y0..y1 + 1 {
+      let shape = tile_bank.get_tile_shape(map.tiles[y * 16 + x]);
+      let t_pos = tile_pos::<S>(map, x, y);
+      let surface = shape.surface((foot.0 - t_pos.0) / ts);
+      if surface.is_none() { continue; }
+      let surface = t_pos.1 + surface.unwrap() * ts;
+      // Either in the slope (by at most a tile), or close enough above to
+      // snap down
+      let depth = foot.1 - surface;
+      if depth > ts || depth < -snap { continue; }
+      if best.map_or(true, |b| surface < b) {
+        best = Some(surface);
+      }
+    }
+    if best.is_none() {
+      // Walking off the bottom of a slope, snap down onto the top of the
+      // floor under the foot
+      let prev_x = ((c.prev_pos.0 + c.size.0 / two - S::from_f32(map.world_pos.0)) / ts).floor();
+      if snap == zero || prev_x < zero || prev_x >= S::from_f32(16.0)
+        || prev_x.to_f32() as usize == x {
+          return false;
+        }
+      let prev_x = prev_x.to_f32() as usize;
+      let was_on_slope = (y0..y1 + 1).any(|y| {
+        tile_bank.get_tile_shape(map.tiles[y * 16 + prev_x]).is_slope()
+      });
+      if !was_on_slope { return false; }
+      for y in y0..y1 + 1 {
+        let shape = tile_bank.get_tile_shape(map.tiles[y * 16 + x]);
+        if shape == TileShape::OneWayTop && c.drop_through { continue; }
+        let bounds = shape.bounds();
+        if bounds.is_none() { continue; }
+        let top = tile_pos::<S>(map, x, y).1 + S::from_f32((bounds.unwrap().0).1) * ts;
+        let depth = foot.1 - top;
+        if depth > zero || depth < -snap { continue; }
+        if best.map_or(true, |b| top < b) {
+          best = Some(top);
+        }
+      }
+      if best.is_some() {
+        move_aabb(g_state, c.entity_id, Vec2(zero, best.unwrap() - foot.1));
+        g_state.comp_body.get_component_mut(c.entity_id).unwrap().grounded = true;
+      }
+      return false;
+    }
+    floor = best.unwrap() - foot.1;
+  }
+
+  if floor < zero {
+    resolve_contact(g_state, c.entity_id, None, Vec2(zero, floor));
+  }
+  else {
+    move_aabb(g_state, c.entity_id, Vec2(zero, floor));
+    g_state.comp_body.get_component_mut(c.entity_id).unwrap().grounded = true;
+  }
+  true
+}
@@ -0,0 +1,51 @@
+use common::scalar::{Scalar, Vec2};
+
+/// Numerical integration method used to move bodies. The acceleration of a
+/// body is constant over a single step.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Integrator {
+  /// Update the velocity, then move by the new velocity. Cheap and stable,
+  /// but positions lag behind the exact solution by half a step's worth of
+  /// acceleration.
+  SemiImplicitEuler,
+  /// Move by the average of the old and new velocities. Exact for constant
+  /// acceleration.
+  VelocityVerlet,
+  /// Classic 4th order Runge-Kutta. Also exact for constant acceleration, and
+  /// treats the speed cap as part of the motion rather than clamping after
+  /// the fact.
+  Rk4,
+}
+
+/// Cap a velocity to a maximum speed, keeping its direction. Compares
+/// lengths rather than squared lengths, as the square of a large max_speed is
+/// out of range for a Fx.
+pub fn cap_speed<S: Scalar>(vel: Vec2<S>, max_speed: S) -> Vec2<S> {
+  let len = vel.len();
+  if len > max_speed {
+    return vel * (max_speed / len);
+  }
+  return vel;
+}
+
+/// Step a body forward by dt with a constant acceleration, with its speed
+/// capped to max_speed. Returns the change in position and the new velocity.
+pub fn integrate<S: Scalar>(integrator: Integrator, vel: Vec2<S>, acc: Vec2<S>,
+                            max_speed: S, dt: S) -> (Vec2<S>, Vec2<S>) {
+  let (two, six) = (S::from_f32(2.0), S::from_f32(6.0));
+  let vel = cap_speed(vel, max_speed);
+  let new_vel = cap_speed(vel + acc * dt, max_speed);
+  match integrator {
+    Integrator::SemiImplicitEuler => (new_vel * dt, new_vel),
+    Integrator::VelocityVerlet => ((vel + new_vel) * (dt / two), new_vel),
+    Integrator::Rk4 => {
+      // dx/dt = cap(v), dv/dt = acc. Acceleration is constant, so only the
+      // position derivatives need evaluating at each stage.
+      let k1 = vel;
+      let k2 = cap_speed(vel + acc * (dt / two), max_speed);
+      let k3 = cap_speed(vel + acc * (dt / two), max_speed);
+      let k4 = new_vel;
+      ((k1 + k2 * two + k3 * two + k4) * (dt / six), new_vel)
+    }
+  }
+}
@@ -1,43 +1,199 @@
 use state::GameState;
+use tile::TileBank;
+use entity::component_core::BodyKind;
+use common::vec::Vec2f32;
+
+/// Raycast and shape cast queries against tiles and colliders
+pub mod raycast;
+
+/// Collision detection and resolution between colliders and tiles
+pub mod collision;
+
+/// Trigger volumes, tracks overlaps with non-solid colliders
+pub mod trigger;
+
+/// Integrators for moving bodies
+pub mod integrator;
+
+use self::integrator::Integrator;
+use common::fixed::{self, Fx};
+use common::scalar::{Scalar, Vec2};
+
+/// Layer bit for tiles. Reserved, can't be registered as a named layer.
+pub const LAYER_TILES: u32 = 1 << 31;
+
+/// Layer bit for the 'default' layer, which is always registered.
+pub const LAYER_DEFAULT: u32 = 1 << 0;
+
+/// Mask for colliding with everything (tiles and all layers).
+pub const MASK_ALL: u32 = !0;
+
+/// How the physics system steps time.
+#[derive(Copy, Clone, PartialEq)]
+pub enum StepMode {
+  /// Float maths, stepped by the frame delta passed to update_physics.
+  Variable,
+  /// Every call to update_physics advances by exactly `step` seconds,
+  /// ignoring the frame delta. Integration, carrying riders, collision
+  /// resolution, slopes and triggers all use fixed point maths, and positions
+  /// and velocities are snapped to the fixed point grid after every step, so
+  /// the same inputs always give the same GameState on every platform. Use
+  /// this for replays and lockstep networking.
+  ///
+  /// Components still store floats. Positions further than 256 units from
+  /// the origin lose some fixed point precision when stored, in the same way
+  /// on every platform.
+  Deterministic { step: f32 },
+}
 
 pub struct PhysicsSystem {
+  /// Integrator used to move bodies. Defaults to velocity verlet.
+  pub integrator: Integrator,
+
+  /// Whether to step with the frame delta, or deterministically. Defaults to
+  /// StepMode::Variable.
+  pub step_mode: StepMode,
+
+  /// Named collision layers. The index of the name is the layer's bit.
+  layer_names: Vec<String>,
+
+  /// Trigger events from the last update.
+  trigger_events: Vec<trigger::TriggerEvent>,
 }
 
 impl PhysicsSystem {
   pub fn new() -> PhysicsSystem {
-    PhysicsSystem {}
+    PhysicsSystem {
+      integrator: Integrator::VelocityVerlet,
+      step_mode: StepMode::Variable,
+      layer_names: vec!["default".to_owned()],
+      trigger_events: Vec::new(),
+    }
+  }
+
+  /// Register a named collision layer, returning the layer bit. If the layer
+  /// already exists, returns the existing bit. Returns None if all 31 layers
+  /// are taken.
+  pub fn register_layer(&mut self, name: &str) -> Option<u32> {
+    let existing = self.get_layer(name);
+    if existing.is_some() { return existing; }
+    if self.layer_names.len() >= 31 { return None; }
+    self.layer_names.push(name.to_owned());
+    Some(1 << (self.layer_names.len() - 1))
+  }
+
+  /// Get the bit of a named layer, or None if it isn't registered.
+  pub fn get_layer(&self, name: &str) -> Option<u32> {
+    for ii in 0..self.layer_names.len() {
+      if self.layer_names[ii] == name {
+        return Some(1 << ii);
+      }
+    }
+    return None;
+  }
+
+  /// Build a mask from a list of named layers. Use "tiles" for LAYER_TILES.
+  /// Returns None if any of the names aren't registered.
+  pub fn get_mask(&self, names: &[&str]) -> Option<u32> {
+    let mut mask = 0;
+    for name in names {
+      if *name == "tiles" {
+        mask |= LAYER_TILES;
+        continue;
+      }
+      let layer = self.get_layer(name);
+      if layer.is_none() { return None; }
+      mask |= layer.unwrap();
+    }
+    Some(mask)
   }
 
   /// Update the game state, apply physics.
   /// dt param is update delta in seconds. Keep this constant for consistent
-  /// performance across all systems.
-  pub fn update_physics(&self, g_state: &mut GameState, dt: f32) {
-    // Add velocities to AABB
-    let dt2 = dt*dt;
+  /// performance across all systems. Ignored in StepMode::Deterministic.
+  pub fn update_physics(&mut self, g_state: &mut GameState, tile_bank: &TileBank, dt: f32) {
+    match self.step_mode {
+      StepMode::Variable => self.step::<f32>(g_state, tile_bank, dt),
+      StepMode::Deterministic { step } => {
+        self.step::<Fx>(g_state, tile_bank, step);
+        snap_to_grid(g_state);
+      }
+    }
+
+    // Emit trigger events for the new positions
+    self.update_triggers(g_state);
+  }
+
+  /// Move bodies and resolve collisions, with maths done in S.
+  fn step<S: Scalar>(&self, g_state: &mut GameState, tile_bank: &TileBank, dt: f32) {
+    let dt = S::from_f32(dt);
+
+    // Find bodies standing on moving platforms before anything moves
+    let riders = collision::find_riders::<S>(g_state);
+    let mut prev_pos = Vec::with_capacity(g_state.comp_body.len());
+    for body in &g_state.comp_body {
+      let aabb = g_state.comp_aabb.get_component(body.entity_id);
+      if aabb.is_some() { prev_pos.push((body.entity_id, aabb.unwrap().pos)); }
+    }
+    let mut kinematic_moves = Vec::new();
+
+    // Integrate bodies
     for body in &mut g_state.comp_body {
-      // Set accel from force
-      body.acc.0 = body.force.0 / body.mass;
-      body.acc.1 = body.force.1 / body.mass;
+      if body.kind == BodyKind::Static {
+        body.clear_force();
+        continue;
+      }
+
+      // Set accel from force. Kinematic bodies ignore forces, as do bodies
+      // without a positive mass (see CompBody::inv_mass).
+      let acc = if body.kind == BodyKind::Kinematic {
+        Vec2(S::zero(), S::zero())
+      } else {
+        Vec2::from_vec2f32(body.force) * S::from_f32(body.inv_mass())
+      };
+      body.acc = acc.to_vec2f32();
       body.clear_force();
 
-      if body.vel.0 != 0.0 || body.vel.1 != 0.0 {
-        let aabb = g_state.comp_aabb.get_component_mut(body.entity_id);
+      let (delta, vel) = integrator::integrate(
+        self.integrator, Vec2::from_vec2f32(body.vel), acc, S::from_f32(body.max_speed), dt);
+      body.vel = vel.to_vec2f32();
+      let aabb = g_state.comp_aabb.get_component_mut(body.entity_id);
+      if aabb.is_none() { continue; }
+      let aabb = aabb.unwrap();
+      aabb.pos = (Vec2::from_vec2f32(aabb.pos) + delta).to_vec2f32();
+      if body.kind == BodyKind::Kinematic {
+        kinematic_moves.push((body.entity_id, delta));
+      }
+    }
+
+    // Carry riders along with their platforms. Platforms moving up push their
+    // riders up when collisions are resolved, and the contact gives riders
+    // the platform's velocity, so riders are only carried downwards.
+    for &(rider, platform) in &riders {
+      for &(entity_id, delta) in &kinematic_moves {
+        if entity_id != platform { continue; }
+        let aabb = g_state.comp_aabb.get_component_mut(rider);
         if aabb.is_none() { continue; }
         let aabb = aabb.unwrap();
-
-        // Apply velocity (with euler integration)
-        aabb.pos.0 += body.vel.0*dt + body.acc.0/2.0*dt2;
-        aabb.pos.1 += body.vel.1*dt + body.acc.1/2.0*dt2;
+        let carry = Vec2(delta.0, delta.1.max(S::zero()));
+        aabb.pos = (Vec2::from_vec2f32(aabb.pos) + carry).to_vec2f32();
       }
+    }
 
-      // Apply accel to vel
-      body.vel.0 += body.acc.0*dt;
-      body.vel.1 += body.acc.1*dt;
+    // Push bodies out of anything solid they've moved into
+    collision::resolve_collisions::<S>(g_state, tile_bank, &prev_pos);
+  }
+}
 
-      // Check if at max speed, and cap
-      if body.vel.len2() > body.max_speed.powi(2) {
-        body.vel.nor().scale(body.max_speed);
-      }
-    }
+/// Snap the positions and velocities of every body onto the fixed point grid,
+/// so that the next deterministic step starts from exactly representable
+/// values.
+fn snap_to_grid(g_state: &mut GameState) {
+  for body in &mut g_state.comp_body {
+    body.vel = Vec2f32(fixed::snap(body.vel.0), fixed::snap(body.vel.1));
+    let aabb = g_state.comp_aabb.get_component_mut(body.entity_id);
+    if aabb.is_none() { continue; }
+    let aabb = aabb.unwrap();
+    aabb.pos = Vec2f32(fixed::snap(aabb.pos.0), fixed::snap(aabb.pos.1));
   }
 }
@@ -0,0 +1,363 @@
+use common::vec::Vec2f32;
+use entity::EntityID;
+use physics::{PhysicsSystem, LAYER_TILES};
+use state::GameState;
+use tile::{TileBank, TileMap16, TileShape};
+use std::cmp::Ordering;
+use std::f32;
+
+/// The thing a raycast hit.
+#[derive(Copy, Clone, PartialEq)]
+pub enum HitTarget {
+  /// An entity's CompCollAABB
+  Entity(EntityID),
+  /// A tile. map is the index into GameState::tile_maps, x / y is the tile
+  /// coordinate inside that map.
+  Tile { map: usize, x: usize, y: usize },
+}
+
+/// Result of a raycast or shape cast.
+#[derive(Copy, Clone)]
+pub struct RaycastHit {
+  pub target: HitTarget,
+
+  /// Point of contact. For shape casts, this is the position (top left) of the
+  /// shape at the time of impact.
+  pub point: Vec2f32,
+
+  /// Surface normal of the thing that was hit.
+  pub normal: Vec2f32,
+
+  /// Distance travelled along the ray before the hit.
+  pub dist: f32,
+}
+
+/// Intersect a ray against a single axis slab. Returns the entry and exit
+/// distances, and whether the entry is on the 'max' side of the slab. Returns
+/// None if the ray is parallel to and outside the slab.
+fn slab(origin: f32, dir: f32, min: f32, max: f32) -> Option<(f32, f32, bool)> {
+  if dir == 0.0 {
+    if origin <= min || origin >= max { return None; }
+    return Some((f32::NEG_INFINITY, f32::INFINITY, false));
+  }
+  let t0 = (min - origin) / dir;
+  let t1 = (max - origin) / dir;
+  if t0 > t1 { Some((t1, t0, true)) } else { Some((t0, t1, false)) }
+}
+
+/// Slab test a ray against an AABB. Returns the distance along the ray and the
+/// surface normal at the point of entry, or None if the ray misses, the box is
+/// further away than max_dist, or the ray starts inside the box.
+/// dir must be normalised.
+fn ray_vs_aabb(origin: Vec2f32, dir: Vec2f32, max_dist: f32,
+               min: Vec2f32, max: Vec2f32) -> Option<(f32, Vec2f32)> {
+  let x = slab(origin.0, dir.0, min.0, max.0);
+  if x.is_none() { return None; }
+  let y = slab(origin.1, dir.1, min.1, max.1);
+  if y.is_none() { return None; }
+  let (x_near, x_far, x_flip) = x.unwrap();
+  let (y_near, y_far, y_flip) = y.unwrap();
+
+  let (t_near, normal) = if x_near > y_near {
+    (x_near, Vec2f32(if x_flip { 1.0 } else { -1.0 }, 0.0))
+  } else {
+    (y_near, Vec2f32(0.0, if y_flip { 1.0 } else { -1.0 }))
+  };
+  let t_far = x_far.min(y_far);
+
+  if t_near > t_far || t_near < 0.0 || t_near > max_dist { return None; }
+  Some((t_near, normal))
+}
+
+/// Intersect a ray with a convex polygon. Returns the distance along the ray
+/// and the surface normal at the point of entry, or None if the ray misses,
+/// the polygon is further away than max_dist, or the ray starts inside it.
+/// dir must be normalised. Works with either winding.
+fn ray_vs_convex(origin: Vec2f32, dir: Vec2f32, max_dist: f32,
+                 points: &[Vec2f32]) -> Option<(f32, Vec2f32)> {
+  if points.len() < 3 { return None; }
+  // Centre of the polygon, used to point the edge normals outwards
+  let mut centre = Vec2f32(0.0, 0.0);
+  for p in points { centre += *p; }
+  let centre = centre * (1.0 / points.len() as f32);
+
+  let mut t_enter = f32::NEG_INFINITY;
+  let mut t_exit = f32::INFINITY;
+  let mut normal = Vec2f32(0.0, 0.0);
+  for ii in 0..points.len() {
+    let a = points[ii];
+    let b = points[(ii + 1) % points.len()];
+    let mut n = Vec2f32(b.1 - a.1, a.0 - b.0);
+    if n.dot(a - centre) < 0.0 { n = n * -1.0; }
+    n.nor();
+
+    // Points inside this edge satisfy n . (p - a) <= 0
+    let denom = n.dot(dir);
+    let dist = n.dot(a - origin);
+    if denom == 0.0 {
+      if dist < 0.0 { return None; }
+      continue;
+    }
+    let t = dist / denom;
+    if denom < 0.0 {
+      if t > t_enter { t_enter = t; normal = n; }
+    }
+    else if t < t_exit {
+      t_exit = t;
+    }
+  }
+
+  if t_enter > t_exit || t_enter < 0.0 || t_enter > max_dist { return None; }
+  Some((t_enter, normal))
+}
+
+/// Convex hull of a set of points (Andrew's monotone chain). Points which
+/// aren't finite are ignored.
+fn convex_hull(mut points: Vec<Vec2f32>) -> Vec<Vec2f32> {
+  points.retain(|p| p.0.is_finite() && p.1.is_finite());
+  points.sort_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap_or(Ordering::Equal));
+  let cross = |o: Vec2f32, a: Vec2f32, b: Vec2f32| -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+  };
+  let mut hull: Vec<Vec2f32> = Vec::with_capacity(points.len() + 1);
+  // Lower hull, then upper hull
+  for pass in 0..2 {
+    let start = hull.len();
+    for ii in 0..points.len() {
+      let p = if pass == 0 { points[ii] } else { points[points.len() - 1 - ii] };
+      while hull.len() >= start + 2
+        && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+          hull.pop();
+        }
+      hull.push(p);
+    }
+    hull.pop();
+  }
+  hull
+}
+
+/// World space outline of a tile's collision shape.
+fn tile_outline(map: &TileMap16, x: usize, y: usize, shape: TileShape) -> Vec<Vec2f32> {
+  let pos = map.tile_pos(x, y);
+  shape.outline().iter()
+    .map(|&(u, v)| pos + Vec2f32(u * map.tile_size, v * map.tile_size))
+    .collect()
+}
+
+/// Walk a ray through a tile map using a DDA grid traversal, pushing any solid
+/// tiles hit onto the hits list. Full tiles containing the origin are ignored,
+/// but slopes and half tiles containing the origin are hit if the origin is
+/// outside the solid part. One way tiles are only hit from above.
+/// If first_only is true, stops at the first solid tile.
+fn raycast_tile_map(map: &TileMap16, map_ix: usize, tile_bank: &TileBank,
+                    origin: Vec2f32, dir: Vec2f32, max_dist: f32,
+                    first_only: bool, hits: &mut Vec<RaycastHit>) {
+  let ts = map.tile_size;
+  let min = map.world_pos;
+  let max = map.world_pos + Vec2f32(16.0 * ts, 16.0 * ts);
+
+  // Find where the ray enters the map
+  let inside = origin.0 >= min.0 && origin.0 < max.0
+    && origin.1 >= min.1 && origin.1 < max.1;
+  let (mut t, mut normal) = if inside {
+    (0.0, Vec2f32(0.0, 0.0))
+  } else {
+    let entry = ray_vs_aabb(origin, dir, max_dist, min, max);
+    if entry.is_none() { return; }
+    entry.unwrap()
+  };
+
+  // Find the starting cell
+  let start = origin + dir * t;
+  let clamp = |v: f32| -> i32 { (v.floor() as i32).max(0).min(15) };
+  let mut cx = clamp((start.0 - min.0) / ts);
+  let mut cy = clamp((start.1 - min.1) / ts);
+
+  let step_x = if dir.0 > 0.0 { 1 } else { -1 };
+  let step_y = if dir.1 > 0.0 { 1 } else { -1 };
+  let t_delta_x = if dir.0 != 0.0 { ts / dir.0.abs() } else { f32::INFINITY };
+  let t_delta_y = if dir.1 != 0.0 { ts / dir.1.abs() } else { f32::INFINITY };
+  let mut t_max_x = if dir.0 > 0.0 {
+    (min.0 + (cx + 1) as f32 * ts - origin.0) / dir.0
+  } else if dir.0 < 0.0 {
+    (min.0 + cx as f32 * ts - origin.0) / dir.0
+  } else { f32::INFINITY };
+  let mut t_max_y = if dir.1 > 0.0 {
+    (min.1 + (cy + 1) as f32 * ts - origin.1) / dir.1
+  } else if dir.1 < 0.0 {
+    (min.1 + cy as f32 * ts - origin.1) / dir.1
+  } else { f32::INFINITY };
+
+  let mut skip = inside;
+  loop {
+    let (x, y) = (cx as usize, cy as usize);
+    let shape = tile_bank.get_tile_shape(map.tiles[y * 16 + x]);
+    let hit = match shape {
+      TileShape::Empty => None,
+      TileShape::Full => if skip { None } else { Some((t, normal)) },
+      TileShape::OneWayTop => if !skip && normal.1 < 0.0 { Some((t, normal)) } else { None },
+      _ => ray_vs_convex(origin, dir, max_dist, &tile_outline(map, x, y, shape)),
+    };
+    if hit.is_some() {
+      let (t_hit, n) = hit.unwrap();
+      hits.push(RaycastHit {
+        target: HitTarget::Tile { map: map_ix, x: x, y: y },
+        point: origin + dir * t_hit,
+        normal: n,
+        dist: t_hit,
+      });
+      if first_only { return; }
+    }
+    skip = false;
+
+    // Step to the next cell
+    if t_max_x < t_max_y {
+      cx += step_x;
+      t = t_max_x;
+      t_max_x += t_delta_x;
+      normal = Vec2f32(-step_x as f32, 0.0);
+    }
+    else {
+      cy += step_y;
+      t = t_max_y;
+      t_max_y += t_delta_y;
+      normal = Vec2f32(0.0, -step_y as f32);
+    }
+    if cx < 0 || cx > 15 || cy < 0 || cy > 15 || t > max_dist { return; }
+  }
+}
+
+/// Sweep an AABB through a tile map, pushing any solid tiles hit onto the hits
+/// list. Tiles already overlapping the shape are ignored, and one way tiles
+/// are only hit from above.
+fn shape_cast_tile_map(map: &TileMap16, map_ix: usize, tile_bank: &TileBank,
+                       pos: Vec2f32, size: Vec2f32, dir: Vec2f32, max_dist: f32,
+                       hits: &mut Vec<RaycastHit>) {
+  let end = pos + dir * max_dist;
+  let rect = map.tile_rect(Vec2f32(pos.0.min(end.0), pos.1.min(end.1)),
+                           Vec2f32(pos.0.max(end.0), pos.1.max(end.1)) + size);
+  if rect.is_none() { return; }
+  let (tx0, ty0, tx1, ty1) = rect.unwrap();
+
+  for y in ty0..ty1 + 1 {
+    for x in tx0..tx1 + 1 {
+      let shape = tile_bank.get_tile_shape(map.tiles[y * 16 + x]);
+      if shape == TileShape::Empty { continue; }
+      // Minkowski difference of the tile and the shape, then raycast the
+      // shape's position against it
+      let t_pos = map.tile_pos(x, y);
+      let hit = if let Some((lo, hi)) = shape.bounds() {
+        ray_vs_aabb(pos, dir, max_dist,
+                    t_pos + lo * map.tile_size - size, t_pos + hi * map.tile_size)
+      } else {
+        let mut points = Vec::new();
+        for p in tile_outline(map, x, y, shape) {
+          points.push(p);
+          points.push(p - Vec2f32(size.0, 0.0));
+          points.push(p - Vec2f32(0.0, size.1));
+          points.push(p - size);
+        }
+        ray_vs_convex(pos, dir, max_dist, &convex_hull(points))
+      };
+      if hit.is_none() { continue; }
+      let (t, normal) = hit.unwrap();
+      if shape == TileShape::OneWayTop && normal.1 >= 0.0 { continue; }
+      hits.push(RaycastHit {
+        target: HitTarget::Tile { map: map_ix, x: x, y: y },
+        point: pos + dir * t,
+        normal: normal,
+        dist: t,
+      });
+    }
+  }
+}
+
+impl PhysicsSystem {
+  /// Cast a ray, returning the closest hit.
+  /// dir doesn't need to be normalised. mask is the set of collision layers
+  /// the ray can hit, including LAYER_TILES for tiles. Only solid colliders
+  /// are hit, triggers are ignored. Colliders and tiles containing the origin
+  /// are ignored. Zero length rays, and rays with NaN or infinite origins or
+  /// directions, never hit anything.
+  pub fn raycast(&self, g_state: &GameState, tile_bank: &TileBank,
+                 origin: Vec2f32, dir: Vec2f32, max_dist: f32,
+                 mask: u32) -> Option<RaycastHit> {
+    let hits = self.cast(g_state, tile_bank, origin, Vec2f32(0.0, 0.0),
+                         dir, max_dist, mask, false, true);
+    hits.into_iter().next()
+  }
+
+  /// Cast a ray, returning every hit along it sorted by distance. See
+  /// raycast().
+  pub fn raycast_all(&self, g_state: &GameState, tile_bank: &TileBank,
+                     origin: Vec2f32, dir: Vec2f32, max_dist: f32,
+                     mask: u32) -> Vec<RaycastHit> {
+    self.cast(g_state, tile_bank, origin, Vec2f32(0.0, 0.0),
+              dir, max_dist, mask, false, false)
+  }
+
+  /// Sweep an AABB at pos (top left) with the given size along dir,
+  /// returning the first hit. The hit point is the position of the AABB at
+  /// the time of impact. Anything already overlapping the AABB is ignored.
+  pub fn shape_cast(&self, g_state: &GameState, tile_bank: &TileBank,
+                    pos: Vec2f32, size: Vec2f32, dir: Vec2f32, max_dist: f32,
+                    mask: u32) -> Option<RaycastHit> {
+    let hits = self.cast(g_state, tile_bank, pos, size,
+                         dir, max_dist, mask, true, true);
+    hits.into_iter().next()
+  }
+
+  /// Shared implementation of the ray and shape casts. A ray is just a shape
+  /// cast with zero size, but tiles are walked with DDA in that case rather
+  /// than testing every tile in the swept bounds.
+  fn cast(&self, g_state: &GameState, tile_bank: &TileBank,
+          pos: Vec2f32, size: Vec2f32, mut dir: Vec2f32, max_dist: f32,
+          mask: u32, is_shape: bool, first_only: bool) -> Vec<RaycastHit> {
+    let mut hits = Vec::new();
+    // Zero length, NaN or infinite rays don't hit anything. An infinite
+    // max_dist is fine.
+    let len2 = dir.len2();
+    if len2 == 0.0 || !len2.is_finite() || !pos.0.is_finite() || !pos.1.is_finite()
+      || !(max_dist > 0.0) {
+        return hits;
+      }
+    dir.nor();
+
+    // Tiles
+    if mask & LAYER_TILES != 0 {
+      for (ix, map) in g_state.tile_maps.iter().enumerate() {
+        if is_shape {
+          shape_cast_tile_map(map, ix, tile_bank, pos, size, dir, max_dist, &mut hits);
+        }
+        else {
+          raycast_tile_map(map, ix, tile_bank, pos, dir, max_dist, first_only, &mut hits);
+        }
+      }
+    }
+
+    // Colliders. Triggers (non-solid colliders) don't block casts.
+    if mask & !LAYER_TILES != 0 {
+      for c in &g_state.comp_coll_aabb {
+        if !c.solid || c.layer & mask == 0 { continue; }
+        let aabb = g_state.comp_aabb.get_component(c.entity_id);
+        if aabb.is_none() { continue; }
+        let aabb = aabb.unwrap();
+        let min = aabb.pos + c.offset;
+        let max = min + c.size;
+        let hit = ray_vs_aabb(pos, dir, max_dist, min - size, max);
+        if hit.is_none() { continue; }
+        let (t, normal) = hit.unwrap();
+        hits.push(RaycastHit {
+          target: HitTarget::Entity(c.entity_id),
+          point: pos + dir * t,
+          normal: normal,
+          dist: t,
+        });
+      }
+    }
+
+    hits.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap_or(Ordering::Equal));
+    if first_only { hits.truncate(1); }
+    hits
+  }
+}
@@ -0,0 +1,101 @@
+use common::fixed::Fx;
+use common::scalar::{Scalar, Vec2};
+use entity::EntityID;
+use physics::{PhysicsSystem, StepMode};
+use physics::collision::{layers_interact, aabbs_overlap};
+use state::GameState;
+
+/// What happened between a trigger and another collider this frame.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TriggerEventKind {
+  /// The collider started overlapping the trigger this frame.
+  Enter,
+  /// The collider was overlapping last frame, and still is.
+  Stay,
+  /// The collider stopped overlapping the trigger this frame, or one of the
+  /// two entities was removed.
+  Exit,
+}
+
+/// Event emitted by the physics system for a trigger (non-solid collider).
+#[derive(Copy, Clone)]
+pub struct TriggerEvent {
+  pub trigger: EntityID,
+  pub other: EntityID,
+  pub kind: TriggerEventKind,
+}
+
+impl PhysicsSystem {
+  /// Get the trigger events from the last physics update.
+  pub fn trigger_events(&self) -> &[TriggerEvent] {
+    &self.trigger_events
+  }
+
+  /// Find every collider overlapping a trigger, compare with last frame's
+  /// overlaps (GameState::trigger_overlaps), and emit enter / stay / exit
+  /// events. Trigger callbacks are called for enter and exit.
+  pub fn update_triggers(&mut self, g_state: &mut GameState) {
+    self.trigger_events.clear();
+    let overlaps = match self.step_mode {
+      StepMode::Variable => find_overlaps::<f32>(g_state),
+      StepMode::Deterministic { .. } => find_overlaps::<Fx>(g_state),
+    };
+
+    // Enter / stay
+    for &(trigger, other) in &overlaps {
+      let kind = if g_state.trigger_overlaps.binary_search(&(trigger, other)).is_ok() {
+        TriggerEventKind::Stay
+      } else {
+        TriggerEventKind::Enter
+      };
+      self.trigger_events.push(TriggerEvent { trigger: trigger, other: other, kind: kind });
+    }
+
+    // Exit. This also catches entities that have been removed, since they
+    // won't be in this frame's overlaps.
+    for &(trigger, other) in &g_state.trigger_overlaps {
+      if overlaps.binary_search(&(trigger, other)).is_err() {
+        self.trigger_events.push(TriggerEvent {
+          trigger: trigger, other: other, kind: TriggerEventKind::Exit
+        });
+      }
+    }
+
+    g_state.trigger_overlaps = overlaps;
+
+    // Callbacks
+    for e in &self.trigger_events {
+      if e.kind == TriggerEventKind::Stay { continue; }
+      let t = g_state.comp_coll_aabb.get_component(e.trigger);
+      if t.is_none() { continue; }
+      if let Some(callback) = t.unwrap().callback {
+        callback(e.trigger, e.other, e.kind);
+      }
+    }
+  }
+}
+
+/// Find every collider overlapping a trigger, with maths done in S. Returns
+/// sorted (trigger, other) pairs.
+fn find_overlaps<S: Scalar>(g_state: &GameState) -> Vec<(EntityID, EntityID)> {
+  let mut overlaps = Vec::new();
+  for t in &g_state.comp_coll_aabb {
+    if t.solid { continue; }
+    let t_aabb = g_state.comp_aabb.get_component(t.entity_id);
+    if t_aabb.is_none() { continue; }
+    let t_pos = Vec2::<S>::from_vec2f32(t_aabb.unwrap().pos) + Vec2::from_vec2f32(t.offset);
+
+    for c in &g_state.comp_coll_aabb {
+      if c.entity_id == t.entity_id { continue; }
+      if !layers_interact(t.layer, t.mask, c.layer, c.mask) { continue; }
+      let c_aabb = g_state.comp_aabb.get_component(c.entity_id);
+      if c_aabb.is_none() { continue; }
+      let c_pos = Vec2::<S>::from_vec2f32(c_aabb.unwrap().pos) + Vec2::from_vec2f32(c.offset);
+      if aabbs_overlap(t_pos, Vec2::from_vec2f32(t.size), c_pos, Vec2::from_vec2f32(c.size)) {
+        overlaps.push((t.entity_id, c.entity_id));
+      }
+    }
+  }
+  overlaps.sort();
+  overlaps
+}
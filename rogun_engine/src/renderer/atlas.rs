@@ -0,0 +1,127 @@
+use common::png;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Rectangle of a texture atlas in pixels, from the top left of the atlas.
+#[derive(Copy, Clone, PartialEq)]
+pub struct AtlasRegion {
+  pub x: u32,
+  pub y: u32,
+  pub w: u32,
+  pub h: u32,
+}
+
+/// A single image holding many sprites and tiles, so everything can be drawn
+/// with one texture. Regions of the atlas are named, and sprites and tiles
+/// refer to the region they draw.
+#[derive(Clone)]
+pub struct TextureAtlas {
+  width: u32,
+  height: u32,
+
+  /// 8 bit RGBA pixels, row by row from the top left.
+  pixels: Vec<u8>,
+
+  /// Named regions, in the order they were added.
+  regions: Vec<(String, AtlasRegion)>,
+}
+
+impl TextureAtlas {
+  /// Create an atlas from 8 bit RGBA pixels, row by row from the top left.
+  pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> TextureAtlas {
+    assert!(pixels.len() == width as usize * height as usize * 4);
+    TextureAtlas { width: width, height: height, pixels: pixels, regions: Vec::new() }
+  }
+
+  /// Create an atlas from a PNG file in memory.
+  pub fn from_png(data: &[u8]) -> Result<TextureAtlas, String> {
+    let (w, h, pixels) = png::decode_rgba(data)?;
+    Ok(TextureAtlas::from_rgba(w, h, pixels))
+  }
+
+  /// Load an atlas from a PNG file.
+  pub fn load_png(path: &Path) -> io::Result<TextureAtlas> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    TextureAtlas::from_png(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+
+  pub fn get_width(&self) -> u32 { self.width }
+  pub fn get_height(&self) -> u32 { self.height }
+
+  /// The atlas image, 8 bit RGBA pixels row by row from the top left.
+  pub fn get_pixels(&self) -> &[u8] { &self.pixels }
+
+  /// Get a pixel's RGBA value, clamping to the edge of the atlas. Returns
+  /// None if the atlas is empty.
+  pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+    if self.width == 0 || self.height == 0 { return None; }
+    let x = x.min(self.width - 1);
+    let y = y.min(self.height - 1);
+    let ix = (y * self.width + x) as usize * 4;
+    Some([self.pixels[ix], self.pixels[ix + 1], self.pixels[ix + 2], self.pixels[ix + 3]])
+  }
+
+  /// Name a region of the atlas, replacing any region with the same name.
+  /// Returns the region.
+  pub fn add_region(&mut self, name: &str, x: u32, y: u32, w: u32, h: u32) -> AtlasRegion {
+    let region = AtlasRegion { x: x, y: y, w: w, h: h };
+    self.regions.retain(|r| r.0 != name);
+    self.regions.push((name.to_owned(), region));
+    region
+  }
+
+  /// Add an image to the atlas under the existing one, widening the atlas if
+  /// the image is wider, and name its region. Existing regions stay where
+  /// they are. Call before uploading the atlas to a renderer. Returns the
+  /// region.
+  pub fn add_image(&mut self, name: &str, w: u32, h: u32, pixels: &[u8]) -> AtlasRegion {
+    assert!(pixels.len() == w as usize * h as usize * 4);
+    let width = self.width.max(w);
+    let mut new_pixels = vec![0; width as usize * (self.height + h) as usize * 4];
+    let (old_row, new_row, image_row) = (self.width as usize * 4, width as usize * 4, w as usize * 4);
+    for y in 0..self.height as usize {
+      new_pixels[y * new_row..y * new_row + old_row]
+        .copy_from_slice(&self.pixels[y * old_row..(y + 1) * old_row]);
+    }
+    for y in 0..h as usize {
+      let start = (self.height as usize + y) * new_row;
+      new_pixels[start..start + image_row].copy_from_slice(&pixels[y * image_row..(y + 1) * image_row]);
+    }
+    let y = self.height;
+    self.width = width;
+    self.height += h;
+    self.pixels = new_pixels;
+    self.add_region(name, 0, y, w, h)
+  }
+
+  /// Split the whole atlas into a grid of cells, named "name_0", "name_1" and
+  /// so on, left to right then top to bottom. Useful for tile sets. Returns
+  /// an error if either cell dimension is 0.
+  pub fn add_grid(&mut self, name: &str, cell_w: u32, cell_h: u32) -> Result<(), String> {
+    if cell_w == 0 || cell_h == 0 {
+      return Err(format!("grid cells must be at least 1x1, not {}x{}", cell_w, cell_h));
+    }
+    let cols = self.width / cell_w;
+    let rows = self.height / cell_h;
+    for ii in 0..cols * rows {
+      self.add_region(&format!("{}_{}", name, ii),
+                      (ii % cols) * cell_w, (ii / cols) * cell_h, cell_w, cell_h);
+    }
+    Ok(())
+  }
+
+  /// Get a region by name.
+  pub fn get_region(&self, name: &str) -> Option<AtlasRegion> {
+    self.regions.iter().find(|r| r.0 == name).map(|r| r.1)
+  }
+
+  /// Texture coordinates (u0, v0, u1, v1) of a region, from 0 to 1 with v
+  /// going down the atlas.
+  pub fn get_uvs(&self, region: AtlasRegion) -> (f32, f32, f32, f32) {
+    let (w, h) = (self.width as f32, self.height as f32);
+    (region.x as f32 / w, region.y as f32 / h,
+     (region.x + region.w) as f32 / w, (region.y + region.h) as f32 / h)
+  }
+}
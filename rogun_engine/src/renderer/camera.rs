@@ -0,0 +1,164 @@
+use common::vec::Vec2f32;
+use entity::EntityID;
+use entity::component_core::CompAABB;
+use state::comp_list::CompList;
+
+/// The part of the world shown on screen. The renderers draw the world from
+/// the camera, and the input system converts the cursor position with it.
+///
+/// A camera can follow a target entity, keeping its AABB centre inside the
+/// dead zone, and stay inside level bounds. Positions are in world units;
+/// zoom is the number of screen pixels per world unit.
+pub struct Camera {
+  /// World position shown at the top left of the screen.
+  pos: Vec2f32,
+  zoom: f32,
+
+  /// Size of the screen in pixels.
+  viewport: Vec2f32,
+
+  /// Entity to follow, if any.
+  pub target: Option<EntityID>,
+
+  /// How quickly the camera catches up with the target, in 1 / seconds. The
+  /// remaining distance shrinks by a factor of e every 1 / smoothing
+  /// seconds. 0 snaps straight to the target.
+  pub smoothing: f32,
+
+  /// Size of a rectangle in the centre of the view, in world units. The
+  /// target can move inside it without the camera moving.
+  pub dead_zone: Vec2f32,
+
+  /// (min, max) corners of the area the camera may show. If the level is
+  /// smaller than the view, the level is centred on screen.
+  pub bounds: Option<(Vec2f32, Vec2f32)>,
+}
+
+/// Clamp a view's position on one axis so [pos, pos + size] stays inside
+/// [min, max], centring it if it doesn't fit.
+fn clamp_axis(pos: f32, size: f32, min: f32, max: f32) -> f32 {
+  if max - min <= size {
+    return (min + max - size) / 2.0;
+  }
+  pos.max(min).min(max - size)
+}
+
+/// Move a view centre on one axis so the target is within half_dead_zone of
+/// it.
+fn follow_axis(centre: f32, target: f32, half_dead_zone: f32) -> f32 {
+  if target > centre + half_dead_zone {
+    return target - half_dead_zone;
+  }
+  if target < centre - half_dead_zone {
+    return target + half_dead_zone;
+  }
+  centre
+}
+
+impl Camera {
+  /// Create a camera showing the world from the origin at 1 pixel per world
+  /// unit, with no target or bounds. The viewport is empty until set.
+  pub fn new() -> Camera {
+    Camera {
+      pos: Vec2f32(0.0, 0.0),
+      zoom: 1.0,
+      viewport: Vec2f32(0.0, 0.0),
+      target: None,
+      smoothing: 0.0,
+      dead_zone: Vec2f32(0.0, 0.0),
+      bounds: None,
+    }
+  }
+
+  /// World position shown at the top left of the screen.
+  pub fn get_pos(&self) -> Vec2f32 { self.pos }
+  pub fn set_pos(&mut self, pos: Vec2f32) { self.pos = pos; }
+
+  /// Screen pixels per world unit.
+  pub fn get_zoom(&self) -> f32 { self.zoom }
+
+  /// Set the zoom, keeping the world position at the centre of the screen
+  /// where it is. Zooms of 0 or less are ignored.
+  pub fn set_zoom(&mut self, zoom: f32) {
+    if zoom <= 0.0 { return; }
+    let centre = self.get_centre();
+    self.zoom = zoom;
+    self.look_at(centre);
+  }
+
+  /// Size of the screen in pixels.
+  pub fn get_viewport(&self) -> Vec2f32 { self.viewport }
+
+  /// Set the size of the screen in pixels. Call when the window is resized.
+  pub fn set_viewport(&mut self, w: u32, h: u32) {
+    self.viewport = Vec2f32(w as f32, h as f32);
+  }
+
+  /// Size of the view in world units.
+  pub fn get_view_size(&self) -> Vec2f32 {
+    Vec2f32(self.viewport.0 / self.zoom, self.viewport.1 / self.zoom)
+  }
+
+  /// (min, max) corners of the world shown on screen.
+  pub fn get_view_rect(&self) -> (Vec2f32, Vec2f32) {
+    (self.pos, self.pos + self.get_view_size())
+  }
+
+  /// World position shown at the centre of the screen.
+  pub fn get_centre(&self) -> Vec2f32 {
+    let size = self.get_view_size();
+    Vec2f32(self.pos.0 + size.0 / 2.0, self.pos.1 + size.1 / 2.0)
+  }
+
+  /// Move the camera so a world position is at the centre of the screen.
+  pub fn look_at(&mut self, point: Vec2f32) {
+    let size = self.get_view_size();
+    self.pos = Vec2f32(point.0 - size.0 / 2.0, point.1 - size.1 / 2.0);
+  }
+
+  /// Convert a position in screen pixels to world coordinates.
+  pub fn screen_to_world(&self, point: Vec2f32) -> Vec2f32 {
+    Vec2f32(self.pos.0 + point.0 / self.zoom, self.pos.1 + point.1 / self.zoom)
+  }
+
+  /// Convert a world position to screen pixels.
+  pub fn world_to_screen(&self, point: Vec2f32) -> Vec2f32 {
+    Vec2f32((point.0 - self.pos.0) * self.zoom, (point.1 - self.pos.1) * self.zoom)
+  }
+
+  /// Move towards the target, then clamp to the bounds. Call once per frame,
+  /// after physics.
+  pub fn update(&mut self, comp_aabb: &CompList<CompAABB>, dt: f32) {
+    if let Some(target) = self.target.and_then(|t| comp_aabb.get_component(t)) {
+      let target = target.pos + target.size * 0.5;
+      let centre = self.get_centre();
+      let wanted = Vec2f32(follow_axis(centre.0, target.0, self.dead_zone.0 / 2.0),
+                           follow_axis(centre.1, target.1, self.dead_zone.1 / 2.0));
+      if self.smoothing <= 0.0 {
+        self.look_at(wanted);
+      } else {
+        let t = 1.0 - (-self.smoothing * dt).exp();
+        self.look_at(centre + (wanted - centre) * t);
+      }
+    }
+    self.clamp_to_bounds();
+  }
+
+  /// Move the camera straight to the target, ignoring smoothing and the
+  /// dead zone. Use after teleporting the target, e.g. on level load.
+  pub fn snap_to_target(&mut self, comp_aabb: &CompList<CompAABB>) {
+    if let Some(target) = self.target.and_then(|t| comp_aabb.get_component(t)) {
+      self.look_at(target.pos + target.size * 0.5);
+    }
+    self.clamp_to_bounds();
+  }
+
+  /// Keep the view inside the bounds, if there are any.
+  fn clamp_to_bounds(&mut self) {
+    if self.bounds.is_none() { return; }
+    let (min, max) = self.bounds.unwrap();
+    let size = self.get_view_size();
+    self.pos = Vec2f32(clamp_axis(self.pos.0, size.0, min.0, max.0),
+                       clamp_axis(self.pos.1, size.1, min.1, max.1));
+  }
+}
@@ -0,0 +1,266 @@
+use common::vec::Vec2f32;
+use common::color::RGBAf32;
+use renderer::atlas::AtlasRegion;
+use state::GameState;
+use tile::{TileBank, TileMap16};
+use std::i32;
+use std::mem;
+
+/// Layer for backgrounds, under the world.
+pub const RENDER_LAYER_BACKGROUND: i32 = -1000;
+
+/// Layer tiles are drawn on by default. This and RENDER_LAYER_SPRITES make
+/// up the world.
+pub const RENDER_LAYER_TILES: i32 = 0;
+
+/// Layer sprites are drawn on by default, over tiles.
+pub const RENDER_LAYER_SPRITES: i32 = 1;
+
+/// Layer for things in front of the world, like foliage the player walks
+/// behind.
+pub const RENDER_LAYER_FOREGROUND: i32 = 1000;
+
+/// Layer for the HUD and menus, over the world. Usually drawn in screen
+/// space.
+pub const RENDER_LAYER_UI: i32 = 2000;
+
+/// Layer debug draws are drawn on, over everything else.
+pub const RENDER_LAYER_DEBUG: i32 = i32::MAX;
+
+/// Opacity of debug draws, so what's under them stays visible.
+const DEBUG_DRAW_ALPHA: f32 = 0.5;
+
+/// Shape of a draw command, in world coordinates, or in pixels for screen
+/// space commands.
+#[derive(Copy, Clone, PartialEq)]
+pub enum DrawPrim {
+  /// Axis aligned rectangle from pos to pos + size.
+  Quad { pos: Vec2f32, size: Vec2f32 },
+
+  /// Region of the texture atlas drawn from pos to pos + size, then rotated
+  /// clockwise by rotation radians around its centre. The command's color
+  /// tints the sprite.
+  Sprite { pos: Vec2f32, size: Vec2f32, region: AtlasRegion,
+           flip_x: bool, flip_y: bool, rotation: f32 },
+}
+
+/// A single thing to draw. Backends draw commands in the order they're
+/// listed, alpha blending later commands over earlier ones.
+#[derive(Copy, Clone, PartialEq)]
+pub struct DrawCommand {
+  /// Commands on higher layers are drawn over lower layers.
+  pub layer: i32,
+  /// Commands with a higher z are drawn over others on the same layer.
+  pub z: i32,
+  /// Screen space commands are in pixels from the top left of the screen,
+  /// and aren't moved by the camera or culled.
+  pub screen_space: bool,
+  pub color: RGBAf32,
+  pub prim: DrawPrim,
+}
+
+/// How much of a frame was drawn, and how much was skipped for being
+/// outside the view.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct RenderStats {
+  pub tile_maps_drawn: u32,
+  pub tile_maps_culled: u32,
+  /// Entity draw commands, like sprites and debug draws.
+  pub commands_drawn: u32,
+  pub commands_culled: u32,
+}
+
+/// Corners of a rectangle from pos to pos + size, rotated clockwise around
+/// its centre. Returned clockwise from the (unrotated) top left.
+pub fn sprite_corners(pos: Vec2f32, size: Vec2f32, rotation: f32) -> [Vec2f32; 4] {
+  let half = Vec2f32(size.0 / 2.0, size.1 / 2.0);
+  let centre = pos + half;
+  let (sin, cos) = rotation.sin_cos();
+  let corner = |x: f32, y: f32| {
+    // y points down, so this rotation is clockwise on screen
+    Vec2f32(centre.0 + x * cos - y * sin, centre.1 + x * sin + y * cos)
+  };
+  [corner(-half.0, -half.1), corner(half.0, -half.1),
+   corner(half.0, half.1), corner(-half.0, half.1)]
+}
+
+/// Atlas pixel coordinates of the corners of a region, in the same order as
+/// sprite_corners(), after flipping.
+pub fn sprite_uvs(region: AtlasRegion, flip_x: bool, flip_y: bool) -> [(f32, f32); 4] {
+  let (mut x0, mut y0) = (region.x as f32, region.y as f32);
+  let (mut x1, mut y1) = (x0 + region.w as f32, y0 + region.h as f32);
+  if flip_x { mem::swap(&mut x0, &mut x1); }
+  if flip_y { mem::swap(&mut y0, &mut y1); }
+  [(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+}
+
+/// World space (min, max) bounds of everything a command draws.
+pub fn command_bounds(cmd: &DrawCommand) -> (Vec2f32, Vec2f32) {
+  let corners = match cmd.prim {
+    DrawPrim::Quad { pos, size } => return (pos, pos + size),
+    DrawPrim::Sprite { pos, size, rotation, .. } => sprite_corners(pos, size, rotation),
+  };
+  let mut min = corners[0];
+  let mut max = corners[0];
+  for c in &corners[1..] {
+    min = Vec2f32(min.0.min(c.0), min.1.min(c.1));
+    max = Vec2f32(max.0.max(c.0), max.1.max(c.1));
+  }
+  (min, max)
+}
+
+/// World space (min, max) bounds of a tile map.
+pub fn tile_map_bounds(t_map: &TileMap16) -> (Vec2f32, Vec2f32) {
+  (t_map.world_pos, t_map.tile_pos(16, 16))
+}
+
+/// Checks if (min, max) bounds overlap a (min, max) view rectangle. Bounds
+/// only touching the edge of the view aren't visible.
+pub fn in_view(bounds: (Vec2f32, Vec2f32), view: (Vec2f32, Vec2f32)) -> bool {
+  let ((min, max), (view_min, view_max)) = (bounds, view);
+  min.0 < view_max.0 && max.0 > view_min.0 && min.1 < view_max.1 && max.1 > view_min.1
+}
+
+/// Sort a draw list by layer, then z. The sort is stable, so commands with
+/// the same layer and z keep their order.
+pub fn sort_draw_list(list: &mut Vec<DrawCommand>) {
+  list.sort_by_key(|c| (c.layer, c.z));
+}
+
+/// Remove the world space commands outside a view rectangle, counting what
+/// was kept and removed in stats.
+pub fn cull_draw_list(list: &[DrawCommand], view: (Vec2f32, Vec2f32),
+                      stats: &mut RenderStats) -> Vec<DrawCommand> {
+  let visible: Vec<DrawCommand> = list.iter()
+    .filter(|c| c.screen_space || in_view(command_bounds(c), view))
+    .cloned()
+    .collect();
+  stats.commands_drawn += visible.len() as u32;
+  stats.commands_culled += (list.len() - visible.len()) as u32;
+  visible
+}
+
+/// Build the draw commands for a tile map's tiles, in tile order, on the
+/// map's layer. Unregistered tiles aren't drawn.
+pub fn build_tile_map_draw_list(t_map: &TileMap16, tile_bank: &TileBank) -> Vec<DrawCommand> {
+  let mut list = Vec::new();
+  for t_ix in 0..t_map.tiles.len() {
+    let t = t_map.tiles[t_ix];
+    // Get color...
+    let tile_data = tile_bank.get_tile(t);
+    if tile_data.is_none() { continue; }
+    let tile_data = tile_data.unwrap();
+    // Get position
+    let pos = t_map.tile_pos(t_ix % 16, t_ix / 16);
+    let size = Vec2f32(t_map.tile_size, t_map.tile_size);
+    list.push(match tile_data.region {
+      Some(region) => DrawCommand {
+        layer: t_map.layer,
+        z: 0,
+        screen_space: false,
+        color: RGBAf32::new(1.0, 1.0, 1.0, 1.0),
+        prim: DrawPrim::Sprite { pos: pos, size: size, region: region,
+                                 flip_x: false, flip_y: false, rotation: 0.0 },
+      },
+      None => DrawCommand {
+        layer: t_map.layer,
+        z: 0,
+        screen_space: false,
+        color: tile_data.color.with_alpha(1.0),
+        prim: DrawPrim::Quad { pos: pos, size: size },
+      },
+    });
+  }
+  list
+}
+
+/// Build the draw commands for a game state's entities, without tiles,
+/// sorted by layer and z. Commands with the same layer and z are in entity
+/// ID order.
+pub fn build_entity_draw_list(g_state: &GameState) -> Vec<DrawCommand> {
+  let mut list = Vec::new();
+
+  // Add sprites
+  for s in &g_state.comp_sprite {
+    let aabb = g_state.comp_aabb.get_component(s.entity_id);
+    if aabb.is_none() { continue; }
+    let aabb = aabb.unwrap();
+    list.push(DrawCommand {
+      layer: s.layer,
+      z: s.z,
+      screen_space: s.screen_space,
+      color: s.tint,
+      prim: DrawPrim::Sprite {
+        pos: aabb.pos + s.offset,
+        size: s.size,
+        region: s.region,
+        flip_x: s.flip_x,
+        flip_y: s.flip_y,
+        rotation: s.rotation,
+      },
+    });
+  }
+
+  // Add text
+  for t in &g_state.comp_text {
+    let aabb = g_state.comp_aabb.get_component(t.entity_id);
+    if aabb.is_none() { continue; }
+    let aabb = aabb.unwrap();
+    list.extend(t.font.build_draw_list(&t.text, aabb.pos + t.offset, &t.style));
+  }
+
+  // Add debug draws
+  for c in &g_state.comp_debug_draw {
+    // Find AABB
+    let aabb = g_state.comp_aabb.get_component(c.entity_id);
+    if aabb.is_none() { continue; }
+    let aabb = aabb.unwrap();
+    list.push(DrawCommand {
+      layer: RENDER_LAYER_DEBUG,
+      z: 0,
+      screen_space: false,
+      color: c.color.with_alpha(DEBUG_DRAW_ALPHA),
+      prim: DrawPrim::Quad { pos: aabb.pos, size: aabb.size },
+    });
+  }
+
+  sort_draw_list(&mut list);
+  list
+}
+
+/// Build the list of draw commands for a game state, sorted by layer and z.
+/// This doesn't need a GL context, so the output can be checked headlessly.
+/// Commands with the same layer and z keep the order they were added in:
+/// tile maps in order, then entities by ID.
+pub fn build_draw_list(g_state: &GameState, tile_bank: &TileBank) -> Vec<DrawCommand> {
+  let mut list = Vec::new();
+  for t_map in &g_state.tile_maps {
+    list.extend(build_tile_map_draw_list(t_map, tile_bank));
+  }
+  list.extend(build_entity_draw_list(g_state));
+
+  sort_draw_list(&mut list);
+  list
+}
+
+/// Build the draw list for the part of a game state inside a (min, max)
+/// view rectangle, like build_draw_list. Tile maps outside the view are
+/// skipped whole, and entity commands are culled one by one. What was drawn
+/// and culled is added to stats.
+pub fn build_visible_draw_list(g_state: &GameState, tile_bank: &TileBank,
+                               view: (Vec2f32, Vec2f32),
+                               stats: &mut RenderStats) -> Vec<DrawCommand> {
+  let mut list = Vec::new();
+  for t_map in &g_state.tile_maps {
+    if !in_view(tile_map_bounds(t_map), view) {
+      stats.tile_maps_culled += 1;
+      continue;
+    }
+    stats.tile_maps_drawn += 1;
+    list.extend(build_tile_map_draw_list(t_map, tile_bank));
+  }
+  list.extend(cull_draw_list(&build_entity_draw_list(g_state), view, stats));
+
+  sort_draw_list(&mut list);
+  list
+}
@@ -1,38 +1,142 @@
 use glium;
-use glium::uniforms::{UniformsStorage, EmptyUniforms};
+use glium::texture::{Texture2d, RawImage2d};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
 use glium::backend::glutin_backend::GlutinFacade;
+use common::vec::Vec2f32;
 use state::GameState;
 use tile::{TileBank};
 
+/// Building lists of things to draw, independent of the backend
+pub mod draw_list;
+
+/// Rendering on the CPU, for machines without a GPU
+pub mod software;
+
+/// Texture atlases, images holding many sprites
+pub mod atlas;
+
+/// Cameras, choosing the part of the world shown on screen
+pub mod camera;
+
+/// Caching tile map meshes between frames
+pub mod tile_mesh;
+
+/// Bitmap fonts and text layout
+pub mod text;
+
+use self::draw_list::*;
+use self::atlas::TextureAtlas;
+use self::tile_mesh::TileMeshCache;
+
+/// Vertex of the GL renderer's meshes, see build_vertices.
 #[derive(Copy, Clone)]
-struct Vertex {
+pub struct Vertex {
   position: [f32; 2],
   color: [f32; 4],
+  /// Texture coordinates into the atlas
+  tex_coords: [f32; 2],
+  /// 1 to multiply the color by the atlas, 0 for a flat color
+  textured: f32,
 }
 
-implement_vertex!(Vertex, position, color);
+implement_vertex!(Vertex, position, color, tex_coords, textured);
 
-pub struct Renderer<'a> {
+pub struct Renderer {
   program: glium::Program,
-  uniforms: UniformsStorage<'a, [[f32; 4]; 4], EmptyUniforms>,
+  proj_mat: [[f32; 4]; 4],
   view_w: u32,
   view_h: u32,
+  /// World position at the top left of the view, and pixels per world unit.
+  view_origin: Vec2f32,
+  view_scale: f32,
+
+  /// Texture atlas on the GPU, a single white pixel until set_atlas is
+  /// called.
+  atlas: Texture2d,
+  atlas_w: u32,
+  atlas_h: u32,
+
+  /// Vertex buffer for each tile map.
+  tile_meshes: TileMeshCache<glium::VertexBuffer<Vertex>>,
+
+  /// What the last render_game drew and culled.
+  stats: RenderStats,
+}
+
+/// Build the projection matrix for a view of size w x h pixels, showing the
+/// world from origin at scale pixels per world unit.
+fn proj_mat(w: u32, h: u32, origin: Vec2f32, scale: f32) -> [[f32; 4]; 4] {
+  // Orthographic proj mat:
+  // glOrtho(x, x + w / scale, y + h / scale, y, -1, 1);
+  let sx = 2.0 * scale / w as f32;
+  let sy = -2.0 * scale / h as f32;
+  [[sx,                  0.0,                 0.0, -0.0],
+   [0.0,                 sy,                  0.0,  0.0],
+   [0.0,                 0.0,                -1.0,  0.0],
+   [-origin.0 * sx - 1.0, -origin.1 * sy + 1.0, 0.0,  1.0]]
+}
+
+/// Upload an image to a texture. Rows are flipped, as GL textures start at
+/// the bottom left.
+fn upload_texture(display: &GlutinFacade, w: u32, h: u32, pixels: &[u8]) -> Texture2d {
+  let image = RawImage2d::from_raw_rgba_reversed(pixels.to_vec(), (w, h));
+  Texture2d::new(display, image).unwrap()
 }
 
-impl<'a> Renderer<'a> {
-  pub fn new(display: &GlutinFacade, w: u32, h: u32) -> Renderer<'a> {
+/// Build the vertices for a list of draw commands, two triangles per
+/// command. Texture coordinates are normalised by the atlas size.
+pub(crate) fn build_vertices(list: &[DrawCommand], atlas_w: u32, atlas_h: u32) -> Vec<Vertex> {
+  let mut data = Vec::with_capacity(list.len() * 6);
+
+  for cmd in list {
+    let color = [cmd.color.r, cmd.color.g, cmd.color.b, cmd.color.a];
+    // Corners clockwise from the top left, and their texture coordinates
+    let (corners, uvs, textured) = match cmd.prim {
+      DrawPrim::Quad { pos, size } => {
+        (sprite_corners(pos, size, 0.0), [[0.0, 0.0]; 4], 0.0)
+      }
+      DrawPrim::Sprite { pos, size, region, flip_x, flip_y, rotation } => {
+        let (w, h) = (atlas_w as f32, atlas_h as f32);
+        let uvs = sprite_uvs(region, flip_x, flip_y);
+        // v is flipped, as the atlas was uploaded upside down
+        let uvs = [[uvs[0].0 / w, 1.0 - uvs[0].1 / h], [uvs[1].0 / w, 1.0 - uvs[1].1 / h],
+                   [uvs[2].0 / w, 1.0 - uvs[2].1 / h], [uvs[3].0 / w, 1.0 - uvs[3].1 / h]];
+        (sprite_corners(pos, size, rotation), uvs, 1.0)
+      }
+    };
+    // Add data to VBO, two triangles per quad
+    for &ii in &[0, 1, 2, 0, 3, 2] {
+      data.push(Vertex {
+        position: [corners[ii].0, corners[ii].1],
+        color: color,
+        tex_coords: uvs[ii],
+        textured: textured,
+      });
+    }
+  }
+  data
+}
+
+impl Renderer {
+  pub fn new(display: &GlutinFacade, w: u32, h: u32) -> Renderer {
     // Vertex shader
     let vert_src = r#"
       #version 100
       attribute vec2 position;
       attribute vec4 color;
+      attribute vec2 tex_coords;
+      attribute float textured;
 
       varying vec4 v_color;
+      varying vec2 v_tex_coords;
+      varying float v_textured;
 
       uniform mat4 proj_mat;
 
       void main() {
           v_color = color;
+          v_tex_coords = tex_coords;
+          v_textured = textured;
           gl_Position = proj_mat * vec4(position, 0.0, 1.0);
       }
     "#;
@@ -43,24 +147,26 @@ impl<'a> Renderer<'a> {
       precision mediump float; // Float precision to medium
 
       varying vec4 v_color;
+      varying vec2 v_tex_coords;
+      varying float v_textured;
+
+      uniform sampler2D atlas;
 
       void main() {
-        gl_FragColor = v_color;
+        vec4 tex = texture2D(atlas, v_tex_coords);
+        gl_FragColor = v_color * mix(vec4(1.0), tex, v_textured);
       }
     "#;
 
-    let uniforms = UniformsStorage::new(
-      "proj_mat",
-      // Orthographic proj mat:
-      // glOrtho(0, w, h, 0, -1, 1);
-      [[2.0/w as f32, 0.0,           0.0, -0.0],
-       [0.0,         -2.0/h as f32,  0.0,  0.0],
-       [0.0,          0.0,          -1.0,  0.0],
-       [-1.0,          1.0,           0.0,  1.0]]);
-
     Renderer { 
-      uniforms: uniforms,
+      proj_mat: proj_mat(w, h, Vec2f32(0.0, 0.0), 1.0),
       view_w: w, view_h: h,
+      view_origin: Vec2f32(0.0, 0.0),
+      view_scale: 1.0,
+      atlas: upload_texture(display, 1, 1, &[255, 255, 255, 255]),
+      atlas_w: 1, atlas_h: 1,
+      tile_meshes: TileMeshCache::new(),
+      stats: RenderStats::default(),
       program: glium::Program::from_source(display,
                                            vert_src, 
                                            frag_src, 
@@ -68,92 +174,121 @@ impl<'a> Renderer<'a> {
     }
   }
 
-  pub fn render_game(&self, display: &GlutinFacade, 
+  /// Resize the view, rebuilding the projection matrix. Call when the window
+  /// is resized.
+  pub fn resize(&mut self, w: u32, h: u32) {
+    self.proj_mat = proj_mat(w, h, self.view_origin, self.view_scale);
+    self.view_w = w;
+    self.view_h = h;
+  }
+
+  /// Set the part of the world shown. origin is the world position at the
+  /// top left of the view, and scale is the number of pixels per world unit.
+  pub fn set_view(&mut self, origin: Vec2f32, scale: f32) {
+    self.proj_mat = proj_mat(self.view_w, self.view_h, origin, scale);
+    self.view_origin = origin;
+    self.view_scale = scale;
+  }
+
+  /// (min, max) corners of the world shown in the view.
+  pub fn get_view_rect(&self) -> (Vec2f32, Vec2f32) {
+    let size = Vec2f32(self.view_w as f32, self.view_h as f32) * (1.0 / self.view_scale);
+    (self.view_origin, self.view_origin + size)
+  }
+
+  /// How much the last render_game drew, and how much was outside the view.
+  pub fn get_stats(&self) -> RenderStats { self.stats }
+
+  /// Upload a texture atlas, replacing the current one. Sprites are drawn
+  /// from this.
+  pub fn set_atlas(&mut self, display: &GlutinFacade, atlas: &TextureAtlas) {
+    self.atlas = upload_texture(display, atlas.get_width(), atlas.get_height(), atlas.get_pixels());
+    self.atlas_w = atlas.get_width();
+    self.atlas_h = atlas.get_height();
+    // Tile meshes hold texture coordinates for the old atlas size
+    self.tile_meshes.clear();
+  }
+
+  /// Draw a game state from its camera. Tile maps are drawn from cached
+  /// meshes, rebuilt only when they change. Everything else is drawn from a
+  /// draw list built each frame. Tile maps and entities outside the view
+  /// are skipped.
+  pub fn render_game(&mut self, display: &GlutinFacade, 
                      target: &mut glium::Frame, 
                      g_state: &GameState,
                      tile_bank: &TileBank) {
-    use glium::Surface;
-    let mut data = Vec::new();
-
-    // Go through tiles, render...
-    for t_map in &g_state.tile_maps {
-      for t_ix in 0..t_map.tiles.len() {
-        let t = t_map.tiles[t_ix];
-        // Get color...
-        let tile_data = tile_bank.get_tile(t);
-        if tile_data.is_none() { continue; }
-        let c = tile_data.unwrap().color;
-        // Get position
-        let x = (t_ix % 16) as f32 * t_map.tile_size 
-          + t_map.world_pos.0;
-        let y = (t_ix / 16) as f32 * t_map.tile_size 
-          + t_map.world_pos.1;
-        // Add data to VBO
-        data.push(Vertex {
-          position: [x, y],
-          color: [c.r, c.g, c.b, 1.0],
-        });
-        data.push(Vertex {
-          position: [x + t_map.tile_size, y],
-          color: [c.r, c.g, c.b, 1.0],
-        });
-        data.push(Vertex {
-          position: [x + t_map.tile_size, y + t_map.tile_size],
-          color: [c.r, c.g, c.b, 1.0],
-        });
-        data.push(Vertex {
-          position: [x, y],
-          color: [c.r, c.g, c.b, 1.0],
-        });
-        data.push(Vertex {
-          position: [x, y + t_map.tile_size],
-          color: [c.r, c.g, c.b, 1.0],
-        });
-        data.push(Vertex {
-          position: [x + t_map.tile_size, y + t_map.tile_size],
-          color: [c.r, c.g, c.b, 1.0],
-        });
+    self.set_view(g_state.camera.get_pos(), g_state.camera.get_zoom());
+
+    let view = self.get_view_rect();
+    let (atlas_w, atlas_h) = (self.atlas_w, self.atlas_h);
+    self.tile_meshes.update(&g_state.tile_maps, tile_bank, view, |t_map| {
+      let list = build_tile_map_draw_list(t_map, tile_bank);
+      glium::VertexBuffer::new(display, &build_vertices(&list, atlas_w, atlas_h)).unwrap()
+    });
+
+    let mut stats = RenderStats::default();
+
+    // Tile maps in layer order, each drawn after the entity commands that
+    // sort before it. Tiles go under entities with the same layer and z = 0.
+    let list = cull_draw_list(&build_entity_draw_list(g_state), view, &mut stats);
+    let mut maps: Vec<usize> = (0..g_state.tile_maps.len()).collect();
+    maps.sort_by_key(|&ii| g_state.tile_maps[ii].layer);
+    let mut next = 0;
+    for ii in maps {
+      let t_map = &g_state.tile_maps[ii];
+      let mesh = self.tile_meshes.get_mesh(ii);
+      if !in_view(tile_map_bounds(t_map), view) || mesh.is_none() {
+        stats.tile_maps_culled += 1;
+        continue;
       }
+      stats.tile_maps_drawn += 1;
+      let end = list[next..].iter().position(|c| (c.layer, c.z) >= (t_map.layer, 0))
+        .map_or(list.len(), |p| next + p);
+      self.draw_list(display, target, &list[next..end]);
+      next = end;
+      self.draw_vertices(target, mesh.unwrap(), self.proj_mat);
     }
+    self.draw_list(display, target, &list[next..]);
+    self.stats = stats;
+  }
 
-    /// Add debug draws to the VBO
-    for c in &g_state.comp_debug_draw {
-      /// Find AABB
-      let aabb = g_state.comp_aabb.get_component(c.entity_id);
-      if aabb.is_none() { continue; }
-      let aabb = aabb.unwrap();
-
-      /// Push data to VBO
-      data.push(Vertex {
-        position: [aabb.pos.0, aabb.pos.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
-      data.push(Vertex {
-        position: [aabb.pos.0 + aabb.size.0, aabb.pos.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
-      data.push(Vertex {
-        position: [aabb.pos.0 + aabb.size.0, aabb.pos.1 + aabb.size.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
-      data.push(Vertex {
-        position: [aabb.pos.0, aabb.pos.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
-      data.push(Vertex {
-        position: [aabb.pos.0, aabb.pos.1 + aabb.size.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
-      data.push(Vertex {
-        position: [aabb.pos.0 + aabb.size.0, aabb.pos.1 + aabb.size.1],
-        color: [c.color.r, c.color.g, c.color.b, 1.0],
-      });
+  /// Draw a list of draw commands, in order, with the current view. Screen
+  /// space commands are drawn in pixels instead.
+  pub fn draw_list(&self, display: &GlutinFacade,
+                   target: &mut glium::Frame,
+                   list: &[DrawCommand]) {
+    let screen_mat = proj_mat(self.view_w, self.view_h, Vec2f32(0.0, 0.0), 1.0);
+    // Draw runs of commands in the same space together
+    let mut start = 0;
+    while start < list.len() {
+      let screen_space = list[start].screen_space;
+      let end = list[start..].iter().position(|c| c.screen_space != screen_space)
+        .map_or(list.len(), |p| start + p);
+      let data = build_vertices(&list[start..end], self.atlas_w, self.atlas_h);
+      let vbo = glium::VertexBuffer::new(display, &data).unwrap();
+      self.draw_vertices(target, &vbo, if screen_space { screen_mat } else { self.proj_mat });
+      start = end;
     }
+  }
 
-    let vbo = glium::VertexBuffer::new(display, &data).unwrap();
+  /// Draw a vertex buffer of triangles with a projection matrix and the
+  /// atlas, alpha blending them over what's already drawn.
+  fn draw_vertices(&self, target: &mut glium::Frame, vbo: &glium::VertexBuffer<Vertex>,
+                   proj_mat: [[f32; 4]; 4]) {
+    use glium::Surface;
+    if vbo.len() == 0 { return; }
     let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
 
-    let mut draw_params = glium::draw_parameters::DrawParameters::default();
-    target.draw(&vbo, indices, &self.program, &self.uniforms, &draw_params).unwrap();
+    let draw_params = glium::draw_parameters::DrawParameters {
+      blend: glium::Blend::alpha_blending(),
+      .. Default::default()
+    };
+    let uniforms = uniform! {
+      proj_mat: proj_mat,
+      atlas: self.atlas.sampled()
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .minify_filter(MinifySamplerFilter::Nearest),
+    };
+    target.draw(vbo, indices, &self.program, &uniforms, &draw_params).unwrap();
   }
 }
@@ -0,0 +1,228 @@
+use common::color::RGBAf32;
+use common::vec::Vec2f32;
+use common::png;
+use renderer::draw_list::*;
+use renderer::atlas::TextureAtlas;
+use state::GameState;
+use tile::TileBank;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::f32;
+
+/// Renders draw lists on the CPU into an RGBA framebuffer, for machines
+/// without a GPU. Draws the same thing as the GL renderer. Until set_view is
+/// called, the framebuffer shows the world from the origin at one pixel per
+/// world unit.
+pub struct SoftwareRenderer {
+  width: u32,
+  height: u32,
+
+  /// World position at the top left of the framebuffer, and pixels per world
+  /// unit.
+  view_origin: Vec2f32,
+  view_scale: f32,
+
+  /// What the last render_game drew and culled.
+  stats: RenderStats,
+
+  /// 8 bit RGBA pixels, row by row from the top left.
+  pixels: Vec<u8>,
+
+  /// Atlas sprites are drawn from. Without one, sprites are drawn as flat
+  /// rectangles of their tint, like the GL renderer.
+  atlas: Option<TextureAtlas>,
+}
+
+/// Returns true if both parts of a vector are finite. Commands with NaN or
+/// infinite positions or sizes are skipped, rather than filling whole rows.
+fn is_finite(v: Vec2f32) -> bool {
+  v.0.is_finite() && v.1.is_finite()
+}
+
+/// Convert a color channel from 0 - 1 to 0 - 255.
+fn to_u8(v: f32) -> u8 {
+  (v.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+impl SoftwareRenderer {
+  /// Create a renderer with a framebuffer cleared to transparent black.
+  pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+    SoftwareRenderer {
+      width: width,
+      height: height,
+      view_origin: Vec2f32(0.0, 0.0),
+      view_scale: 1.0,
+      stats: RenderStats::default(),
+      pixels: vec![0; width as usize * height as usize * 4],
+      atlas: None,
+    }
+  }
+
+  pub fn get_width(&self) -> u32 { self.width }
+  pub fn get_height(&self) -> u32 { self.height }
+
+  /// The framebuffer, 8 bit RGBA pixels row by row from the top left.
+  pub fn get_pixels(&self) -> &[u8] { &self.pixels }
+
+  /// Get a pixel's RGBA value. Panics if it's outside the framebuffer.
+  pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+    assert!(x < self.width && y < self.height);
+    let ix = (y * self.width + x) as usize * 4;
+    [self.pixels[ix], self.pixels[ix + 1], self.pixels[ix + 2], self.pixels[ix + 3]]
+  }
+
+  /// Set the texture atlas sprites are drawn from.
+  pub fn set_atlas(&mut self, atlas: &TextureAtlas) {
+    self.atlas = Some(atlas.clone());
+  }
+
+  /// Set the part of the world shown. origin is the world position at the
+  /// top left of the framebuffer, and scale is the number of pixels per world
+  /// unit.
+  pub fn set_view(&mut self, origin: Vec2f32, scale: f32) {
+    self.view_origin = origin;
+    self.view_scale = scale;
+  }
+
+  /// (min, max) corners of the world shown in the framebuffer.
+  pub fn get_view_rect(&self) -> (Vec2f32, Vec2f32) {
+    let size = Vec2f32(self.width as f32, self.height as f32) * (1.0 / self.view_scale);
+    (self.view_origin, self.view_origin + size)
+  }
+
+  /// How much the last render_game drew, and how much was outside the view.
+  pub fn get_stats(&self) -> RenderStats { self.stats }
+
+  /// Fill the framebuffer with a color.
+  pub fn clear(&mut self, color: RGBAf32) {
+    let c = [to_u8(color.r), to_u8(color.g), to_u8(color.b), to_u8(color.a)];
+    for p in self.pixels.chunks_mut(4) {
+      p.copy_from_slice(&c);
+    }
+  }
+
+  /// Draw a game state from its camera over a black background, like the GL
+  /// renderer. Tile maps and entities outside the view are skipped.
+  pub fn render_game(&mut self, g_state: &GameState, tile_bank: &TileBank) {
+    self.set_view(g_state.camera.get_pos(), g_state.camera.get_zoom());
+    self.clear(RGBAf32::new(0.0, 0.0, 0.0, 1.0));
+    let mut stats = RenderStats::default();
+    let list = build_visible_draw_list(g_state, tile_bank, self.get_view_rect(), &mut stats);
+    self.draw_list(&list);
+    self.stats = stats;
+  }
+
+  /// Draw a list of draw commands, in order, with the current view.
+  pub fn draw_list(&mut self, list: &[DrawCommand]) {
+    for cmd in list {
+      let cmd = &self.to_screen(cmd);
+      match cmd.prim {
+        DrawPrim::Quad { pos, size } => {
+          if !is_finite(pos) || !is_finite(size) { continue; }
+          // Fill pixels whose centres are inside the quad, like GL
+          let x0 = (pos.0 - 0.5).ceil().max(0.0) as i64;
+          let y0 = (pos.1 - 0.5).ceil().max(0.0) as i64;
+          let x1 = (pos.0 + size.0 - 0.5).ceil().min(self.width as f32) as i64;
+          let y1 = (pos.1 + size.1 - 0.5).ceil().min(self.height as f32) as i64;
+          for y in y0..y1 {
+            for x in x0..x1 {
+              self.blend(x as u32, y as u32, cmd.color);
+            }
+          }
+        }
+        DrawPrim::Sprite { .. } => self.draw_sprite(cmd),
+      }
+    }
+  }
+
+  /// Move a command from world coordinates to framebuffer pixels. Screen
+  /// space commands are already in pixels.
+  fn to_screen(&self, cmd: &DrawCommand) -> DrawCommand {
+    if cmd.screen_space { return *cmd; }
+    let (origin, scale) = (self.view_origin, self.view_scale);
+    let prim = match cmd.prim {
+      DrawPrim::Quad { pos, size } =>
+        DrawPrim::Quad { pos: (pos - origin) * scale, size: size * scale },
+      DrawPrim::Sprite { pos, size, region, flip_x, flip_y, rotation } =>
+        DrawPrim::Sprite { pos: (pos - origin) * scale, size: size * scale, region: region,
+                           flip_x: flip_x, flip_y: flip_y, rotation: rotation },
+    };
+    DrawCommand { prim: prim, ..*cmd }
+  }
+
+  /// Draw a region of the atlas, sampling the nearest texel to each pixel's
+  /// centre.
+  fn draw_sprite(&mut self, cmd: &DrawCommand) {
+    let (pos, size, region, flip_x, flip_y, rotation) = match cmd.prim {
+      DrawPrim::Sprite { pos, size, region, flip_x, flip_y, rotation } =>
+        (pos, size, region, flip_x, flip_y, rotation),
+      _ => return,
+    };
+    let tint = cmd.color;
+    if !is_finite(pos) || !is_finite(size) || !rotation.is_finite() { return; }
+    if size.0 <= 0.0 || size.1 <= 0.0 { return; }
+
+    // Pixels covering the rotated sprite
+    let corners = sprite_corners(pos, size, rotation);
+    let min_x = corners.iter().fold(f32::MAX, |m, c| m.min(c.0));
+    let min_y = corners.iter().fold(f32::MAX, |m, c| m.min(c.1));
+    let max_x = corners.iter().fold(f32::MIN, |m, c| m.max(c.0));
+    let max_y = corners.iter().fold(f32::MIN, |m, c| m.max(c.1));
+    let x0 = (min_x - 0.5).ceil().max(0.0) as i64;
+    let y0 = (min_y - 0.5).ceil().max(0.0) as i64;
+    let x1 = (max_x - 0.5).ceil().min(self.width as f32) as i64;
+    let y1 = (max_y - 0.5).ceil().min(self.height as f32) as i64;
+
+    let centre = pos + Vec2f32(size.0 / 2.0, size.1 / 2.0);
+    let (sin, cos) = rotation.sin_cos();
+    for y in y0..y1 {
+      for x in x0..x1 {
+        // Rotate the pixel centre back into the sprite's space, 0 - 1
+        let (dx, dy) = (x as f32 + 0.5 - centre.0, y as f32 + 0.5 - centre.1);
+        let u = (dx * cos + dy * sin) / size.0 + 0.5;
+        let v = (dy * cos - dx * sin) / size.1 + 0.5;
+        if u < 0.0 || u >= 1.0 || v < 0.0 || v >= 1.0 { continue; }
+
+        let texel = match self.atlas {
+          Some(ref atlas) => {
+            let u = if flip_x { 1.0 - u } else { u };
+            let v = if flip_y { 1.0 - v } else { v };
+            let tx = ((u * region.w as f32) as u32).min(region.w.max(1) - 1);
+            let ty = ((v * region.h as f32) as u32).min(region.h.max(1) - 1);
+            atlas.get_pixel(region.x + tx, region.y + ty).unwrap_or([0, 0, 0, 0])
+          }
+          None => [255, 255, 255, 255],
+        };
+        let color = RGBAf32::new(texel[0] as f32 / 255.0 * tint.r,
+                                 texel[1] as f32 / 255.0 * tint.g,
+                                 texel[2] as f32 / 255.0 * tint.b,
+                                 texel[3] as f32 / 255.0 * tint.a);
+        self.blend(x as u32, y as u32, color);
+      }
+    }
+  }
+
+  /// Alpha blend a color over a pixel.
+  fn blend(&mut self, x: u32, y: u32, color: RGBAf32) {
+    let ix = (y * self.width + x) as usize * 4;
+    let dst = &mut self.pixels[ix..ix + 4];
+    let a = color.a.max(0.0).min(1.0);
+    let src = [color.r, color.g, color.b];
+    for c in 0..3 {
+      dst[c] = to_u8(src[c] * a + dst[c] as f32 / 255.0 * (1.0 - a));
+    }
+    dst[3] = to_u8(a + dst[3] as f32 / 255.0 * (1.0 - a));
+  }
+
+  /// Encode the framebuffer as a PNG file.
+  pub fn to_png(&self) -> Vec<u8> {
+    png::encode_rgba(self.width, self.height, &self.pixels)
+  }
+
+  /// Save the framebuffer to a PNG file.
+  pub fn save_png(&self, path: &Path) -> io::Result<()> {
+    let mut f = File::create(path)?;
+    f.write_all(&self.to_png())
+  }
+}
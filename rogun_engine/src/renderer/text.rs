@@ -0,0 +1,303 @@
+use common::color::RGBAf32;
+use common::png;
+use common::vec::Vec2f32;
+use renderer::atlas::{AtlasRegion, TextureAtlas};
+use renderer::draw_list::*;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// A character's image in the atlas, and how to place it.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Glyph {
+  pub region: AtlasRegion,
+  /// Offset of the image from the pen position, at the top of the line.
+  pub offset: Vec2f32,
+  /// How far to move the pen after drawing this character.
+  pub advance: f32,
+}
+
+/// Horizontal alignment of lines of text.
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextAlign {
+  Left,
+  Centre,
+  Right,
+}
+
+/// How to lay out and draw a string.
+#[derive(Copy, Clone, PartialEq)]
+pub struct TextStyle {
+  /// World units (or pixels, in screen space) per font pixel.
+  pub scale: f32,
+  pub color: RGBAf32,
+  /// Lines are aligned within the wrap width, or within the widest line
+  /// when not wrapping.
+  pub align: TextAlign,
+  /// Break lines between words so they fit in this width, if set. Words
+  /// wider than this are left on a line of their own.
+  pub wrap_width: Option<f32>,
+
+  /// Render layer and order within it, like CompSprite.
+  pub layer: i32,
+  pub z: i32,
+  /// Position and size the text in screen pixels, ignoring the camera.
+  pub screen_space: bool,
+}
+
+impl TextStyle {
+  /// White, left aligned, unwrapped world space text at 1 world unit per
+  /// font pixel, on RENDER_LAYER_FOREGROUND.
+  pub fn new() -> TextStyle {
+    TextStyle {
+      scale: 1.0,
+      color: RGBAf32::new(1.0, 1.0, 1.0, 1.0),
+      align: TextAlign::Left,
+      wrap_width: None,
+      layer: RENDER_LAYER_FOREGROUND,
+      z: 0,
+      screen_space: false,
+    }
+  }
+}
+
+/// A font drawn from images of each character in the texture atlas. Loaded
+/// from a BMFont text file, or from a PNG grid of equally sized characters.
+pub struct BitmapFont {
+  /// Distance between lines, in font pixels.
+  line_height: f32,
+
+  /// Glyphs, sorted by character.
+  glyphs: Vec<(char, Glyph)>,
+
+  /// Extra advance between pairs of characters, sorted by pair.
+  kerning: Vec<((char, char), f32)>,
+}
+
+/// Get the value of key=value in a line of a BMFont file, without quotes.
+fn bmfont_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+  let start = line.find(&format!(" {}=", key))? + key.len() + 2;
+  let rest = &line[start..];
+  if rest.starts_with('"') {
+    return rest[1..].split('"').next();
+  }
+  rest.split_whitespace().next()
+}
+
+/// Get a number from a line of a BMFont file.
+fn bmfont_number(line: &str, key: &str) -> Result<i32, String> {
+  bmfont_value(line, key).and_then(|v| v.parse().ok())
+    .ok_or_else(|| format!("Invalid BMFont line, missing {}: {}", key, line))
+}
+
+/// Convert a BMFont character ID to a char.
+fn bmfont_char(id: i32) -> Option<char> {
+  if id < 0 { return None; }
+  ::std::char::from_u32(id as u32)
+}
+
+impl BitmapFont {
+  /// Create a font from a grid of equally sized characters filling a region
+  /// of the atlas, left to right then top to bottom, starting with
+  /// first_char. Each character advances by the cell width. Cells must be
+  /// at least 1x1.
+  pub fn from_grid(region: AtlasRegion, cell_w: u32, cell_h: u32,
+                   first_char: char) -> Result<BitmapFont, String> {
+    if cell_w == 0 || cell_h == 0 {
+      return Err(format!("font grid cells must be at least 1x1, not {}x{}", cell_w, cell_h));
+    }
+    let cols = region.w / cell_w;
+    let rows = region.h / cell_h;
+    let mut glyphs = Vec::new();
+    for ii in 0..cols * rows {
+      let c = ::std::char::from_u32(first_char as u32 + ii);
+      if c.is_none() { continue; }
+      glyphs.push((c.unwrap(), Glyph {
+        region: AtlasRegion { x: region.x + (ii % cols) * cell_w, y: region.y + (ii / cols) * cell_h,
+                              w: cell_w, h: cell_h },
+        offset: Vec2f32(0.0, 0.0),
+        advance: cell_w as f32,
+      }));
+    }
+    Ok(BitmapFont { line_height: cell_h as f32, glyphs: glyphs, kerning: Vec::new() })
+  }
+
+  /// Parse a font in the BMFont text format. Glyph positions are relative to
+  /// page, the region of the atlas holding the font's image. Only single
+  /// page fonts are supported.
+  pub fn from_bmfont(fnt: &str, page: AtlasRegion) -> Result<BitmapFont, String> {
+    let mut font = BitmapFont { line_height: 0.0, glyphs: Vec::new(), kerning: Vec::new() };
+    for line in fnt.lines() {
+      let line = line.trim();
+      let tag = line.split_whitespace().next().unwrap_or("");
+      // Pad so every key has a space before it
+      let line = &format!(" {}", line);
+      match tag {
+        "common" => {
+          font.line_height = bmfont_number(line, "lineHeight")? as f32;
+          if bmfont_number(line, "pages")? != 1 {
+            return Err("Only single page BMFonts are supported".to_owned());
+          }
+        }
+        "char" => {
+          let c = bmfont_char(bmfont_number(line, "id")?);
+          if c.is_none() { continue; }
+          let (x, y) = (bmfont_number(line, "x")?, bmfont_number(line, "y")?);
+          let (w, h) = (bmfont_number(line, "width")?, bmfont_number(line, "height")?);
+          if x < 0 || y < 0 || w < 0 || h < 0 || (x + w) as u32 > page.w || (y + h) as u32 > page.h {
+            return Err(format!("BMFont character outside its page: {}", line));
+          }
+          font.glyphs.push((c.unwrap(), Glyph {
+            region: AtlasRegion { x: page.x + x as u32, y: page.y + y as u32, w: w as u32, h: h as u32 },
+            offset: Vec2f32(bmfont_number(line, "xoffset")? as f32, bmfont_number(line, "yoffset")? as f32),
+            advance: bmfont_number(line, "xadvance")? as f32,
+          }));
+        }
+        "kerning" => {
+          let first = bmfont_char(bmfont_number(line, "first")?);
+          let second = bmfont_char(bmfont_number(line, "second")?);
+          if first.is_none() || second.is_none() { continue; }
+          font.kerning.push(((first.unwrap(), second.unwrap()), bmfont_number(line, "amount")? as f32));
+        }
+        _ => {}
+      }
+    }
+    font.glyphs.sort_by_key(|g| g.0);
+    font.kerning.sort_by_key(|k| k.0);
+    Ok(font)
+  }
+
+  /// Load a BMFont text file, adding its page image (a PNG next to it) to
+  /// the atlas. Load fonts before uploading the atlas to a renderer.
+  pub fn load_bmfont(path: &Path, atlas: &mut TextureAtlas) -> io::Result<BitmapFont> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut fnt = String::new();
+    File::open(path)?.read_to_string(&mut fnt)?;
+
+    let page_line = fnt.lines().find(|l| l.trim().starts_with("page "));
+    let file = page_line.and_then(|l| bmfont_value(&format!(" {}", l.trim()), "file").map(|f| f.to_owned()));
+    if file.is_none() {
+      return Err(invalid(format!("BMFont has no page: {}", path.display())));
+    }
+    let file = file.unwrap();
+    let mut data = Vec::new();
+    File::open(path.with_file_name(&file))?.read_to_end(&mut data)?;
+    let (w, h, pixels) = png::decode_rgba(&data).map_err(&invalid)?;
+
+    let page = atlas.add_image(&file, w, h, &pixels);
+    BitmapFont::from_bmfont(&fnt, page).map_err(invalid)
+  }
+
+  /// Distance between lines, in font pixels.
+  pub fn get_line_height(&self) -> f32 { self.line_height }
+
+  /// Get a character's glyph, if the font has it.
+  pub fn get_glyph(&self, c: char) -> Option<Glyph> {
+    self.glyphs.binary_search_by_key(&c, |g| g.0).ok().map(|ix| self.glyphs[ix].1)
+  }
+
+  /// Extra advance between a pair of characters, in font pixels.
+  pub fn get_kerning(&self, first: char, second: char) -> f32 {
+    self.kerning.binary_search_by_key(&(first, second), |k| k.0).ok().map_or(0.0, |ix| self.kerning[ix].1)
+  }
+
+  /// Glyph drawn for a character. Characters the font doesn't have are
+  /// drawn as '?', or skipped if there's no '?' either.
+  fn glyph_or_fallback(&self, c: char) -> Option<Glyph> {
+    self.get_glyph(c).or_else(|| self.get_glyph('?'))
+  }
+
+  /// Width of a line of text, in font pixels.
+  fn line_width(&self, line: &str) -> f32 {
+    let mut width = 0.0;
+    let mut prev = None;
+    for c in line.chars() {
+      if let Some(p) = prev { width += self.get_kerning(p, c); }
+      width += self.glyph_or_fallback(c).map_or(0.0, |g| g.advance);
+      prev = Some(c);
+    }
+    width
+  }
+
+  /// Split text into lines at newlines, and between words to fit the wrap
+  /// width.
+  fn wrap_lines(&self, text: &str, style: &TextStyle) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+      if style.wrap_width.is_none() {
+        lines.push(paragraph.to_owned());
+        continue;
+      }
+      let wrap_width = style.wrap_width.unwrap() / style.scale;
+      let mut line = String::new();
+      for word in paragraph.split(' ') {
+        let candidate = if line.is_empty() { word.to_owned() } else { format!("{} {}", line, word) };
+        if !line.is_empty() && self.line_width(&candidate) > wrap_width {
+          lines.push(line);
+          line = word.to_owned();
+        } else {
+          line = candidate;
+        }
+      }
+      lines.push(line);
+    }
+    lines
+  }
+
+  /// Size of text laid out in a style. The width is the wrap width if
+  /// wrapping, or the widest line.
+  pub fn measure(&self, text: &str, style: &TextStyle) -> Vec2f32 {
+    let lines = self.wrap_lines(text, style);
+    let width = match style.wrap_width {
+      Some(w) => w,
+      None => lines.iter().fold(0.0, |w: f32, l| w.max(self.line_width(l))) * style.scale,
+    };
+    Vec2f32(width, lines.len() as f32 * self.line_height * style.scale)
+  }
+
+  /// Lay out text with its top left at pos, and build a sprite draw command
+  /// for each visible character. Draw them with a renderer's draw_list, or
+  /// attach the text to an entity with CompText.
+  pub fn build_draw_list(&self, text: &str, pos: Vec2f32, style: &TextStyle) -> Vec<DrawCommand> {
+    let block_w = self.measure(text, style).0;
+    let mut list = Vec::new();
+    for (ii, line) in self.wrap_lines(text, style).iter().enumerate() {
+      let line_w = self.line_width(line) * style.scale;
+      let x = match style.align {
+        TextAlign::Left => 0.0,
+        TextAlign::Centre => (block_w - line_w) / 2.0,
+        TextAlign::Right => block_w - line_w,
+      };
+      let line_pos = pos + Vec2f32(x, ii as f32 * self.line_height * style.scale);
+
+      // Pen position along the line, in font pixels
+      let mut pen = 0.0;
+      let mut prev = None;
+      for c in line.chars() {
+        if let Some(p) = prev { pen += self.get_kerning(p, c); }
+        prev = Some(c);
+        let glyph = self.glyph_or_fallback(c);
+        if glyph.is_none() { continue; }
+        let glyph = glyph.unwrap();
+        if glyph.region.w > 0 && glyph.region.h > 0 && c != ' ' {
+          list.push(DrawCommand {
+            layer: style.layer,
+            z: style.z,
+            screen_space: style.screen_space,
+            color: style.color,
+            prim: DrawPrim::Sprite {
+              pos: line_pos + Vec2f32(pen + glyph.offset.0, glyph.offset.1) * style.scale,
+              size: Vec2f32(glyph.region.w as f32, glyph.region.h as f32) * style.scale,
+              region: glyph.region,
+              flip_x: false,
+              flip_y: false,
+              rotation: 0.0,
+            },
+          });
+        }
+        pen += glyph.advance;
+      }
+    }
+    list
+  }
+}
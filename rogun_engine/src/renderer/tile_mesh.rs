@@ -0,0 +1,94 @@
+use common::vec::Vec2f32;
+use renderer::draw_list::{in_view, tile_map_bounds};
+use tile::{TileBank, TileMap16};
+
+/// Everything a tile map's mesh is built from. If this is the same as last
+/// frame, the mesh is too.
+struct MeshSource {
+  world_pos: Vec2f32,
+  tile_size: f32,
+  tiles: [u16; 16*16],
+  bank_version: u64,
+}
+
+impl MeshSource {
+  fn new(t_map: &TileMap16, tile_bank: &TileBank) -> MeshSource {
+    MeshSource {
+      world_pos: t_map.world_pos,
+      tile_size: t_map.tile_size,
+      tiles: t_map.tiles,
+      bank_version: tile_bank.get_version(),
+    }
+  }
+
+  fn matches(&self, t_map: &TileMap16, tile_bank: &TileBank) -> bool {
+    self.bank_version == tile_bank.get_version()
+      && self.world_pos == t_map.world_pos
+      && self.tile_size == t_map.tile_size
+      && self.tiles[..] == t_map.tiles[..]
+  }
+}
+
+/// Caches a mesh per tile map, so tiles aren't rebuilt every frame. A map's
+/// mesh is only rebuilt when its tiles, position or tile size change, or
+/// when a tile is registered in the tile bank, and only while the map is in
+/// view. Maps that change while out of view are rebuilt once they're seen.
+///
+/// Generic over the mesh type: the GL renderer caches vertex buffers, but
+/// anything built from a tile map works.
+pub struct TileMeshCache<M> {
+  /// One entry per tile map, in the same order as GameState::tile_maps.
+  /// None until the map is first seen, and after it changes out of view.
+  meshes: Vec<Option<(MeshSource, M)>>,
+
+  /// Number of meshes built since the cache was created.
+  rebuilds: u64,
+}
+
+impl<M> TileMeshCache<M> {
+  pub fn new() -> TileMeshCache<M> {
+    TileMeshCache { meshes: Vec::new(), rebuilds: 0 }
+  }
+
+  /// Bring the cache up to date with a list of tile maps, calling build for
+  /// each map inside the (min, max) view rectangle that's new or has changed
+  /// since the last update. Changed maps outside the view drop their mesh
+  /// instead, and are built when they come into view.
+  pub fn update<F>(&mut self, tile_maps: &[TileMap16], tile_bank: &TileBank,
+                   view: (Vec2f32, Vec2f32), mut build: F)
+    where F: FnMut(&TileMap16) -> M {
+    self.meshes.truncate(tile_maps.len());
+    while self.meshes.len() < tile_maps.len() {
+      self.meshes.push(None);
+    }
+    for (t_map, entry) in tile_maps.iter().zip(self.meshes.iter_mut()) {
+      let fresh = entry.as_ref().map_or(false, |e| e.0.matches(t_map, tile_bank));
+      if fresh {
+        continue;
+      }
+      if !in_view(tile_map_bounds(t_map), view) {
+        *entry = None;
+        continue;
+      }
+      *entry = Some((MeshSource::new(t_map, tile_bank), build(t_map)));
+      self.rebuilds += 1;
+    }
+  }
+
+  /// Throw away every mesh, so they're all rebuilt on the next update. Call
+  /// when something else meshes depend on changes, like the texture atlas.
+  pub fn clear(&mut self) {
+    self.meshes.clear();
+  }
+
+  /// Cached mesh of the tile map at index ix of the maps passed to update.
+  /// None if that map hasn't been in view since it last changed.
+  pub fn get_mesh(&self, ix: usize) -> Option<&M> {
+    self.meshes.get(ix).and_then(|e| e.as_ref()).map(|e| &e.1)
+  }
+
+  /// Number of meshes built since the cache was created.
+  pub fn get_rebuild_count(&self) -> u64 {
+    self.rebuilds
+  }
+}
@@ -117,6 +117,15 @@ impl<T : Component> CompList<T> {
   pub fn remove(&mut self, index: usize) {
     self.list.remove(index);
   }
+
+  /// Removes the component belonging to the entity ID given, if there is one.
+  /// @param entity_id The ID of the entity who owns the component to remove.
+  pub fn remove_component(&mut self, entity_id: EntityID) {
+    let index = self.get_component_index(entity_id);
+    if index.is_some() {
+      self.list.remove(index.unwrap());
+    }
+  }
 }
 
 /// Implement the IntoIterator for CompList ref, lets us iterate over the list
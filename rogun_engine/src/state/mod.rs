@@ -2,7 +2,10 @@ use entity::component_core::*;
 use entity::component_render::*;
 use entity::component_collision::*;
 use entity::component_control::*;
+use entity::EntityID;
 use tile::TileMap16;
+use common::rng::Rng;
+use renderer::camera::Camera;
 
 pub mod comp_list;
 
@@ -10,26 +13,161 @@ use self::comp_list::CompList;
 
 pub struct GameState<'a> {
   pub comp_debug_draw: CompList<CompDebugDraw>,
+  pub comp_sprite: CompList<CompSprite>,
+  pub comp_text: CompList<CompText>,
   pub comp_aabb: CompList<CompAABB>,
   pub comp_body: CompList<CompBody>,
   pub comp_coll_aabb: CompList<CompCollAABB<'a>>,
   pub comp_lr_controller: CompList<CompLRController>,
   pub comp_jump_controller: CompList<CompJumpController>,
+  pub comp_top_down_controller: CompList<CompTopDownController>,
 
   pub tile_maps: Vec<TileMap16>,
+
+  /// (trigger, other) pairs overlapping as of the last physics update.
+  /// Sorted. Managed by the physics system.
+  pub trigger_overlaps: Vec<(EntityID, EntityID)>,
+
+  /// Random number generator for game logic. Reseeded when input recording
+  /// or playback starts, so replays get the same numbers.
+  pub rng: Rng,
+
+  /// Camera the world is rendered from.
+  pub camera: Camera,
 }
 
 impl<'a> GameState<'a> {
   pub fn new() -> GameState<'a> {
     GameState {
       comp_debug_draw: CompList::new(),
+      comp_sprite: CompList::new(),
+      comp_text: CompList::new(),
       comp_aabb: CompList::new(),
       comp_body: CompList::new(),
       comp_coll_aabb: CompList::new(),
       comp_lr_controller: CompList::new(),
       comp_jump_controller: CompList::new(),
+      comp_top_down_controller: CompList::new(),
 
       tile_maps: Vec::new(),
+
+      trigger_overlaps: Vec::new(),
+
+      rng: Rng::new(0),
+
+      camera: Camera::new(),
     }
   }
+
+  /// Hash the simulated parts of the game state (AABBs, bodies, colliders,
+  /// controllers, tile maps, trigger overlaps and the RNG). Uses FNV-1a over the raw bits of every value, so the
+  /// hash is stable between runs and platforms. Two states with the same hash
+  /// after running the deterministic physics mode have behaved identically.
+  pub fn state_hash(&self) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    {
+      let mut write = |v: u32| {
+        for b in 0..4 {
+          hash ^= ((v >> (b * 8)) & 0xff) as u64;
+          hash = hash.wrapping_mul(0x100000001b3);
+        }
+      };
+
+      for aabb in &self.comp_aabb {
+        write(aabb.entity_id.0 as u32);
+        write(aabb.pos.0.to_bits());
+        write(aabb.pos.1.to_bits());
+        write(aabb.size.0.to_bits());
+        write(aabb.size.1.to_bits());
+      }
+      for body in &self.comp_body {
+        write(body.entity_id.0 as u32);
+        write(body.kind as u32);
+        write(body.vel.0.to_bits());
+        write(body.vel.1.to_bits());
+        write(body.acc.0.to_bits());
+        write(body.acc.1.to_bits());
+        write(body.force.0.to_bits());
+        write(body.force.1.to_bits());
+        write(body.mass.to_bits());
+        write(body.restitution.to_bits());
+        write(body.friction.to_bits());
+        write(body.max_speed.to_bits());
+        write(body.grounded as u32);
+        write(body.drop_through as u32);
+      }
+      for c in &self.comp_coll_aabb {
+        write(c.entity_id.0 as u32);
+        write(c.offset.0.to_bits());
+        write(c.offset.1.to_bits());
+        write(c.size.0.to_bits());
+        write(c.size.1.to_bits());
+        write(c.solid as u32);
+        write(c.layer);
+        write(c.mask);
+      }
+      for c in &self.comp_lr_controller {
+        write(c.entity_id.0 as u32);
+        write(c.force.to_bits());
+      }
+      for c in &self.comp_jump_controller {
+        write(c.entity_id.0 as u32);
+        write(c.jump_speed.to_bits());
+        write(c.num_jumps as u32);
+        write(c.max_jumps as u32);
+        write(c.coyote_time.to_bits());
+        write(c.jump_buffer.to_bits());
+        write(c.release_damping.to_bits());
+        write(c.time_since_grounded.to_bits());
+        write(c.time_since_jump_pressed.is_some() as u32);
+        write(c.time_since_jump_pressed.map_or(0, |t| t.to_bits()));
+        write(c.jump_held as u32);
+      }
+      for c in &self.comp_top_down_controller {
+        write(c.entity_id.0 as u32);
+        write(c.eight_way as u32);
+        write(c.speed.to_bits());
+        write(c.acceleration.to_bits());
+        write(c.deceleration.to_bits());
+        write(c.dash_speed.to_bits());
+        write(c.dash_time.to_bits());
+        write(c.dash_cooldown.to_bits());
+        write(c.dash_time_left.to_bits());
+        write(c.dash_cooldown_left.to_bits());
+        write(c.facing.0.to_bits());
+        write(c.facing.1.to_bits());
+      }
+      for map in &self.tile_maps {
+        write(map.world_pos.0.to_bits());
+        write(map.world_pos.1.to_bits());
+        write(map.tile_size.to_bits());
+        for t in map.tiles.iter() {
+          write(*t as u32);
+        }
+      }
+      for &(trigger, other) in &self.trigger_overlaps {
+        write(trigger.0 as u32);
+        write(other.0 as u32);
+      }
+      for &v in &[self.rng.get_seed(), self.rng.get_state()] {
+        write(v as u32);
+        write((v >> 32) as u32);
+      }
+    }
+    hash
+  }
+
+  /// Remove every component belonging to an entity. Any triggers it was
+  /// overlapping will get an exit event on the next physics update.
+  pub fn remove_entity(&mut self, entity_id: EntityID) {
+    self.comp_debug_draw.remove_component(entity_id);
+    self.comp_sprite.remove_component(entity_id);
+    self.comp_text.remove_component(entity_id);
+    self.comp_aabb.remove_component(entity_id);
+    self.comp_body.remove_component(entity_id);
+    self.comp_coll_aabb.remove_component(entity_id);
+    self.comp_lr_controller.remove_component(entity_id);
+    self.comp_jump_controller.remove_component(entity_id);
+    self.comp_top_down_controller.remove_component(entity_id);
+  }
 }
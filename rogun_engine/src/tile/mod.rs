@@ -1,5 +1,96 @@
 use common::vec::Vec2f32;
 use common::color::RGBf32;
+use common::scalar::Scalar;
+use renderer::atlas::AtlasRegion;
+
+/// Collision shape of a tile. Slopes are named by the direction the floor
+/// rises in. The 22.5 degree slopes (really 2:1, about 26.6 degrees) take two
+/// tiles to rise one tile, a 'low' tile followed by a 'high' tile.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TileShape {
+  /// Doesn't collide with anything
+  Empty,
+  /// Solid square
+  Full,
+  /// Only solid from above, bodies can jump up through it. Bodies with
+  /// CompBody::drop_through set fall through it.
+  OneWayTop,
+  /// Solid bottom half of the tile
+  HalfBottom,
+  /// Solid top half of the tile
+  HalfTop,
+  /// 45 degree slope, floor rising to the right
+  Slope45Right,
+  /// 45 degree slope, floor rising to the left
+  Slope45Left,
+  /// Lower tile of a 22.5 degree slope rising to the right
+  Slope22RightLow,
+  /// Upper tile of a 22.5 degree slope rising to the right
+  Slope22RightHigh,
+  /// Lower tile of a 22.5 degree slope rising to the left
+  Slope22LeftLow,
+  /// Upper tile of a 22.5 degree slope rising to the left
+  Slope22LeftHigh,
+}
+
+impl TileShape {
+  /// Returns true for any of the slope shapes.
+  pub fn is_slope(&self) -> bool {
+    match *self {
+      TileShape::Slope45Right | TileShape::Slope45Left
+        | TileShape::Slope22RightLow | TileShape::Slope22RightHigh
+        | TileShape::Slope22LeftLow | TileShape::Slope22LeftHigh => true,
+      _ => false,
+    }
+  }
+
+  /// Height of a slope's floor at u across the tile (0 = left edge, 1 = right
+  /// edge). Both are fractions of the tile size, with 0 being the top of the
+  /// tile as y points down. Returns None for shapes which aren't slopes.
+  pub fn surface<S: Scalar>(&self, u: S) -> Option<S> {
+    let (one, half) = (S::from_f32(1.0), S::from_f32(0.5));
+    let u = u.max(S::zero()).min(one);
+    match *self {
+      TileShape::Slope45Right => Some(one - u),
+      TileShape::Slope45Left => Some(u),
+      TileShape::Slope22RightLow => Some(one - u * half),
+      TileShape::Slope22RightHigh => Some(half - u * half),
+      TileShape::Slope22LeftLow => Some(half + u * half),
+      TileShape::Slope22LeftHigh => Some(u * half),
+      _ => None,
+    }
+  }
+
+  /// Outline of the solid part of the tile as a convex polygon, in fractions
+  /// of the tile size. Empty for TileShape::Empty.
+  pub fn outline(&self) -> &'static [(f32, f32)] {
+    match *self {
+      TileShape::Empty => &[],
+      TileShape::Full | TileShape::OneWayTop =>
+        &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::HalfBottom => &[(0.0, 0.5), (1.0, 0.5), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::HalfTop => &[(0.0, 0.0), (1.0, 0.0), (1.0, 0.5), (0.0, 0.5)],
+      TileShape::Slope45Right => &[(1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::Slope45Left => &[(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::Slope22RightLow => &[(1.0, 0.5), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::Slope22RightHigh => &[(0.0, 0.5), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::Slope22LeftLow => &[(0.0, 0.5), (1.0, 1.0), (0.0, 1.0)],
+      TileShape::Slope22LeftHigh => &[(0.0, 0.0), (1.0, 0.5), (1.0, 1.0), (0.0, 1.0)],
+    }
+  }
+
+  /// Solid rectangle of box shaped tiles as (min, max) fractions of the tile
+  /// size, or None for slopes and empty tiles.
+  pub fn bounds(&self) -> Option<(Vec2f32, Vec2f32)> {
+    match *self {
+      TileShape::Full | TileShape::OneWayTop =>
+        Some((Vec2f32(0.0, 0.0), Vec2f32(1.0, 1.0))),
+      TileShape::HalfBottom => Some((Vec2f32(0.0, 0.5), Vec2f32(1.0, 1.0))),
+      TileShape::HalfTop => Some((Vec2f32(0.0, 0.0), Vec2f32(1.0, 0.5))),
+      _ => None,
+    }
+  }
+}
 
 /// Tile object, describes a tile's data
 #[derive(Clone, Copy)]
@@ -8,27 +99,46 @@ pub struct Tile {
   /// this.
   pub id: u16,
   pub color: RGBf32,
+
+  /// Region of the texture atlas to draw, if any. Tiles without a region are
+  /// drawn as a flat square of color.
+  pub region: Option<AtlasRegion>,
+
+  /// Collision shape, used for raycasts and collisions with bodies.
+  /// TileShape::Empty by default, so tiles are only solid when a game asks
+  /// for it.
+  pub shape: TileShape,
 }
 
 impl Tile {
   pub fn new(id: u16) -> Tile {
-    Tile {id: id, color: RGBf32::new(0.0, 0.0, 0.0)}
+    Tile {id: id, color: RGBf32::new(0.0, 0.0, 0.0), region: None, shape: TileShape::Empty}
   }
 }
 
 /// Bank of tiles, add tiles to the lib_state's TileBank object to register them for use in the tile map's flyweight pattern.
 pub struct TileBank {
   tiles: Vec<Tile>,
+
+  /// Incremented whenever a tile is registered.
+  version: u64,
 }
 
 impl TileBank {
   pub fn new() -> TileBank {
-    TileBank { tiles: Vec::new() }
+    TileBank { tiles: Vec::new(), version: 0 }
+  }
+
+  /// Number of times a tile has been registered. Renderers compare this to
+  /// tell when cached tile meshes are out of date.
+  pub fn get_version(&self) -> u64 {
+    self.version
   }
 
   /// Inserts a tile into the tile bank. Maintains sorted order. 
   /// O(log(n)).
   pub fn register_tile(&mut self, tile: Tile) {
+    self.version += 1;
     if self.tiles.len() == 0 {
       self.tiles.push(tile);
       return;
@@ -99,6 +209,11 @@ impl TileBank {
       return Some(self.tiles[index.unwrap()]);
     }
   }
+
+  /// Get the collision shape of a tile. Unregistered tiles are empty.
+  pub fn get_tile_shape(&self, id: u16) -> TileShape {
+    self.get_tile(id).map_or(TileShape::Empty, |t| t.shape)
+  }
 }
 
 /// 16 x 16 tile map
@@ -111,6 +226,11 @@ pub struct TileMap16 {
 
   /// Flyweight array of tiles
   pub tiles: [u16; 16*16],
+
+  /// Render layer the tiles are drawn on, 0 by default, the renderer's
+  /// RENDER_LAYER_TILES. Use RENDER_LAYER_BACKGROUND or
+  /// RENDER_LAYER_FOREGROUND for scenery maps.
+  pub layer: i32,
 }
 
 impl TileMap16 {
@@ -118,7 +238,30 @@ impl TileMap16 {
     TileMap16 { 
       world_pos: Vec2f32(0.0, 0.0),
       tile_size: 16.0,
-      tiles: [0; 16*16] 
+      tiles: [0; 16*16],
+      layer: 0,
     }
   }
+
+  /// World position of the top left of the tile at x, y.
+  pub fn tile_pos(&self, x: usize, y: usize) -> Vec2f32 {
+    Vec2f32(self.world_pos.0 + x as f32 * self.tile_size,
+            self.world_pos.1 + y as f32 * self.tile_size)
+  }
+
+  /// Find the range of tiles covered by a world space rectangle. Returns
+  /// (x0, y0, x1, y1), inclusive, or None if the rectangle doesn't touch the
+  /// map.
+  pub fn tile_rect(&self, min: Vec2f32, max: Vec2f32) -> Option<(usize, usize, usize, usize)> {
+    let size = 16.0 * self.tile_size;
+    if max.0 < self.world_pos.0 || max.1 < self.world_pos.1
+      || min.0 > self.world_pos.0 + size || min.1 > self.world_pos.1 + size {
+        return None;
+      }
+    let clamp = |v: f32| -> usize { (v.floor() as i32).max(0).min(15) as usize };
+    Some((clamp((min.0 - self.world_pos.0) / self.tile_size),
+          clamp((min.1 - self.world_pos.1) / self.tile_size),
+          clamp((max.0 - self.world_pos.0) / self.tile_size),
+          clamp((max.1 - self.world_pos.1) / self.tile_size)))
+  }
 }
@@ -0,0 +1,113 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::physics::PhysicsSystem;
+use engine::state::GameState;
+use engine::tile::TileBank;
+use common::pos;
+
+const GRAVITY: f32 = 500.0;
+const DT: f32 = 1.0 / 60.0;
+
+/// Add a body with a solid collider the size of its AABB.
+fn add_body(g_state: &mut GameState, id: u16, kind: BodyKind, pos: Vec2f32, size: Vec2f32, vel: Vec2f32) {
+  let body = common::add_box(g_state, id, pos, size);
+  body.kind = kind;
+  body.vel = vel;
+}
+
+/// Step the physics, pulling every body down with gravity.
+fn step(physics: &mut PhysicsSystem, g_state: &mut GameState, frames: u32) {
+  let tile_bank = TileBank::new();
+  for _ in 0..frames {
+    for body in &mut g_state.comp_body {
+      let mass = body.mass;
+      body.apply_force(Vec2f32(0.0, GRAVITY * mass));
+    }
+    physics.update_physics(g_state, &tile_bank, DT);
+  }
+}
+
+#[test]
+fn rider_is_carried_by_moving_platform() {
+  // Sideways, rising and sinking platforms
+  for &vel in &[Vec2f32(60.0, 0.0), Vec2f32(60.0, -30.0), Vec2f32(60.0, 30.0)] {
+    let mut g_state = GameState::new();
+    add_body(&mut g_state, 0, BodyKind::Kinematic, Vec2f32(0.0, 100.0), Vec2f32(64.0, 16.0), vel);
+    add_body(&mut g_state, 1, BodyKind::Dynamic, Vec2f32(20.0, 84.0), Vec2f32(16.0, 16.0),
+             Vec2f32(0.0, 0.0));
+    let mut physics = PhysicsSystem::new();
+    for _ in 0..60 {
+      step(&mut physics, &mut g_state, 1);
+      // The rider's feet never leave the platform
+      let gap = pos(&g_state, 0).1 - (pos(&g_state, 1).1 + 16.0);
+      assert!(gap.abs() < 0.5);
+    }
+
+    // The platform ignores gravity, and the rider moves with it
+    let platform = pos(&g_state, 0);
+    assert!((platform - (Vec2f32(0.0, 100.0) + vel)).len() < 0.01);
+    assert!((pos(&g_state, 1).0 - 80.0).abs() < 0.01);
+    assert!(g_state.comp_body.get_component(EntityID(1)).unwrap().grounded);
+  }
+}
+
+#[test]
+fn jumping_rider_is_not_carried() {
+  let mut g_state = GameState::new();
+  add_body(&mut g_state, 0, BodyKind::Kinematic, Vec2f32(0.0, 100.0), Vec2f32(64.0, 16.0),
+           Vec2f32(60.0, 0.0));
+  add_body(&mut g_state, 1, BodyKind::Dynamic, Vec2f32(20.0, 84.0), Vec2f32(16.0, 16.0),
+           Vec2f32(0.0, -200.0));
+  let mut physics = PhysicsSystem::new();
+  step(&mut physics, &mut g_state, 1);
+  assert!(pos(&g_state, 1).0 == 20.0);
+  assert!(pos(&g_state, 1).1 < 84.0);
+}
+
+#[test]
+fn kinematic_body_pushes_dynamic_body_without_being_pushed_back() {
+  let mut g_state = GameState::new();
+  add_body(&mut g_state, 0, BodyKind::Kinematic, Vec2f32(0.0, 0.0), Vec2f32(16.0, 16.0),
+           Vec2f32(120.0, 0.0));
+  add_body(&mut g_state, 1, BodyKind::Dynamic, Vec2f32(40.0, 0.0), Vec2f32(16.0, 16.0),
+           Vec2f32(0.0, 0.0));
+  // Heavier than the pusher, which makes no difference
+  g_state.comp_body.get_component_mut(EntityID(1)).unwrap().mass = 100.0;
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..60 {
+    physics.update_physics(&mut g_state, &TileBank::new(), DT);
+  }
+
+  let pusher = pos(&g_state, 0);
+  assert!((pusher.0 - 120.0).abs() < 0.01 && pusher.1 == 0.0);
+  let vel = g_state.comp_body.get_component(EntityID(0)).unwrap().vel;
+  assert!(vel.0 == 120.0 && vel.1 == 0.0);
+  assert!(pos(&g_state, 1).0 >= pusher.0 + 16.0 - 0.01);
+}
+
+#[test]
+fn static_body_never_moves() {
+  let mut g_state = GameState::new();
+  add_body(&mut g_state, 0, BodyKind::Static, Vec2f32(0.0, 100.0), Vec2f32(64.0, 16.0),
+           Vec2f32(50.0, 50.0));
+  add_body(&mut g_state, 1, BodyKind::Dynamic, Vec2f32(20.0, 0.0), Vec2f32(16.0, 16.0),
+           Vec2f32(0.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..60 {
+    let body = g_state.comp_body.get_component_mut(EntityID(0)).unwrap();
+    body.apply_force(Vec2f32(1000.0, 1000.0));
+    body.apply_impulse(Vec2f32(1000.0, 1000.0));
+    step(&mut physics, &mut g_state, 1);
+  }
+
+  // Velocity is ignored, as are forces, impulses and the body landing on it
+  assert!(pos(&g_state, 0) == Vec2f32(0.0, 100.0));
+  assert!((pos(&g_state, 1).1 - 84.0).abs() < 0.01);
+  assert!(g_state.comp_body.get_component(EntityID(1)).unwrap().grounded);
+  assert!(g_state.comp_body.get_component(EntityID(0)).unwrap().force == Vec2f32(0.0, 0.0));
+}
@@ -0,0 +1,134 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::camera::Camera;
+use engine::renderer::software::SoftwareRenderer;
+use engine::state::GameState;
+use engine::state::comp_list::CompList;
+use engine::tile::TileBank;
+
+/// AABBs with a single 10x10 target, entity 0, centred on centre.
+fn target_at(centre: Vec2f32) -> CompList<CompAABB> {
+  let mut list = CompList::new();
+  list.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: centre - Vec2f32(5.0, 5.0),
+    size: Vec2f32(10.0, 10.0),
+  });
+  list
+}
+
+/// A 200x100 pixel camera following entity 0.
+fn camera() -> Camera {
+  let mut camera = Camera::new();
+  camera.set_viewport(200, 100);
+  camera.target = Some(EntityID(0));
+  camera
+}
+
+fn close(a: Vec2f32, b: Vec2f32) -> bool {
+  (a - b).len() < 0.001
+}
+
+#[test]
+fn screen_world_conversion() {
+  let mut camera = camera();
+  camera.set_pos(Vec2f32(50.0, -20.0));
+  camera.set_zoom(2.0);
+  // Zooming keeps the centre, which was (150, 30)
+  assert!(close(camera.get_centre(), Vec2f32(150.0, 30.0)));
+  assert!(close(camera.get_pos(), Vec2f32(100.0, 5.0)));
+  assert!(close(camera.get_view_size(), Vec2f32(100.0, 50.0)));
+
+  assert!(close(camera.screen_to_world(Vec2f32(0.0, 0.0)), Vec2f32(100.0, 5.0)));
+  assert!(close(camera.screen_to_world(Vec2f32(200.0, 100.0)), Vec2f32(200.0, 55.0)));
+  let p = Vec2f32(123.0, 45.0);
+  assert!(close(camera.screen_to_world(camera.world_to_screen(p)), p));
+
+  camera.set_zoom(0.0);
+  assert!(camera.get_zoom() == 2.0);
+}
+
+#[test]
+fn follows_target_with_dead_zone_and_smoothing() {
+  let mut camera = camera();
+  camera.dead_zone = Vec2f32(40.0, 20.0);
+  camera.snap_to_target(&target_at(Vec2f32(0.0, 0.0)));
+  assert!(close(camera.get_centre(), Vec2f32(0.0, 0.0)));
+
+  // Inside the dead zone, nothing moves
+  camera.update(&target_at(Vec2f32(15.0, -8.0)), 1.0 / 60.0);
+  assert!(close(camera.get_centre(), Vec2f32(0.0, 0.0)));
+
+  // Outside, the target ends up on the edge of the dead zone
+  camera.update(&target_at(Vec2f32(50.0, 30.0)), 1.0 / 60.0);
+  assert!(close(camera.get_centre(), Vec2f32(30.0, 20.0)));
+
+  // With smoothing, the camera covers 1 - e^(-smoothing * dt) of the way
+  camera.dead_zone = Vec2f32(0.0, 0.0);
+  camera.smoothing = 10.0;
+  camera.update(&target_at(Vec2f32(130.0, 20.0)), 0.1);
+  let moved = 100.0 * (1.0 - (-1.0f32).exp());
+  assert!(close(camera.get_centre(), Vec2f32(30.0 + moved, 20.0)));
+  for _ in 0..100 {
+    camera.update(&target_at(Vec2f32(130.0, 20.0)), 0.1);
+  }
+  assert!(close(camera.get_centre(), Vec2f32(130.0, 20.0)));
+
+  // Without a target, the camera stays put
+  camera.target = Some(EntityID(1));
+  camera.update(&target_at(Vec2f32(500.0, 500.0)), 0.1);
+  assert!(close(camera.get_centre(), Vec2f32(130.0, 20.0)));
+}
+
+#[test]
+fn clamps_to_bounds() {
+  let mut camera = camera();
+  camera.bounds = Some((Vec2f32(0.0, 0.0), Vec2f32(1000.0, 60.0)));
+  camera.snap_to_target(&target_at(Vec2f32(20.0, 20.0)));
+  // x is clamped to the left edge. The level is shorter than the view, so
+  // it's centred vertically.
+  assert!(close(camera.get_pos(), Vec2f32(0.0, -20.0)));
+
+  camera.update(&target_at(Vec2f32(2000.0, 20.0)), 1.0 / 60.0);
+  assert!(close(camera.get_pos(), Vec2f32(800.0, -20.0)));
+
+  camera.set_zoom(4.0);
+  camera.update(&target_at(Vec2f32(500.0, 50.0)), 1.0 / 60.0);
+  assert!(close(camera.get_pos(), Vec2f32(475.0, 35.0)));
+}
+
+#[test]
+fn software_renderer_draws_from_camera() {
+  let mut g_state = GameState::new();
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: Vec2f32(100.0, 50.0),
+    size: Vec2f32(2.0, 2.0),
+  });
+  g_state.comp_debug_draw.add_component(CompDebugDraw {
+    entity_id: EntityID(0),
+    color: RGBf32::new(1.0, 0.0, 0.0),
+  });
+  g_state.camera.set_viewport(10, 10);
+  g_state.camera.set_pos(Vec2f32(99.0, 49.0));
+  g_state.camera.set_zoom(2.0);
+  // Zooming around the centre (104, 54) puts the view at (101.5, 51.5)
+
+  let mut r = SoftwareRenderer::new(10, 10);
+  r.render_game(&g_state, &TileBank::new());
+  let red = [128, 0, 0, 255];
+  assert!(r.get_pixel(0, 0) == red);
+  assert!(r.get_pixel(0, 1) == [0, 0, 0, 255]);
+  assert!(r.get_pixel(1, 0) == [0, 0, 0, 255]);
+
+  g_state.camera.set_pos(Vec2f32(99.0, 49.0));
+  r.render_game(&g_state, &TileBank::new());
+  assert!(r.get_pixel(1, 1) == [0, 0, 0, 255]);
+  assert!(r.get_pixel(2, 2) == red && r.get_pixel(5, 5) == red);
+  assert!(r.get_pixel(6, 6) == [0, 0, 0, 255]);
+}
@@ -0,0 +1,114 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::physics::{PhysicsSystem, LAYER_DEFAULT, LAYER_TILES, MASK_ALL};
+use engine::state::GameState;
+use engine::tile::{TileBank, TileMap16};
+use common::pos;
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Add a 16x16 dynamic box with a solid collider.
+fn add_box(g_state: &mut GameState, id: u16, pos: Vec2f32, vel: Vec2f32, layer: u32, mask: u32) {
+  common::add_box(g_state, id, pos, Vec2f32(16.0, 16.0)).vel = vel;
+  let coll = g_state.comp_coll_aabb.get_component_mut(EntityID(id)).unwrap();
+  coll.layer = layer;
+  coll.mask = mask;
+}
+
+/// Fire box 0 at box 1, which is sitting still 100 units to the right, and
+/// return both boxes' final x.
+fn fire_at(layers: (u32, u32), masks: (u32, u32)) -> (f32, f32) {
+  let mut physics = PhysicsSystem::new();
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_box(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(600.0, 0.0), layers.0, masks.0);
+  add_box(&mut g_state, 1, Vec2f32(100.0, 0.0), Vec2f32(0.0, 0.0), layers.1, masks.1);
+  for _ in 0..60 {
+    physics.update_physics(&mut g_state, &tile_bank, DT);
+  }
+  (pos(&g_state, 0).0, pos(&g_state, 1).0)
+}
+
+#[test]
+fn bodies_masking_each_other_out_pass_through() {
+  let mut physics = PhysicsSystem::new();
+  let player = physics.register_layer("player").unwrap();
+  let enemy = physics.register_layer("enemy").unwrap();
+
+  // Colliding: box 0 shoves box 1 along, and never gets past it
+  let (x0, x1) = fire_at((player, enemy), (MASK_ALL, MASK_ALL));
+  assert!(x1 > 100.0 && x0 <= x1 - 16.0 + 0.001);
+
+  // Neither mask has the other's layer
+  let (x0, x1) = fire_at((player, enemy), (LAYER_TILES, LAYER_TILES));
+  assert!(x0 > 500.0 && x1 == 100.0);
+
+  // Only one side masks the other out, which is also enough
+  let (x0, x1) = fire_at((player, enemy), (player | enemy, enemy));
+  assert!(x0 > 500.0 && x1 == 100.0);
+}
+
+#[test]
+fn tile_collisions_need_layer_tiles_in_the_mask() {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(common::full_tile(1));
+
+  // Drop a box with each mask onto a floor at y = 160
+  let drop = |mask: u32| -> f32 {
+    let mut g_state = GameState::new();
+    let mut map = TileMap16::new();
+    for x in 0..16 {
+      map.tiles[10 * 16 + x] = 1;
+    }
+    g_state.tile_maps.push(map);
+    add_box(&mut g_state, 0, Vec2f32(32.0, 100.0), Vec2f32(0.0, 0.0), LAYER_DEFAULT, mask);
+    let mut physics = PhysicsSystem::new();
+    for _ in 0..60 {
+      g_state.comp_body.get_component_mut(EntityID(0)).unwrap().apply_force(Vec2f32(0.0, 500.0));
+      physics.update_physics(&mut g_state, &tile_bank, DT);
+    }
+    pos(&g_state, 0).1
+  };
+
+  assert!((drop(MASK_ALL) - 144.0).abs() < 0.01);
+  assert!((drop(LAYER_TILES) - 144.0).abs() < 0.01);
+  assert!(drop(LAYER_DEFAULT) > 160.0);
+}
+
+#[test]
+fn unknown_layer_names() {
+  let mut physics = PhysicsSystem::new();
+  assert!(physics.get_layer("default") == Some(LAYER_DEFAULT));
+  assert!(physics.get_layer("player").is_none());
+  assert!(physics.get_mask(&["default", "player"]).is_none());
+
+  let player = physics.register_layer("player").unwrap();
+  assert!(physics.get_layer("player") == Some(player));
+  assert!(physics.get_mask(&["default", "player"]) == Some(LAYER_DEFAULT | player));
+  assert!(physics.get_mask(&["tiles"]) == Some(LAYER_TILES));
+  assert!(physics.get_mask(&[]) == Some(0));
+}
+
+#[test]
+fn only_31_layers_can_be_registered() {
+  let mut physics = PhysicsSystem::new();
+  // "default" takes the first bit
+  let mut all = LAYER_DEFAULT;
+  for ii in 1..31 {
+    let layer = physics.register_layer(&format!("layer{}", ii)).unwrap();
+    assert!(layer == 1 << ii);
+    all |= layer;
+  }
+  assert!(all == !LAYER_TILES);
+
+  assert!(physics.register_layer("one too many").is_none());
+  assert!(physics.get_layer("one too many").is_none());
+
+  // Registering an existing name still works when full
+  assert!(physics.register_layer("layer7") == Some(1 << 7));
+  assert!(physics.register_layer("default") == Some(LAYER_DEFAULT));
+}
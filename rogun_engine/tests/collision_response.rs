@@ -0,0 +1,164 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::physics::PhysicsSystem;
+use engine::state::GameState;
+use engine::tile::{TileBank, TileMap16};
+use common::vel;
+
+const GRAVITY: f32 = 500.0;
+const DT: f32 = 1.0 / 60.0;
+
+/// Add a 16x16 dynamic box with a solid collider.
+fn add_box(g_state: &mut GameState, id: u16, pos: Vec2f32, vel: Vec2f32,
+           mass: f32, restitution: f32, friction: f32) {
+  let body = common::add_box(g_state, id, pos, Vec2f32(16.0, 16.0));
+  body.vel = vel;
+  body.mass = mass;
+  body.restitution = restitution;
+  body.friction = friction;
+}
+
+/// A tile bank with a full tile, and a game state with a floor of them with
+/// its top at y = 160.
+fn floor<'a>() -> (GameState<'a>, TileBank) {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(common::full_tile(1));
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  for x in 0..16 {
+    map.tiles[10 * 16 + x] = 1;
+  }
+  g_state.tile_maps.push(map);
+  (g_state, tile_bank)
+}
+
+/// Step the physics, pulling every body down with gravity if it's set.
+fn step(physics: &mut PhysicsSystem, g_state: &mut GameState, tile_bank: &TileBank, gravity: f32) {
+  for body in &mut g_state.comp_body {
+    let mass = body.mass;
+    body.apply_force(Vec2f32(0.0, gravity * mass));
+  }
+  physics.update_physics(g_state, tile_bank, DT);
+}
+
+fn momentum(g_state: &GameState) -> Vec2f32 {
+  let mut p = Vec2f32(0.0, 0.0);
+  for body in &g_state.comp_body {
+    p += body.vel * body.mass;
+  }
+  p
+}
+
+/// Collide a box of mass 1 moving right with a box of mass 3 moving left,
+/// returning both final velocities.
+fn head_on(restitution: f32) -> (Vec2f32, Vec2f32) {
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_box(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(200.0, 0.0), 1.0, restitution, 0.0);
+  add_box(&mut g_state, 1, Vec2f32(50.0, 0.0), Vec2f32(-100.0, 0.0), 3.0, restitution, 0.0);
+  let before = momentum(&g_state);
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..30 {
+    step(&mut physics, &mut g_state, &tile_bank, 0.0);
+    assert!((momentum(&g_state) - before).len() < 0.01);
+  }
+  (vel(&g_state, 0), vel(&g_state, 1))
+}
+
+#[test]
+fn elastic_collision_conserves_momentum_and_energy() {
+  let (v0, v1) = head_on(1.0);
+  // 1 * 200 + 3 * -100 = -100. Elastic, so the bodies separate as fast as
+  // they approached: v1 - v0 = 300.
+  assert!((v0.0 + 3.0 * v1.0 - -100.0).abs() < 0.01);
+  assert!((v1.0 - v0.0 - 300.0).abs() < 0.01);
+  assert!((v0.0 - -250.0).abs() < 0.01 && (v1.0 - 50.0).abs() < 0.01);
+  assert!(v0.1 == 0.0 && v1.1 == 0.0);
+}
+
+#[test]
+fn inelastic_collision_conserves_momentum() {
+  let (v0, v1) = head_on(0.0);
+  // The bodies end up moving together at -100 / 4
+  assert!((v0.0 - -25.0).abs() < 0.01 && (v1.0 - -25.0).abs() < 0.01);
+}
+
+#[test]
+fn restitution_sets_bounce_height() {
+  // Drop a box from 100 units above the floor, returning the highest it gets
+  // after landing.
+  let bounce = |restitution: f32| -> f32 {
+    let (mut g_state, tile_bank) = floor();
+    add_box(&mut g_state, 0, Vec2f32(32.0, 44.0), Vec2f32(0.0, 0.0), 1.0, restitution, 0.0);
+    let mut physics = PhysicsSystem::new();
+    let mut landed = false;
+    let mut top = f32::MAX;
+    for _ in 0..120 {
+      step(&mut physics, &mut g_state, &tile_bank, GRAVITY);
+      landed = landed || g_state.comp_body.get_component(EntityID(0)).unwrap().grounded;
+      if landed {
+        top = top.min(g_state.comp_aabb.get_component(EntityID(0)).unwrap().pos.1);
+      }
+    }
+    144.0 - top
+  };
+
+  // Perfectly elastic gets back to about where it started, losing a little
+  // to the step size. No restitution never leaves the floor.
+  assert!((bounce(1.0) - 100.0).abs() < 5.0);
+  assert!(bounce(0.5) > 15.0 && bounce(0.5) < 35.0);
+  assert!(bounce(0.0).abs() < 0.01);
+}
+
+#[test]
+fn friction_slows_sliding_body() {
+  // Slide a box along the floor at 200 units / second for a second
+  let slide = |friction: f32| -> Vec<f32> {
+    let (mut g_state, tile_bank) = floor();
+    add_box(&mut g_state, 0, Vec2f32(0.0, 144.0), Vec2f32(200.0, 0.0), 1.0, 0.0, friction);
+    let mut physics = PhysicsSystem::new();
+    let mut speeds = Vec::new();
+    for _ in 0..60 {
+      step(&mut physics, &mut g_state, &tile_bank, GRAVITY);
+      speeds.push(vel(&g_state, 0).0);
+    }
+    speeds
+  };
+
+  // Frictionless keeps going
+  assert!(slide(0.0).iter().all(|&v| v == 200.0));
+
+  // Coulomb friction decelerates at friction * gravity until it stops, so
+  // 0.5 stops after 0.8 seconds
+  let speeds = slide(0.5);
+  assert!((speeds[23] - (200.0 - 250.0 * 24.0 * DT)).abs() < 1.0);
+  assert!(speeds.windows(2).all(|w| w[1] <= w[0]));
+  assert!(speeds[59] == 0.0);
+}
+
+#[test]
+fn impulses_and_forces_are_scaled_by_mass() {
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_box(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(0.0, 0.0), 2.0, 0.0, 0.0);
+  add_box(&mut g_state, 1, Vec2f32(100.0, 0.0), Vec2f32(0.0, 0.0), 2.0, 0.0, 0.0);
+  g_state.comp_body.get_component_mut(EntityID(1)).unwrap().kind = BodyKind::Kinematic;
+
+  for body in &mut g_state.comp_body {
+    body.apply_impulse(Vec2f32(10.0, -4.0));
+  }
+  assert!(vel(&g_state, 0) == Vec2f32(5.0, -2.0));
+  // Only dynamic bodies take impulses
+  assert!(vel(&g_state, 1) == Vec2f32(0.0, 0.0));
+
+  // A force of 120 on a mass of 2 accelerates it by 60 units / second^2
+  g_state.comp_body.get_component_mut(EntityID(0)).unwrap().apply_force(Vec2f32(120.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  assert!((vel(&g_state, 0).0 - (5.0 + 60.0 * DT)).abs() < 0.0001);
+}
@@ -0,0 +1,57 @@
+//! Fixtures shared by the integration tests. Each test only uses some of
+//! them.
+#![allow(dead_code)]
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_collision::*;
+use engine::physics::{LAYER_DEFAULT, MASK_ALL};
+use engine::state::GameState;
+use engine::tile::{Tile, TileShape};
+
+/// Add an entity with an AABB at pos and a dynamic body at rest, see
+/// CompBody::new. Returns the body, to be changed as needed.
+pub fn add_body<'a, 'b>(g_state: &'b mut GameState<'a>, id: u16,
+                        pos: Vec2f32, size: Vec2f32) -> &'b mut CompBody {
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(id),
+    pos: pos,
+    size: size,
+  });
+  g_state.comp_body.add_component(CompBody::new(EntityID(id)));
+  g_state.comp_body.get_component_mut(EntityID(id)).unwrap()
+}
+
+/// Add a body like add_body, with a solid collider the size of its AABB on
+/// LAYER_DEFAULT, colliding with everything.
+pub fn add_box<'a, 'b>(g_state: &'b mut GameState<'a>, id: u16,
+                       pos: Vec2f32, size: Vec2f32) -> &'b mut CompBody {
+  g_state.comp_coll_aabb.add_component(CompCollAABB {
+    entity_id: EntityID(id),
+    offset: Vec2f32(0.0, 0.0),
+    size: size,
+    solid: true,
+    layer: LAYER_DEFAULT,
+    mask: MASK_ALL,
+    callback: None,
+  });
+  add_body(g_state, id, pos, size)
+}
+
+/// A solid square tile. Tile::new tiles don't collide.
+pub fn full_tile(id: u16) -> Tile {
+  let mut tile = Tile::new(id);
+  tile.shape = TileShape::Full;
+  tile
+}
+
+/// Position of an entity's AABB.
+pub fn pos(g_state: &GameState, id: u16) -> Vec2f32 {
+  g_state.comp_aabb.get_component(EntityID(id)).unwrap().pos
+}
+
+/// Velocity of an entity's body.
+pub fn vel(g_state: &GameState, id: u16) -> Vec2f32 {
+  g_state.comp_body.get_component(EntityID(id)).unwrap().vel
+}
@@ -0,0 +1,101 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::atlas::AtlasRegion;
+use engine::renderer::draw_list::*;
+use engine::renderer::software::SoftwareRenderer;
+use engine::state::GameState;
+use engine::tile::*;
+use std::f32::consts::PI;
+
+fn close(a: Vec2f32, b: Vec2f32) -> bool {
+  (a - b).len() < 0.001
+}
+
+/// Three full tile maps in a row, 256 units wide each, and a debug drawn
+/// 10x10 entity at each of the positions.
+fn world(entities: &[Vec2f32]) -> (GameState<'static>, TileBank) {
+  let mut tile_bank = TileBank::new();
+  let mut tile = Tile::new(1);
+  tile.color = RGBf32::new(0.0, 1.0, 0.0);
+  tile_bank.register_tile(tile);
+
+  let mut g_state = GameState::new();
+  for ii in 0..3 {
+    let mut map = TileMap16::new();
+    map.world_pos = Vec2f32(ii as f32 * 256.0, 0.0);
+    map.tiles = [1; 16*16];
+    g_state.tile_maps.push(map);
+  }
+  for (ii, pos) in entities.iter().enumerate() {
+    g_state.comp_aabb.add_component(CompAABB {
+      entity_id: EntityID(ii as u16),
+      pos: *pos,
+      size: Vec2f32(10.0, 10.0),
+    });
+    g_state.comp_debug_draw.add_component(CompDebugDraw {
+      entity_id: EntityID(ii as u16),
+      color: RGBf32::new(1.0, 0.0, 0.0),
+    });
+  }
+  (g_state, tile_bank)
+}
+
+#[test]
+fn bounds_and_overlap() {
+  let cmd = DrawCommand {
+    layer: 0,
+    z: 0,
+    screen_space: false,
+    color: RGBAf32::new(1.0, 1.0, 1.0, 1.0),
+    prim: DrawPrim::Sprite { pos: Vec2f32(0.0, 0.0), size: Vec2f32(2.0, 2.0),
+                             region: AtlasRegion { x: 0, y: 0, w: 1, h: 1 },
+                             flip_x: false, flip_y: false, rotation: PI / 4.0 },
+  };
+  let (min, max) = command_bounds(&cmd);
+  let r = 2.0f32.sqrt();
+  assert!(close(min, Vec2f32(1.0 - r, 1.0 - r)) && close(max, Vec2f32(1.0 + r, 1.0 + r)));
+
+  let view = (Vec2f32(0.0, 0.0), Vec2f32(100.0, 50.0));
+  assert!(in_view((Vec2f32(-5.0, -5.0), Vec2f32(1.0, 1.0)), view));
+  assert!(in_view((Vec2f32(-5.0, -5.0), Vec2f32(200.0, 200.0)), view));
+  assert!(!in_view((Vec2f32(100.0, 0.0), Vec2f32(110.0, 10.0)), view));
+  assert!(!in_view((Vec2f32(0.0, -10.0), Vec2f32(10.0, 0.0)), view));
+}
+
+#[test]
+fn culls_tile_maps_and_entities_outside_the_view() {
+  let (g_state, tile_bank) = world(&[Vec2f32(250.0, 0.0), Vec2f32(500.0, 100.0),
+                                     Vec2f32(700.0, 0.0), Vec2f32(310.0, 95.0)]);
+  let view = (Vec2f32(260.0, 0.0), Vec2f32(510.0, 100.0));
+  let mut stats = RenderStats::default();
+  let list = build_visible_draw_list(&g_state, &tile_bank, view, &mut stats);
+  assert!(stats == RenderStats { tile_maps_drawn: 1, tile_maps_culled: 2,
+                                 commands_drawn: 1, commands_culled: 3 });
+  assert!(list.len() == 256 + 1);
+  assert!(list[256].prim == DrawPrim::Quad { pos: Vec2f32(310.0, 95.0), size: Vec2f32(10.0, 10.0) });
+
+  // Everything visible gives the same list as build_draw_list
+  let mut stats = RenderStats::default();
+  let view = (Vec2f32(-1000.0, -1000.0), Vec2f32(1000.0, 1000.0));
+  assert!(build_visible_draw_list(&g_state, &tile_bank, view, &mut stats)
+          == build_draw_list(&g_state, &tile_bank));
+  assert!(stats.tile_maps_culled == 0 && stats.commands_culled == 0);
+}
+
+#[test]
+fn software_renderer_culls_from_camera() {
+  let (mut g_state, tile_bank) = world(&[Vec2f32(500.0, 20.0), Vec2f32(0.0, 0.0)]);
+  g_state.camera.set_pos(Vec2f32(470.0, 10.0));
+  let mut r = SoftwareRenderer::new(40, 40);
+  r.render_game(&g_state, &tile_bank);
+  assert!(r.get_stats() == RenderStats { tile_maps_drawn: 1, tile_maps_culled: 2,
+                                         commands_drawn: 1, commands_culled: 1 });
+  assert!(r.get_pixel(0, 0) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(30, 10) == [128, 128, 0, 255] && r.get_pixel(39, 19) == [128, 128, 0, 255]);
+  assert!(r.get_pixel(39, 20) == [0, 255, 0, 255]);
+}
@@ -0,0 +1,162 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::common::color::RGBf32;
+use engine::entity::EntityID;
+use engine::entity::component_control::*;
+use engine::physics::{PhysicsSystem, StepMode, LAYER_DEFAULT};
+use engine::state::GameState;
+use engine::tile::{TileBank, TileMap16};
+
+/// Build a small level with a floor of tiles and a few bouncing boxes.
+fn setup<'a>() -> (GameState<'a>, TileBank) {
+  let mut tile_bank = TileBank::new();
+  let mut tile = common::full_tile(1);
+  tile.color = RGBf32::new(1.0, 1.0, 1.0);
+  tile_bank.register_tile(tile);
+
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  for x in 0..16 {
+    map.tiles[15 * 16 + x] = 1;
+  }
+  g_state.tile_maps.push(map);
+
+  for ii in 0..4 {
+    let pos = Vec2f32(20.0 + ii as f32 * 30.0, 10.0 + ii as f32 * 7.3);
+    let body = common::add_box(&mut g_state, ii, pos, Vec2f32(16.0, 16.0));
+    body.vel = Vec2f32(33.3 - ii as f32 * 21.7, 0.0);
+    body.mass = 1.0 + ii as f32 * 0.37;
+    body.restitution = 0.6;
+    body.friction = 0.3;
+    body.max_speed = 400.0;
+  }
+  (g_state, tile_bank)
+}
+
+/// Run the level for a number of frames, with frame deltas from the given
+/// function, and return the hash of the final state.
+fn run<F: Fn(u32) -> f32>(frames: u32, frame_delta: F) -> u64 {
+  let (mut g_state, tile_bank) = setup();
+  let mut physics = PhysicsSystem::new();
+  physics.step_mode = StepMode::Deterministic { step: 1.0 / 60.0 };
+  for frame in 0..frames {
+    for body in &mut g_state.comp_body {
+      let mass = body.mass;
+      body.apply_force(Vec2f32(0.0, 500.0 * mass));
+    }
+    physics.update_physics(&mut g_state, &tile_bank, frame_delta(frame));
+  }
+  g_state.state_hash()
+}
+
+#[test]
+fn identical_inputs_give_identical_hashes() {
+  assert!(run(600, |_| 1.0 / 60.0) == run(600, |_| 1.0 / 60.0));
+}
+
+#[test]
+fn frame_delta_is_ignored() {
+  let steady = run(600, |_| 1.0 / 60.0);
+  let jittery = run(600, |frame| if frame % 3 == 0 { 0.031 } else { 0.009 });
+  assert!(steady == jittery);
+}
+
+#[test]
+fn different_lengths_give_different_hashes() {
+  assert!(run(300, |_| 1.0 / 60.0) != run(301, |_| 1.0 / 60.0));
+}
+
+#[test]
+fn hash_covers_every_body_and_collider_field() {
+  let (g_state, _) = setup();
+  let base = g_state.state_hash();
+  let changes: [fn(&mut GameState); 7] = [
+    |g| g.comp_body.get_component_mut(EntityID(1)).unwrap().force.0 = 1.0,
+    |g| g.comp_body.get_component_mut(EntityID(1)).unwrap().restitution = 0.5,
+    |g| g.comp_body.get_component_mut(EntityID(1)).unwrap().friction = 0.5,
+    |g| g.comp_body.get_component_mut(EntityID(1)).unwrap().grounded = true,
+    |g| g.comp_body.get_component_mut(EntityID(1)).unwrap().drop_through = true,
+    |g| g.comp_coll_aabb.get_component_mut(EntityID(1)).unwrap().size.1 = 8.0,
+    |g| g.comp_coll_aabb.get_component_mut(EntityID(1)).unwrap().mask = LAYER_DEFAULT,
+  ];
+  for change in changes.iter() {
+    let (mut g_state, _) = setup();
+    change(&mut g_state);
+    assert!(g_state.state_hash() != base);
+  }
+}
+
+#[test]
+fn hash_covers_controllers_triggers_and_rng() {
+  let setup_controlled = || {
+    let (mut g_state, _) = setup();
+    g_state.comp_jump_controller.add_component(
+      CompJumpController::new(EntityID(0), "jump", 200.0, 2));
+    g_state.comp_top_down_controller.add_component(
+      CompTopDownController::new(EntityID(1), "up", "down", "left", "right", 100.0));
+    g_state
+  };
+  let base = setup_controlled().state_hash();
+  let changes: [fn(&mut GameState); 8] = [
+    |g| { g.rng.next_u64(); },
+    |g| g.comp_jump_controller.get_component_mut(EntityID(0)).unwrap().time_since_grounded = 0.05,
+    |g| g.comp_jump_controller.get_component_mut(EntityID(0)).unwrap().time_since_jump_pressed = Some(0.0),
+    |g| g.comp_jump_controller.get_component_mut(EntityID(0)).unwrap().num_jumps = 1,
+    |g| g.comp_jump_controller.get_component_mut(EntityID(0)).unwrap().jump_held = true,
+    |g| g.comp_top_down_controller.get_component_mut(EntityID(1)).unwrap().dash_time_left = 0.1,
+    |g| g.comp_top_down_controller.get_component_mut(EntityID(1)).unwrap().dash_cooldown_left = 0.1,
+    |g| g.trigger_overlaps.push((EntityID(0), EntityID(1))),
+  ];
+  for change in changes.iter() {
+    let mut g_state = setup_controlled();
+    change(&mut g_state);
+    assert!(g_state.state_hash() != base);
+  }
+}
+
+#[test]
+fn massless_bodies_ignore_forces() {
+  // Masses of 0, below the fixed point resolution, and negative
+  for &mass in &[0.0, 1.0e-6, -1.0] {
+    for &step_mode in &[StepMode::Variable, StepMode::Deterministic { step: 1.0 / 60.0 }] {
+      let (mut g_state, tile_bank) = setup();
+      let mut physics = PhysicsSystem::new();
+      physics.step_mode = step_mode;
+      {
+        let body = g_state.comp_body.get_component_mut(EntityID(0)).unwrap();
+        body.mass = mass;
+        body.apply_force(Vec2f32(0.0, 500.0));
+        body.apply_impulse(Vec2f32(100.0, 0.0));
+      }
+      physics.update_physics(&mut g_state, &tile_bank, 1.0 / 60.0);
+      let body = g_state.comp_body.get_component(EntityID(0)).unwrap();
+      if mass > 0.0 {
+        assert!(body.vel.1 > 0.0);
+      } else {
+        assert!(body.acc == Vec2f32(0.0, 0.0) && body.vel.1 == 0.0);
+        // Impulses are ignored too, leaving the starting velocity
+        assert!((body.vel.0 - 33.3).abs() < 0.001);
+      }
+    }
+  }
+}
+
+#[test]
+fn huge_speed_caps_dont_overflow() {
+  for &max_speed in &[1.0e8, 1.0e30, ::std::f32::MAX, ::std::f32::INFINITY] {
+    let (mut g_state, tile_bank) = setup();
+    let mut physics = PhysicsSystem::new();
+    physics.step_mode = StepMode::Deterministic { step: 1.0 / 60.0 };
+    g_state.comp_body.get_component_mut(EntityID(0)).unwrap().max_speed = max_speed;
+    let before = g_state.comp_aabb.get_component(EntityID(0)).unwrap().pos;
+    physics.update_physics(&mut g_state, &tile_bank, 1.0 / 60.0);
+    // No gravity, so the body just carries on at its starting velocity
+    let body = g_state.comp_body.get_component(EntityID(0)).unwrap();
+    assert!((body.vel.0 - 33.3).abs() < 0.001 && body.vel.1 == 0.0);
+    let moved = g_state.comp_aabb.get_component(EntityID(0)).unwrap().pos - before;
+    assert!((moved.0 - 33.3 / 60.0).abs() < 0.001 && moved.1 == 0.0);
+  }
+}
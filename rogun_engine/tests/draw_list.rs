@@ -0,0 +1,61 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::draw_list::*;
+use engine::state::GameState;
+use engine::tile::*;
+
+/// Quad draw command.
+fn quad(layer: i32, color: RGBAf32, pos: Vec2f32, size: Vec2f32) -> DrawCommand {
+  DrawCommand { layer: layer, z: 0, screen_space: false, color: color,
+                prim: DrawPrim::Quad { pos: pos, size: size } }
+}
+
+#[test]
+fn tiles_then_debug_draws() {
+  let mut tile_bank = TileBank::new();
+  let mut tile = Tile::new(1);
+  tile.color = RGBf32::new(0.0, 1.0, 0.0);
+  tile_bank.register_tile(tile);
+
+  let mut g_state = GameState::new();
+  // Debug draw added first, but drawn on top
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: Vec2f32(5.0, 6.0),
+    size: Vec2f32(10.0, 20.0),
+  });
+  g_state.comp_debug_draw.add_component(CompDebugDraw {
+    entity_id: EntityID(0),
+    color: RGBf32::new(1.0, 0.0, 0.0),
+  });
+  let mut map = TileMap16::new();
+  map.world_pos = Vec2f32(100.0, 0.0);
+  map.tiles[0] = 1;
+  map.tiles[17] = 1;
+  // Unregistered tiles aren't drawn
+  map.tiles[2] = 7;
+  g_state.tile_maps.push(map);
+
+  let green = RGBAf32::new(0.0, 1.0, 0.0, 1.0);
+  let expected = vec![
+    quad(RENDER_LAYER_TILES, green, Vec2f32(100.0, 0.0), Vec2f32(16.0, 16.0)),
+    quad(RENDER_LAYER_TILES, green, Vec2f32(116.0, 16.0), Vec2f32(16.0, 16.0)),
+    quad(RENDER_LAYER_DEBUG, RGBAf32::new(1.0, 0.0, 0.0, 0.5), Vec2f32(5.0, 6.0), Vec2f32(10.0, 20.0)),
+  ];
+  assert!(build_draw_list(&g_state, &tile_bank) == expected);
+}
+
+#[test]
+fn debug_draw_needs_aabb() {
+  let mut g_state = GameState::new();
+  g_state.comp_debug_draw.add_component(CompDebugDraw {
+    entity_id: EntityID(0),
+    color: RGBf32::new(1.0, 0.0, 0.0),
+  });
+  assert!(build_draw_list(&g_state, &TileBank::new()).is_empty());
+}
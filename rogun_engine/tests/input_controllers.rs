@@ -0,0 +1,239 @@
+extern crate engine;
+extern crate glium;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_control::*;
+use engine::input::InputSystem;
+use engine::input::input_map::Binding;
+use engine::input::input_source::ScriptedInput;
+use engine::state::GameState;
+use glium::glutin::VirtualKeyCode;
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Game state with a single dynamic body, controlled with A / D and Space.
+fn setup() -> (GameState<'static>, InputSystem) {
+  let mut g_state = GameState::new();
+  // Standing on the ground, as the physics system would find
+  common::add_body(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(16.0, 16.0)).grounded = true;
+  g_state.comp_lr_controller.add_component(CompLRController {
+    entity_id: EntityID(0),
+    action_l: "move_left".to_owned(),
+    action_r: "move_right".to_owned(),
+    force: 100.0,
+  });
+  g_state.comp_jump_controller.add_component(
+    CompJumpController::new(EntityID(0), "jump", 200.0, 2));
+
+  let mut input = InputSystem::new();
+  input.input_map.bind("move_left", Binding::Key(VirtualKeyCode::A));
+  input.input_map.bind("move_right", Binding::Key(VirtualKeyCode::D));
+  input.input_map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+  (g_state, input)
+}
+
+/// Run a frame of input, returning the body's force and velocity, then
+/// clear the force as the physics system would.
+fn step(g_state: &mut GameState, input: &mut InputSystem, script: &mut ScriptedInput) -> (Vec2f32, Vec2f32) {
+  input.process_input(g_state, script, DT);
+  let body = g_state.comp_body.get_component_mut(EntityID(0)).unwrap();
+  let result = (body.force, body.vel);
+  body.force = Vec2f32(0.0, 0.0);
+  result
+}
+
+#[test]
+fn lr_controller_follows_actions() {
+  let (mut g_state, mut input) = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(1, VirtualKeyCode::D, 2);
+  script.key_press(4, VirtualKeyCode::A);
+  script.key_press(5, VirtualKeyCode::D);
+
+  let mut forces = Vec::new();
+  for _ in 0..6 {
+    let (force, _) = step(&mut g_state, &mut input, &mut script);
+    forces.push(force.0);
+  }
+  // Holding both directions cancels out
+  assert!(forces == vec![0.0, 100.0, 100.0, 0.0, -100.0, 0.0]);
+  assert!(script.is_finished());
+}
+
+#[test]
+fn jump_only_on_press() {
+  let (mut g_state, mut input) = setup();
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::Space);
+
+  let (_, vel) = step(&mut g_state, &mut input, &mut script);
+  assert!(vel.1 == -200.0);
+
+  // Holding jump doesn't jump again
+  g_state.comp_body.get_component_mut(EntityID(0)).unwrap().vel.1 = -50.0;
+  let (_, vel) = step(&mut g_state, &mut input, &mut script);
+  assert!(vel.1 == -50.0);
+}
+
+#[test]
+fn early_release_cuts_jump() {
+  let (mut g_state, mut input) = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::Space, 1);
+
+  step(&mut g_state, &mut input, &mut script);
+  g_state.comp_body.get_component_mut(EntityID(0)).unwrap().grounded = false;
+  let (_, vel) = step(&mut g_state, &mut input, &mut script);
+  assert!(vel.1 == -100.0);
+}
+
+#[test]
+fn double_jump_limited_by_max_jumps() {
+  let (mut g_state, mut input) = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::Space, 1);
+  script.key_tap(2, VirtualKeyCode::Space, 1);
+  script.key_tap(4, VirtualKeyCode::Space, 1);
+
+  let mut jumps = 0;
+  for _ in 0..6 {
+    let (_, vel) = step(&mut g_state, &mut input, &mut script);
+    let body = g_state.comp_body.get_component_mut(EntityID(0)).unwrap();
+    if vel.1 == -200.0 { jumps += 1; }
+    body.vel.1 = 0.0;
+    body.grounded = false;
+  }
+  assert!(jumps == 2);
+}
+
+/// Game state with a body controlled with WASD, and a dash on Space.
+fn setup_top_down(eight_way: bool) -> (GameState<'static>, InputSystem) {
+  let (mut g_state, mut input) = setup();
+  g_state.comp_lr_controller.remove_component(EntityID(0));
+  g_state.comp_jump_controller.remove_component(EntityID(0));
+  let mut c = CompTopDownController::new(EntityID(0), "up", "down", "left", "right", 100.0);
+  c.eight_way = eight_way;
+  c.action_dash = Some("dash".to_owned());
+  c.dash_speed = 400.0;
+  c.dash_time = 0.1;
+  c.dash_cooldown = 0.5;
+  g_state.comp_top_down_controller.add_component(c);
+
+  input.input_map.bind("up", Binding::Key(VirtualKeyCode::W));
+  input.input_map.bind("down", Binding::Key(VirtualKeyCode::S));
+  input.input_map.bind("left", Binding::Key(VirtualKeyCode::A));
+  input.input_map.bind("right", Binding::Key(VirtualKeyCode::D));
+  input.input_map.bind("dash", Binding::Key(VirtualKeyCode::Space));
+  (g_state, input)
+}
+
+#[test]
+fn top_down_diagonals_are_normalized() {
+  let (mut g_state, mut input) = setup_top_down(true);
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::W);
+  script.key_press(0, VirtualKeyCode::D);
+
+  // Takes a tenth of a second to reach full speed
+  let (_, vel) = step(&mut g_state, &mut input, &mut script);
+  assert!((vel.len() - 100.0 * DT * 10.0).abs() < 0.001);
+  let mut vel = vel;
+  for _ in 0..10 { vel = step(&mut g_state, &mut input, &mut script).1; }
+  assert!((vel.len() - 100.0).abs() < 0.001);
+  assert!(vel.0 > 0.0 && (vel.0 + vel.1).abs() < 0.001);
+}
+
+#[test]
+fn top_down_four_way_uses_latest_press() {
+  let (mut g_state, mut input) = setup_top_down(false);
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::D);
+  script.key_press(10, VirtualKeyCode::S);
+  script.key_release(20, VirtualKeyCode::S);
+
+  let mut vel = Vec2f32(0.0, 0.0);
+  for _ in 0..10 { vel = step(&mut g_state, &mut input, &mut script).1; }
+  assert!(vel.0 == 100.0 && vel.1 == 0.0);
+  for _ in 0..10 { vel = step(&mut g_state, &mut input, &mut script).1; }
+  assert!(vel.0 == 0.0 && vel.1 == 100.0);
+  for _ in 0..10 { vel = step(&mut g_state, &mut input, &mut script).1; }
+  assert!(vel.0 == 100.0 && vel.1 == 0.0);
+}
+
+#[test]
+fn top_down_dash_has_cooldown() {
+  let (mut g_state, mut input) = setup_top_down(true);
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::Space, 1);
+  script.key_tap(10, VirtualKeyCode::Space, 1);
+  script.key_tap(40, VirtualKeyCode::Space, 1);
+
+  let mut dashes = Vec::new();
+  let mut dash_time_left = 0.0;
+  for ii in 0..50 {
+    let (_, vel) = step(&mut g_state, &mut input, &mut script);
+    let c = g_state.comp_top_down_controller.get_component(EntityID(0)).unwrap();
+    if c.dash_time_left > dash_time_left {
+      assert!(vel.len() == 400.0);
+      dashes.push(ii);
+    }
+    dash_time_left = c.dash_time_left;
+  }
+  // Dashes facing down, the second press is still on cooldown
+  assert!(dashes == vec![0, 40]);
+  let vel = g_state.comp_body.get_component(EntityID(0)).unwrap().vel;
+  assert!(vel.0 == 0.0 && vel.1 > 0.0);
+}
+
+/// Run frames of input with a single jump allowed, setting whether the body
+/// is on the ground before each frame. Returns the frames it jumped on. Jumps
+/// where the key has already been released are cut short, but still count.
+fn jump_frames<F: Fn(u32) -> bool>(script: &mut ScriptedInput, frames: u32, grounded: F) -> Vec<u32> {
+  let (mut g_state, mut input) = setup();
+  g_state.comp_jump_controller.get_component_mut(EntityID(0)).unwrap().max_jumps = 1;
+  let mut jumps = Vec::new();
+  for frame in 0..frames {
+    g_state.comp_body.get_component_mut(EntityID(0)).unwrap().grounded = grounded(frame);
+    let (_, vel) = step(&mut g_state, &mut input, script);
+    if vel.1 < 0.0 { jumps.push(frame); }
+    g_state.comp_body.get_component_mut(EntityID(0)).unwrap().vel.1 = 0.0;
+  }
+  jumps
+}
+
+#[test]
+fn jump_buffered_before_landing() {
+  // Falls from the start, and presses jump on frame 10, 0.1 seconds (6
+  // frames) of buffer
+  let mut script = ScriptedInput::new();
+  script.key_tap(10, VirtualKeyCode::Space, 1);
+  assert!(jump_frames(&mut script, 20, |frame| frame >= 13) == vec![13]);
+
+  // Landing after the buffer runs out doesn't jump
+  let mut script = ScriptedInput::new();
+  script.key_tap(10, VirtualKeyCode::Space, 1);
+  assert!(jump_frames(&mut script, 30, |frame| frame >= 20).is_empty());
+}
+
+#[test]
+fn jump_allowed_during_coyote_time() {
+  // Walks off a ledge after frame 0, with 0.1 seconds (6 frames) of coyote
+  // time
+  let mut script = ScriptedInput::new();
+  script.key_tap(3, VirtualKeyCode::Space, 1);
+  assert!(jump_frames(&mut script, 10, |frame| frame == 0) == vec![3]);
+
+  // Too late, the ground jump is used up
+  let mut script = ScriptedInput::new();
+  script.key_tap(10, VirtualKeyCode::Space, 1);
+  assert!(jump_frames(&mut script, 20, |frame| frame == 0).is_empty());
+
+  // Coyote time doesn't give a second jump after jumping off the ground
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::Space, 1);
+  script.key_tap(3, VirtualKeyCode::Space, 1);
+  assert!(jump_frames(&mut script, 10, |frame| frame == 0) == vec![0]);
+}
@@ -0,0 +1,37 @@
+extern crate engine;
+extern crate glium;
+
+use engine::input::input_map::{InputMap, Binding};
+use glium::glutin::{VirtualKeyCode, MouseButton};
+
+#[test]
+fn config_round_trip() {
+  let mut map = InputMap::new();
+  map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+  map.bind("jump", Binding::Key(VirtualKeyCode::W));
+  map.bind("fire", Binding::Mouse(MouseButton::Left));
+  map.bind_axis("move_x", Binding::Key(VirtualKeyCode::A), Binding::Key(VirtualKeyCode::D));
+
+  let loaded = InputMap::from_config_str(&map.to_config_string()).unwrap();
+  assert!(loaded.get_bindings("jump") == map.get_bindings("jump"));
+  assert!(loaded.get_bindings("fire") == &[Binding::Mouse(MouseButton::Left)]);
+  let (neg, pos) = loaded.get_axis_bindings("move_x");
+  assert!(neg == &[Binding::Key(VirtualKeyCode::A)]);
+  assert!(pos == &[Binding::Key(VirtualKeyCode::D)]);
+}
+
+#[test]
+fn config_errors_report_line() {
+  let config = "# Controls\naction jump = key:Space\naction fire = key:NotAKey\n";
+  let err = InputMap::from_config_str(config).err().unwrap();
+  assert!(err.contains("line 3"));
+}
+
+#[test]
+fn rebind_replaces_bindings() {
+  let mut map = InputMap::new();
+  map.bind("jump", Binding::Key(VirtualKeyCode::Space));
+  map.bind("jump", Binding::Key(VirtualKeyCode::W));
+  map.rebind("jump", Binding::Mouse(MouseButton::Right));
+  assert!(map.get_bindings("jump") == &[Binding::Mouse(MouseButton::Right)]);
+}
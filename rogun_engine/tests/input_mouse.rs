@@ -0,0 +1,82 @@
+extern crate engine;
+extern crate glium;
+
+use engine::common::vec::Vec2f32;
+use engine::input::InputSystem;
+use engine::input::input_source::ScriptedInput;
+use engine::state::GameState;
+use glium::glutin::{Event, MouseButton, MouseScrollDelta, TouchPhase};
+
+/// Run a frame of input with no game state to control.
+fn step(input: &mut InputSystem, script: &mut ScriptedInput) {
+  input.process_input(&mut GameState::new(), script, 0.1);
+}
+
+#[test]
+fn mouse_button_edges() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.mouse_press(1, MouseButton::Right);
+  script.mouse_release(4, MouseButton::Right);
+
+  step(&mut input, &mut script);
+  assert!(input.is_mouse_button_down(MouseButton::Right).is_none());
+
+  // Just pressed on the first frame only
+  step(&mut input, &mut script);
+  let b = input.is_mouse_button_down(MouseButton::Right).unwrap();
+  assert!(b.just_pressed);
+  assert!(input.is_mouse_button_down(MouseButton::Left).is_none());
+  step(&mut input, &mut script);
+  assert!(!input.is_mouse_button_down(MouseButton::Right).unwrap().just_pressed);
+  assert!(input.is_mouse_button_just_released(MouseButton::Right).is_none());
+
+  // Just released on the release frame only, with how long it was held
+  step(&mut input, &mut script);
+  step(&mut input, &mut script);
+  assert!(input.is_mouse_button_down(MouseButton::Right).is_none());
+  let b = input.is_mouse_button_just_released(MouseButton::Right).unwrap();
+  assert!((b.hold_time - 0.3).abs() < 0.0001);
+  step(&mut input, &mut script);
+  assert!(input.is_mouse_button_just_released(MouseButton::Right).is_none());
+}
+
+#[test]
+fn mouse_world_pos_uses_view() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.mouse_move(0, 300, 120);
+
+  step(&mut input, &mut script);
+  assert!(input.get_mouse_pos() == Vec2f32(300.0, 120.0));
+  // Identity view by default
+  assert!(input.get_mouse_world_pos() == Vec2f32(300.0, 120.0));
+
+  // 4 pixels per unit, scrolled to (-50, 200)
+  input.set_view(Vec2f32(-50.0, 200.0), 4.0);
+  assert!(input.get_mouse_world_pos() == Vec2f32(25.0, 230.0));
+
+  // The position stays put between moves
+  step(&mut input, &mut script);
+  assert!(input.get_mouse_world_pos() == Vec2f32(25.0, 230.0));
+}
+
+#[test]
+fn wheel_delta_resets_every_frame() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.mouse_wheel(0, 0.0, 1.0);
+  script.mouse_wheel(0, 0.5, 2.0);
+  // 20 pixels to a line
+  script.push_event(2, Event::MouseWheel(MouseScrollDelta::PixelDelta(-10.0, -40.0), TouchPhase::Moved));
+
+  // Deltas in the same frame add up
+  step(&mut input, &mut script);
+  assert!(input.get_wheel_delta() == Vec2f32(0.5, 3.0));
+  step(&mut input, &mut script);
+  assert!(input.get_wheel_delta() == Vec2f32(0.0, 0.0));
+  step(&mut input, &mut script);
+  assert!(input.get_wheel_delta() == Vec2f32(-0.5, -2.0));
+  step(&mut input, &mut script);
+  assert!(input.get_wheel_delta() == Vec2f32(0.0, 0.0));
+}
@@ -0,0 +1,98 @@
+extern crate engine;
+extern crate glium;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_control::*;
+use engine::input::InputSystem;
+use engine::input::input_map::Binding;
+use engine::input::input_source::ScriptedInput;
+use engine::input::recording::InputRecording;
+use engine::physics::PhysicsSystem;
+use engine::state::GameState;
+use engine::tile::TileBank;
+use glium::glutin::{VirtualKeyCode, MouseButton};
+
+/// Game state with a body moved left and right with A / D.
+fn setup() -> (GameState<'static>, InputSystem) {
+  let mut g_state = GameState::new();
+  common::add_body(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(16.0, 16.0));
+  g_state.comp_lr_controller.add_component(CompLRController {
+    entity_id: EntityID(0),
+    action_l: "move_left".to_owned(),
+    action_r: "move_right".to_owned(),
+    force: 100.0,
+  });
+
+  let mut input = InputSystem::new();
+  input.input_map.bind("move_left", Binding::Key(VirtualKeyCode::A));
+  input.input_map.bind("move_right", Binding::Key(VirtualKeyCode::D));
+  (g_state, input)
+}
+
+/// Run frames of input and physics, with varying frame deltas. Every frame
+/// the body is nudged by a random amount, so the RNG affects the outcome.
+fn run(g_state: &mut GameState, input: &mut InputSystem, script: &mut ScriptedInput, frames: u32) {
+  let tile_bank = TileBank::new();
+  let mut physics = PhysicsSystem::new();
+  for ii in 0..frames {
+    let dt = 1.0 / (50.0 + (ii % 7) as f32);
+    let dt = input.process_input(g_state, script, dt);
+    let nudge = g_state.rng.range(-1.0, 1.0);
+    g_state.comp_body.get_component_mut(EntityID(0)).unwrap().apply_force(Vec2f32(0.0, nudge));
+    physics.update_physics(g_state, &tile_bank, dt);
+  }
+}
+
+#[test]
+fn playback_reproduces_recording() {
+  let (mut g_state, mut input) = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(2, VirtualKeyCode::D, 20);
+  script.key_tap(30, VirtualKeyCode::A, 5);
+  script.mouse_move(31, 40, 50);
+  script.mouse_press(32, MouseButton::Left);
+  input.start_recording(&mut g_state);
+  run(&mut g_state, &mut input, &mut script, 60);
+  let recording = input.stop_recording().unwrap();
+  let expected = g_state.state_hash();
+
+  let recording = InputRecording::from_bytes(&recording.to_bytes()).unwrap();
+  assert!(recording.frames.len() == 60);
+  let (mut g_state, mut input) = setup();
+  input.start_playback(recording, &mut g_state).unwrap();
+  // The playback ignores anything from the input source
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::A);
+  run(&mut g_state, &mut input, &mut script, 60);
+  assert!(g_state.state_hash() == expected);
+  assert!(input.get_mouse_pos() == Vec2f32(40.0, 50.0));
+  assert!(input.is_mouse_button_down(MouseButton::Left).is_some());
+
+  // Playback stops after the last frame, releasing everything
+  run(&mut g_state, &mut input, &mut ScriptedInput::new(), 1);
+  assert!(!input.is_playing_back());
+  assert!(input.is_mouse_button_down(MouseButton::Left).is_none());
+}
+
+#[test]
+fn idle_frames_are_compact() {
+  let (mut g_state, mut input) = setup();
+  input.start_recording(&mut g_state);
+  for _ in 0..1000 {
+    input.process_input(&mut g_state, &mut ScriptedInput::new(), 1.0 / 60.0);
+  }
+  let bytes = input.stop_recording().unwrap().to_bytes();
+  assert!(bytes.len() < 1100);
+}
+
+#[test]
+fn rejects_other_engine_versions() {
+  let (mut g_state, mut input) = setup();
+  let mut recording = InputRecording::new(0);
+  recording.engine_version = "0.0.0-other".to_owned();
+  assert!(input.start_playback(recording, &mut g_state).is_err());
+  assert!(InputRecording::from_bytes(b"not a recording").is_none());
+}
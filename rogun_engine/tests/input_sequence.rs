@@ -0,0 +1,75 @@
+extern crate engine;
+extern crate glium;
+
+use engine::input::InputSystem;
+use engine::input::input_map::Binding;
+use engine::input::input_source::ScriptedInput;
+use engine::input::sequence::InputSequence;
+use engine::state::GameState;
+use glium::glutin::VirtualKeyCode;
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Input system with direction and punch actions, and a double tap dash and
+/// quarter circle punch sequence.
+fn setup() -> InputSystem {
+  let mut input = InputSystem::new();
+  input.input_map.bind("right", Binding::Key(VirtualKeyCode::D));
+  input.input_map.bind("down", Binding::Key(VirtualKeyCode::S));
+  input.input_map.bind("down_right", Binding::Key(VirtualKeyCode::C));
+  input.input_map.bind("punch", Binding::Key(VirtualKeyCode::J));
+  input.add_sequence(InputSequence::new("dash", &[("right", 0.0), ("right", 0.25)]));
+  input.add_sequence(InputSequence::new("fireball", &[
+    ("down", 0.0), ("down_right", 0.2), ("right", 0.2), ("punch", 0.2)]));
+  input
+}
+
+/// Run frames of input, returning the frames each sequence triggered on.
+fn run(input: &mut InputSystem, script: &mut ScriptedInput, frames: u32, name: &str) -> Vec<u32> {
+  let mut triggered = Vec::new();
+  for ii in 0..frames {
+    input.process_input(&mut GameState::new(), script, DT);
+    if input.is_sequence_triggered(name) { triggered.push(ii); }
+  }
+  triggered
+}
+
+#[test]
+fn double_tap_within_window() {
+  let mut input = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::D, 3);
+  script.key_tap(10, VirtualKeyCode::D, 3);
+  // Too slow, 30 frames is half a second
+  script.key_tap(40, VirtualKeyCode::D, 3);
+  script.key_tap(70, VirtualKeyCode::D, 3);
+  assert!(run(&mut input, &mut script, 80, "dash") == vec![10]);
+}
+
+#[test]
+fn motion_input_in_order() {
+  let mut input = setup();
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::S, 2);
+  script.key_tap(3, VirtualKeyCode::C, 2);
+  script.key_tap(6, VirtualKeyCode::D, 2);
+  script.key_tap(9, VirtualKeyCode::J, 2);
+  // Out of order doesn't count
+  script.key_tap(20, VirtualKeyCode::S, 2);
+  script.key_tap(23, VirtualKeyCode::D, 2);
+  script.key_tap(26, VirtualKeyCode::J, 2);
+  assert!(run(&mut input, &mut script, 40, "fireball") == vec![9]);
+}
+
+#[test]
+fn first_step_restarts_sequence() {
+  let mut input = setup();
+  let mut script = ScriptedInput::new();
+  // The second down restarts the timing window
+  script.key_tap(0, VirtualKeyCode::S, 2);
+  script.key_tap(10, VirtualKeyCode::S, 2);
+  script.key_tap(20, VirtualKeyCode::C, 2);
+  script.key_tap(23, VirtualKeyCode::D, 2);
+  script.key_tap(26, VirtualKeyCode::J, 2);
+  assert!(run(&mut input, &mut script, 40, "fireball") == vec![26]);
+}
@@ -0,0 +1,96 @@
+extern crate engine;
+extern crate glium;
+
+use engine::input::InputSystem;
+use engine::input::input_map::Binding;
+use engine::input::input_source::ScriptedInput;
+use engine::state::GameState;
+use glium::glutin::{VirtualKeyCode, MouseButton};
+
+const DT: f32 = 0.1;
+
+/// Run a frame of input with no game state to control.
+fn step(input: &mut InputSystem, script: &mut ScriptedInput) {
+  input.process_input(&mut GameState::new(), script, DT);
+}
+
+#[test]
+fn hold_time_reported_on_release() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.key_tap(0, VirtualKeyCode::X, 5);
+
+  step(&mut input, &mut script);
+  let k = input.is_key_down(VirtualKeyCode::X).unwrap();
+  assert!(k.just_pressed && k.hold_frames == 0 && k.hold_time == 0.0);
+  for _ in 0..4 { step(&mut input, &mut script); }
+  let k = input.is_key_down(VirtualKeyCode::X).unwrap();
+  assert!(!k.just_pressed && k.hold_frames == 4);
+
+  step(&mut input, &mut script);
+  assert!(input.is_key_down(VirtualKeyCode::X).is_none());
+  let k = input.is_key_just_released(VirtualKeyCode::X).unwrap();
+  assert!(k.hold_frames == 5 && (k.hold_time - 0.5).abs() < 0.0001);
+
+  // Only reported for one frame
+  step(&mut input, &mut script);
+  assert!(input.is_key_just_released(VirtualKeyCode::X).is_none());
+}
+
+#[test]
+fn keys_auto_repeat() {
+  let mut input = InputSystem::new();
+  input.set_key_repeat(0.25, 0.1);
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::Down);
+
+  let mut repeats = Vec::new();
+  for _ in 0..6 {
+    step(&mut input, &mut script);
+    repeats.push(input.is_key_repeated(VirtualKeyCode::Down));
+  }
+  // Pressed at 0, then repeats at 0.25, 0.35, 0.45...
+  assert!(repeats == vec![true, false, false, true, true, true]);
+
+  input.disable_key_repeat();
+  step(&mut input, &mut script);
+  assert!(!input.is_key_repeated(VirtualKeyCode::Down));
+}
+
+#[test]
+fn zero_and_negative_repeat_settings_are_clamped() {
+  for &(delay, interval) in &[(0.0, 0.0), (-1.0, -1.0), (0.1, ::std::f32::NAN)] {
+    let mut input = InputSystem::new();
+    input.set_key_repeat(delay, interval);
+    let mut script = ScriptedInput::new();
+    script.key_press(0, VirtualKeyCode::Down);
+    // Repeats every frame rather than overflowing the repeat count
+    for _ in 0..5 {
+      step(&mut input, &mut script);
+      assert!(input.is_key_repeated(VirtualKeyCode::Down));
+    }
+  }
+}
+
+#[test]
+fn action_release_and_hold_time() {
+  let mut input = InputSystem::new();
+  input.input_map.bind("fire", Binding::Key(VirtualKeyCode::Space));
+  input.input_map.bind("fire", Binding::Mouse(MouseButton::Left));
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::Space);
+  script.mouse_press(2, MouseButton::Left);
+  script.key_release(4, VirtualKeyCode::Space);
+  script.mouse_release(5, MouseButton::Left);
+
+  for _ in 0..5 { step(&mut input, &mut script); }
+  // Still held with the mouse
+  assert!(!input.is_action_just_released("fire"));
+  assert!((input.get_action_hold_time("fire").unwrap() - 0.2).abs() < 0.0001);
+
+  step(&mut input, &mut script);
+  assert!(input.is_action_just_released("fire"));
+  assert!((input.get_action_hold_time("fire").unwrap() - 0.3).abs() < 0.0001);
+  step(&mut input, &mut script);
+  assert!(input.get_action_hold_time("fire").is_none());
+}
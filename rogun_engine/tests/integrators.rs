@@ -0,0 +1,90 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::physics::PhysicsSystem;
+use engine::physics::integrator::Integrator;
+use engine::state::GameState;
+use engine::tile::TileBank;
+
+const GRAVITY: f32 = 500.0;
+const DT: f32 = 1.0 / 60.0;
+
+/// Fire a projectile under gravity for the given number of frames, returning
+/// its final position and velocity.
+fn fire(integrator: Integrator, vel: Vec2f32, max_speed: f32, frames: u32) -> (Vec2f32, Vec2f32) {
+  let mut g_state = GameState::new();
+  let tile_bank = TileBank::new();
+  let mut physics = PhysicsSystem::new();
+  physics.integrator = integrator;
+
+  let body = common::add_body(&mut g_state, 0, Vec2f32(0.0, 0.0), Vec2f32(1.0, 1.0));
+  body.vel = vel;
+  body.mass = 2.0;
+  body.max_speed = max_speed;
+
+  for _ in 0..frames {
+    let body = g_state.comp_body.get_component_mut(EntityID(0)).unwrap();
+    let mass = body.mass;
+    body.apply_force(Vec2f32(0.0, GRAVITY * mass));
+    physics.update_physics(&mut g_state, &tile_bank, DT);
+  }
+
+  let pos = g_state.comp_aabb.get_component(EntityID(0)).unwrap().pos;
+  let vel = g_state.comp_body.get_component(EntityID(0)).unwrap().vel;
+  (pos, vel)
+}
+
+/// Analytic position of a projectile after time t.
+fn analytic(vel: Vec2f32, t: f32) -> Vec2f32 {
+  Vec2f32(vel.0 * t, vel.1 * t + 0.5 * GRAVITY * t * t)
+}
+
+#[test]
+fn verlet_matches_projectile_motion() {
+  let v0 = Vec2f32(100.0, -300.0);
+  let (pos, vel) = fire(Integrator::VelocityVerlet, v0, 10000.0, 120);
+  let expected = analytic(v0, 120.0 * DT);
+  assert!((pos - expected).len() < 0.01);
+  assert!((vel.1 - (v0.1 + GRAVITY * 120.0 * DT)).abs() < 0.01);
+}
+
+#[test]
+fn rk4_matches_projectile_motion() {
+  let v0 = Vec2f32(100.0, -300.0);
+  let (pos, _) = fire(Integrator::Rk4, v0, 10000.0, 120);
+  let expected = analytic(v0, 120.0 * DT);
+  assert!((pos - expected).len() < 0.01);
+}
+
+#[test]
+fn semi_implicit_euler_error_is_bounded() {
+  // Semi-implicit euler overshoots by g * t * dt / 2 each axis under gravity
+  let v0 = Vec2f32(100.0, -300.0);
+  let t = 120.0 * DT;
+  let (pos, _) = fire(Integrator::SemiImplicitEuler, v0, 10000.0, 120);
+  let expected = analytic(v0, t);
+  assert!((pos.0 - expected.0).abs() < 0.01);
+  assert!((pos.1 - expected.1 - GRAVITY * t * DT / 2.0).abs() < 0.01);
+}
+
+#[test]
+fn speed_cap_holds_for_every_integrator() {
+  // Falling from rest, capped at 200. Reaches the cap at t = 0.4, after which
+  // it falls at constant speed.
+  for &integrator in &[Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::Rk4] {
+    let (pos, vel) = fire(integrator, Vec2f32(0.0, 0.0), 200.0, 60);
+    assert!(vel.len() <= 200.0 + 0.001);
+    let expected = 0.5 * GRAVITY * 0.4 * 0.4 + 200.0 * 0.6;
+    assert!((pos.1 - expected).abs() < 200.0 * DT);
+  }
+}
+
+#[test]
+fn zero_velocity_with_zero_speed_cap() {
+  let (pos, vel) = fire(Integrator::VelocityVerlet, Vec2f32(0.0, 0.0), 0.0, 10);
+  assert!(pos.0 == 0.0 && pos.1 == 0.0);
+  assert!(vel.0 == 0.0 && vel.1 == 0.0);
+}
@@ -0,0 +1,107 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::atlas::AtlasRegion;
+use engine::renderer::draw_list::*;
+use engine::renderer::software::SoftwareRenderer;
+use engine::state::GameState;
+use engine::tile::*;
+
+const REGION: AtlasRegion = AtlasRegion { x: 0, y: 0, w: 1, h: 1 };
+
+/// Add a 4x4 sprite entity at pos, tinted tint.
+fn add_sprite(g_state: &mut GameState, id: u16, pos: Vec2f32, tint: RGBAf32) -> CompSprite {
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(id),
+    pos: pos,
+    size: Vec2f32(4.0, 4.0),
+  });
+  let mut sprite = CompSprite::new(EntityID(id), REGION);
+  sprite.size = Vec2f32(4.0, 4.0);
+  sprite.tint = tint;
+  sprite
+}
+
+/// The (layer, z) and tint of each command, in draw order.
+fn order(list: &[DrawCommand]) -> Vec<(i32, i32, f32)> {
+  list.iter().map(|c| (c.layer, c.z, c.color.r)).collect()
+}
+
+#[test]
+fn sorts_by_layer_then_z() {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(Tile::new(1));
+  let mut g_state = GameState::new();
+
+  // A background map after a world map in the list is still drawn first
+  let mut map = TileMap16::new();
+  map.tiles[0] = 1;
+  g_state.tile_maps.push(map);
+  let mut map = TileMap16::new();
+  map.tiles[0] = 1;
+  map.layer = RENDER_LAYER_BACKGROUND;
+  g_state.tile_maps.push(map);
+
+  let white = |v: f32| RGBAf32::new(v, 1.0, 1.0, 1.0);
+  let mut s = add_sprite(&mut g_state, 0, Vec2f32(0.0, 0.0), white(0.1));
+  s.z = 5;
+  g_state.comp_sprite.add_component(s);
+  let mut s = add_sprite(&mut g_state, 1, Vec2f32(0.0, 0.0), white(0.2));
+  s.layer = RENDER_LAYER_UI;
+  g_state.comp_sprite.add_component(s);
+  let s = add_sprite(&mut g_state, 2, Vec2f32(0.0, 0.0), white(0.3));
+  g_state.comp_sprite.add_component(s);
+  let mut s = add_sprite(&mut g_state, 3, Vec2f32(0.0, 0.0), white(0.4));
+  s.z = -1;
+  s.layer = RENDER_LAYER_TILES;
+  g_state.comp_sprite.add_component(s);
+  let mut s = add_sprite(&mut g_state, 4, Vec2f32(0.0, 0.0), white(0.5));
+  s.layer = RENDER_LAYER_FOREGROUND;
+  g_state.comp_sprite.add_component(s);
+  let s = add_sprite(&mut g_state, 5, Vec2f32(0.0, 0.0), white(0.6));
+  g_state.comp_sprite.add_component(s);
+
+  let list = build_draw_list(&g_state, &tile_bank);
+  assert!(order(&list) == vec![
+    (RENDER_LAYER_BACKGROUND, 0, 0.0),
+    (RENDER_LAYER_TILES, -1, 0.4),
+    (RENDER_LAYER_TILES, 0, 0.0),
+    (RENDER_LAYER_SPRITES, 0, 0.3),
+    (RENDER_LAYER_SPRITES, 0, 0.6),
+    (RENDER_LAYER_SPRITES, 5, 0.1),
+    (RENDER_LAYER_FOREGROUND, 0, 0.5),
+    (RENDER_LAYER_UI, 0, 0.2),
+  ]);
+}
+
+#[test]
+fn blends_layers_and_draws_ui_in_screen_space() {
+  let mut g_state = GameState::new();
+  g_state.camera.set_pos(Vec2f32(100.0, 100.0));
+
+  // Half transparent blue over opaque red, then a UI sprite at the top left
+  // of the screen
+  let s = add_sprite(&mut g_state, 0, Vec2f32(100.0, 100.0), RGBAf32::new(1.0, 0.0, 0.0, 1.0));
+  g_state.comp_sprite.add_component(s);
+  let mut s = add_sprite(&mut g_state, 1, Vec2f32(102.0, 100.0), RGBAf32::new(0.0, 0.0, 1.0, 0.5));
+  s.layer = RENDER_LAYER_FOREGROUND;
+  g_state.comp_sprite.add_component(s);
+  let mut s = add_sprite(&mut g_state, 2, Vec2f32(0.0, 6.0), RGBAf32::new(0.0, 1.0, 0.0, 1.0));
+  s.layer = RENDER_LAYER_UI;
+  s.screen_space = true;
+  g_state.comp_sprite.add_component(s);
+
+  let mut r = SoftwareRenderer::new(8, 10);
+  r.render_game(&g_state, &TileBank::new());
+  assert!(r.get_pixel(0, 0) == [255, 0, 0, 255]);
+  assert!(r.get_pixel(2, 0) == [128, 0, 128, 255]);
+  assert!(r.get_pixel(5, 0) == [0, 0, 128, 255]);
+  assert!(r.get_pixel(0, 6) == [0, 255, 0, 255] && r.get_pixel(3, 9) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(4, 6) == [0, 0, 0, 255]);
+  // Screen space commands are never culled
+  assert!(r.get_stats().commands_drawn == 3 && r.get_stats().commands_culled == 0);
+}
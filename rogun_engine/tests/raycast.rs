@@ -0,0 +1,218 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_collision::*;
+use engine::physics::{PhysicsSystem, LAYER_DEFAULT, LAYER_TILES, MASK_ALL};
+use engine::physics::raycast::{HitTarget, RaycastHit};
+use engine::state::GameState;
+use engine::tile::{TileBank, TileMap16};
+use std::f32;
+
+/// Layer of the box added by setup().
+const LAYER_BOX: u32 = 1 << 1;
+
+/// A 16x16 tile map at the origin with a single full tile at (5, 2), so its
+/// world bounds are (80, 32) to (96, 48). A 10x16 box collider on LAYER_BOX
+/// sits at (30, 32).
+fn setup<'a>() -> (GameState<'a>, TileBank) {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(common::full_tile(1));
+
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  map.tiles[2 * 16 + 5] = 1;
+  g_state.tile_maps.push(map);
+
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: Vec2f32(30.0, 32.0),
+    size: Vec2f32(10.0, 16.0),
+  });
+  g_state.comp_coll_aabb.add_component(CompCollAABB {
+    entity_id: EntityID(0),
+    offset: Vec2f32(0.0, 0.0),
+    size: Vec2f32(10.0, 16.0),
+    solid: true,
+    layer: LAYER_BOX,
+    mask: MASK_ALL,
+    callback: None,
+  });
+  (g_state, tile_bank)
+}
+
+fn near(a: Vec2f32, b: Vec2f32) -> bool {
+  (a - b).len() < 0.001
+}
+
+fn is_tile(hit: &RaycastHit, x: usize, y: usize) -> bool {
+  hit.target == HitTarget::Tile { map: 0, x: x, y: y }
+}
+
+#[test]
+fn ray_hits_tile_with_point_normal_and_distance() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+
+  // Left to right, under the box
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(8.0, 40.0), Vec2f32(1.0, 0.0),
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(is_tile(&hit, 5, 2));
+  assert!(near(hit.point, Vec2f32(80.0, 40.0)));
+  assert!(near(hit.normal, Vec2f32(-1.0, 0.0)));
+  assert!((hit.dist - 72.0).abs() < 0.001);
+
+  // Bottom to top, with a direction that isn't normalised
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(88.0, 200.0), Vec2f32(0.0, -5.0),
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(is_tile(&hit, 5, 2));
+  assert!(near(hit.point, Vec2f32(88.0, 48.0)));
+  assert!(near(hit.normal, Vec2f32(0.0, 1.0)));
+  assert!((hit.dist - 152.0).abs() < 0.001);
+}
+
+#[test]
+fn ray_from_outside_the_map_walks_into_it() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(-50.0, 40.0), Vec2f32(1.0, 0.0),
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(is_tile(&hit, 5, 2));
+  assert!(near(hit.point, Vec2f32(80.0, 40.0)));
+  assert!((hit.dist - 130.0).abs() < 0.001);
+
+  // Diagonally, entering the tile through its top left quarter
+  let dir = Vec2f32(1.0, 1.0);
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(50.0, 0.0), dir,
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(is_tile(&hit, 5, 2));
+  assert!(near(hit.point, Vec2f32(82.0, 32.0)));
+  assert!(near(hit.normal, Vec2f32(0.0, -1.0)));
+  assert!((hit.dist - 32.0 * 2.0f32.sqrt()).abs() < 0.001);
+}
+
+#[test]
+fn ray_stops_at_max_distance() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  let cast = |max_dist| physics.raycast(&g_state, &tile_bank, Vec2f32(8.0, 40.0),
+                                        Vec2f32(1.0, 0.0), max_dist, LAYER_TILES);
+  assert!(cast(71.0).is_none());
+  assert!(cast(73.0).is_some());
+  assert!(cast(f32::INFINITY).is_some());
+  assert!(cast(0.0).is_none());
+}
+
+#[test]
+fn raycast_all_sorts_by_distance_and_filters_by_layer() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  let cast = |mask| physics.raycast_all(&g_state, &tile_bank, Vec2f32(8.0, 40.0),
+                                        Vec2f32(1.0, 0.0), 1000.0, mask);
+
+  let hits = cast(MASK_ALL);
+  assert!(hits.len() == 2);
+  assert!(hits[0].target == HitTarget::Entity(EntityID(0)));
+  assert!((hits[0].dist - 22.0).abs() < 0.001);
+  assert!(is_tile(&hits[1], 5, 2));
+
+  let hits = cast(LAYER_TILES);
+  assert!(hits.len() == 1 && is_tile(&hits[0], 5, 2));
+
+  let hits = cast(LAYER_BOX);
+  assert!(hits.len() == 1 && hits[0].target == HitTarget::Entity(EntityID(0)));
+
+  assert!(cast(LAYER_DEFAULT).is_empty());
+
+  // The closest hit is the box, unless the mask leaves it out
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(8.0, 40.0), Vec2f32(1.0, 0.0),
+                            1000.0, MASK_ALL).unwrap();
+  assert!(hit.target == HitTarget::Entity(EntityID(0)));
+  assert!(near(hit.point, Vec2f32(30.0, 40.0)));
+  assert!(near(hit.normal, Vec2f32(-1.0, 0.0)));
+}
+
+#[test]
+fn ray_starting_inside_a_tile_ignores_it() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  assert!(physics.raycast(&g_state, &tile_bank, Vec2f32(88.0, 40.0), Vec2f32(1.0, 0.0),
+                          1000.0, LAYER_TILES).is_none());
+}
+
+#[test]
+fn shape_cast_returns_position_at_impact() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  let size = Vec2f32(8.0, 8.0);
+
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(50.0, 36.0), size,
+                               Vec2f32(1.0, 0.0), 1000.0, MASK_ALL).unwrap();
+  assert!(is_tile(&hit, 5, 2));
+  assert!(near(hit.point, Vec2f32(72.0, 36.0)));
+  assert!(near(hit.normal, Vec2f32(-1.0, 0.0)));
+  assert!((hit.dist - 22.0).abs() < 0.001);
+
+  // Falling onto the tile, only just overlapping its left edge
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(73.0, 0.0), size,
+                               Vec2f32(0.0, 1.0), 1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(73.0, 24.0)));
+  assert!(near(hit.normal, Vec2f32(0.0, -1.0)));
+
+  // Sweeping into the box from the left
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(0.0, 44.0), size,
+                               Vec2f32(1.0, 0.0), 1000.0, MASK_ALL).unwrap();
+  assert!(hit.target == HitTarget::Entity(EntityID(0)));
+  assert!(near(hit.point, Vec2f32(22.0, 44.0)));
+
+  assert!(physics.shape_cast(&g_state, &tile_bank, Vec2f32(50.0, 36.0), size,
+                             Vec2f32(1.0, 0.0), 21.0, MASK_ALL).is_none());
+}
+
+#[test]
+fn degenerate_rays_hit_nothing() {
+  let (g_state, tile_bank) = setup();
+  let physics = PhysicsSystem::new();
+  let origin = Vec2f32(8.0, 40.0);
+  for &dir in &[Vec2f32(0.0, 0.0), Vec2f32(f32::NAN, 0.0), Vec2f32(1.0, f32::INFINITY)] {
+    assert!(physics.raycast(&g_state, &tile_bank, origin, dir, 1000.0, MASK_ALL).is_none());
+    assert!(physics.raycast_all(&g_state, &tile_bank, origin, dir, 1000.0, MASK_ALL).is_empty());
+    assert!(physics.shape_cast(&g_state, &tile_bank, origin, Vec2f32(4.0, 4.0),
+                               dir, 1000.0, MASK_ALL).is_none());
+  }
+  assert!(physics.raycast_all(&g_state, &tile_bank, Vec2f32(f32::NAN, 40.0),
+                              Vec2f32(1.0, 0.0), 1000.0, MASK_ALL).is_empty());
+  assert!(physics.raycast_all(&g_state, &tile_bank, origin,
+                              Vec2f32(1.0, 0.0), f32::NAN, MASK_ALL).is_empty());
+}
+
+#[test]
+fn casts_pass_through_triggers() {
+  let (mut g_state, tile_bank) = setup();
+  // A trigger on the box's layer, in front of the box
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(1),
+    pos: Vec2f32(16.0, 32.0),
+    size: Vec2f32(8.0, 16.0),
+  });
+  g_state.comp_coll_aabb.add_component(CompCollAABB {
+    entity_id: EntityID(1),
+    offset: Vec2f32(0.0, 0.0),
+    size: Vec2f32(8.0, 16.0),
+    solid: false,
+    layer: LAYER_BOX,
+    mask: MASK_ALL,
+    callback: None,
+  });
+  let physics = PhysicsSystem::new();
+
+  let hits = physics.raycast_all(&g_state, &tile_bank, Vec2f32(8.0, 40.0), Vec2f32(1.0, 0.0),
+                                 1000.0, MASK_ALL);
+  assert!(hits.len() == 2 && hits[0].target == HitTarget::Entity(EntityID(0)));
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(0.0, 44.0), Vec2f32(8.0, 8.0),
+                               Vec2f32(1.0, 0.0), 1000.0, MASK_ALL).unwrap();
+  assert!(hit.target == HitTarget::Entity(EntityID(0)));
+}
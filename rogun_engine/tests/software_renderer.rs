@@ -0,0 +1,99 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::draw_list::*;
+use engine::renderer::software::SoftwareRenderer;
+use engine::state::GameState;
+use engine::tile::*;
+
+#[test]
+fn renders_tiles_and_debug_draws() {
+  let mut tile_bank = TileBank::new();
+  let mut tile = Tile::new(1);
+  tile.color = RGBf32::new(0.0, 1.0, 0.0);
+  tile_bank.register_tile(tile);
+
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  map.tiles[1] = 1;
+  g_state.tile_maps.push(map);
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: Vec2f32(20.0, 4.0),
+    size: Vec2f32(4.0, 4.0),
+  });
+  g_state.comp_debug_draw.add_component(CompDebugDraw {
+    entity_id: EntityID(0),
+    color: RGBf32::new(1.0, 0.0, 0.0),
+  });
+
+  let mut r = SoftwareRenderer::new(40, 20);
+  r.render_game(&g_state, &tile_bank);
+  assert!(r.get_pixel(15, 0) == [0, 0, 0, 255]);
+  assert!(r.get_pixel(16, 0) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(31, 15) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(32, 0) == [0, 0, 0, 255]);
+  assert!(r.get_pixel(16, 16) == [0, 0, 0, 255]);
+  // Debug draw on top of the tile, at 50% opacity
+  assert!(r.get_pixel(20, 4) == [128, 128, 0, 255]);
+  assert!(r.get_pixel(23, 7) == [128, 128, 0, 255]);
+  assert!(r.get_pixel(24, 7) == [0, 255, 0, 255]);
+}
+
+#[test]
+fn blends_and_clips() {
+  let mut r = SoftwareRenderer::new(4, 4);
+  r.clear(RGBAf32::new(0.0, 0.0, 1.0, 1.0));
+  r.draw_list(&[DrawCommand {
+    layer: 0,
+    z: 0,
+    screen_space: false,
+    color: RGBAf32::new(1.0, 0.0, 0.0, 0.5),
+    prim: DrawPrim::Quad { pos: Vec2f32(-10.0, 1.0), size: Vec2f32(12.0, 100.0) },
+  }]);
+  assert!(r.get_pixel(0, 0) == [0, 0, 255, 255]);
+  assert!(r.get_pixel(1, 3) == [128, 0, 128, 255]);
+  assert!(r.get_pixel(2, 3) == [0, 0, 255, 255]);
+}
+
+#[test]
+fn png_output() {
+  let mut r = SoftwareRenderer::new(3, 2);
+  r.clear(RGBAf32::new(1.0, 1.0, 1.0, 1.0));
+  let png = r.to_png();
+  assert!(png[..8] == [137, 80, 78, 71, 13, 10, 26, 10]);
+  assert!(png[12..16] == *b"IHDR");
+  assert!(png[16..24] == [0, 0, 0, 3, 0, 0, 0, 2]);
+  assert!(png[png.len() - 8..png.len() - 4] == *b"IEND");
+  // Signature, IHDR, IDAT with a zlib header, 2 rows of filter byte and 3
+  // pixels, block header and checksum, then IEND
+  assert!(png.len() == 8 + 25 + 12 + 2 + 5 + 2 * 13 + 4 + 12);
+}
+
+#[test]
+fn skips_non_finite_quads() {
+  let nan = ::std::f32::NAN;
+  let inf = ::std::f32::INFINITY;
+  let mut r = SoftwareRenderer::new(4, 4);
+  r.clear(RGBAf32::new(0.0, 0.0, 1.0, 1.0));
+  let quads = [(Vec2f32(nan, 1.0), Vec2f32(2.0, 2.0)), (Vec2f32(-inf, 1.0), Vec2f32(2.0, 2.0)),
+               (Vec2f32(0.0, 1.0), Vec2f32(inf, 2.0)), (Vec2f32(0.0, 1.0), Vec2f32(2.0, nan))];
+  for &(pos, size) in &quads {
+    r.draw_list(&[DrawCommand {
+      layer: 0,
+      z: 0,
+      screen_space: false,
+      color: RGBAf32::new(1.0, 0.0, 0.0, 1.0),
+      prim: DrawPrim::Quad { pos: pos, size: size },
+    }]);
+  }
+  for y in 0..4 {
+    for x in 0..4 {
+      assert!(r.get_pixel(x, y) == [0, 0, 255, 255]);
+    }
+  }
+}
@@ -0,0 +1,168 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::png;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::atlas::*;
+use engine::renderer::draw_list::*;
+use engine::renderer::software::SoftwareRenderer;
+use engine::state::GameState;
+use engine::tile::*;
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// 4x2 atlas with two 2x2 regions. "a" has red, green, blue and white
+/// pixels, "b" is all yellow.
+fn test_atlas() -> TextureAtlas {
+  let (r, g, b, w, y) = ([255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255],
+                         [255, 255, 255, 255], [255, 255, 0, 255]);
+  let rows = [r, g, y, y, b, w, y, y];
+  let pixels = rows.iter().flat_map(|p| p.iter().cloned()).collect();
+  let mut atlas = TextureAtlas::from_rgba(4, 2, pixels);
+  atlas.add_grid("cell", 2, 2).unwrap();
+  atlas.add_region("a", 0, 0, 2, 2);
+  atlas.add_region("b", 2, 0, 2, 2);
+  atlas
+}
+
+/// Draw a 2x2 sprite of region "a", scaled up 2x, into a 4x4 framebuffer.
+fn draw(flip_x: bool, flip_y: bool, rotation: f32, tint: RGBAf32) -> SoftwareRenderer {
+  let atlas = test_atlas();
+  let mut r = SoftwareRenderer::new(4, 4);
+  r.set_atlas(&atlas);
+  r.draw_list(&[DrawCommand {
+    layer: 0,
+    z: 0,
+    screen_space: false,
+    color: tint,
+    prim: DrawPrim::Sprite {
+      pos: Vec2f32(0.0, 0.0), size: Vec2f32(4.0, 4.0), region: atlas.get_region("a").unwrap(),
+      flip_x: flip_x, flip_y: flip_y, rotation: rotation,
+    },
+  }]);
+  r
+}
+
+#[test]
+fn decodes_compressed_pngs() {
+  let (w, h, pixels) = png::decode_rgba(include_bytes!("data/gradient.png")).unwrap();
+  assert!(w == 16 && h == 8);
+  for y in 0..8 {
+    for x in 0..16 {
+      let ix = (y * 16 + x) * 4;
+      let expected = [x as u8 * 16, y as u8 * 32, (x + y) as u8 * 8, 255 - x as u8];
+      assert!(pixels[ix..ix + 4] == expected);
+    }
+  }
+
+  let (w, h, pixels) = png::decode_rgba(include_bytes!("data/palette.png")).unwrap();
+  assert!(w == 4 && h == 4);
+  assert!(pixels[0..4] == [0, 0, 0, 0]);
+  assert!(pixels[4..8] == [255, 0, 0, 255]);
+  assert!(pixels[60..64] == [0, 255, 0, 255]);
+}
+
+#[test]
+fn png_round_trip() {
+  let pixels: Vec<u8> = (0..70000 * 4).map(|ii| (ii * 7 % 251) as u8).collect();
+  let (w, h, decoded) = png::decode_rgba(&png::encode_rgba(700, 100, &pixels)).unwrap();
+  assert!(w == 700 && h == 100 && decoded == pixels);
+  assert!(png::decode_rgba(b"not a png").is_err());
+}
+
+#[test]
+fn huge_or_truncated_pngs_are_errors() {
+  let valid = png::encode_rgba(1, 1, &[1, 2, 3, 4]);
+  // IHDR width and height follow the signature and chunk header
+  for &(w, h) in &[(0xffffffffu32, 1u32), (1, 0xffffffff), (png::MAX_DIMENSION + 1, 1),
+                   (0x80000000, 0x80000000)] {
+    let mut data = valid.clone();
+    data[16..20].copy_from_slice(&w.to_be_bytes());
+    data[20..24].copy_from_slice(&h.to_be_bytes());
+    assert!(png::decode_rgba(&data).is_err());
+  }
+
+  // A chunk claiming to be longer than the file
+  let mut data = valid.clone();
+  data[8..12].copy_from_slice(&0xffffffffu32.to_be_bytes());
+  assert!(png::decode_rgba(&data).is_err());
+}
+
+#[test]
+fn atlas_regions() {
+  let atlas = TextureAtlas::load_png(Path::new("tests/data/gradient.png")).unwrap();
+  assert!(atlas.get_width() == 16 && atlas.get_pixel(1, 2) == Some([16, 64, 24, 254]));
+
+  let atlas = test_atlas();
+  assert!(atlas.get_region("cell_1") == Some(AtlasRegion { x: 2, y: 0, w: 2, h: 2 }));
+  assert!(atlas.get_region("cell_2").is_none());
+  assert!(atlas.get_uvs(atlas.get_region("b").unwrap()) == (0.5, 0.0, 1.0, 1.0));
+}
+
+#[test]
+fn sprites_and_tiles_in_draw_list() {
+  let atlas = test_atlas();
+  let mut tile_bank = TileBank::new();
+  let mut tile = Tile::new(1);
+  tile.region = atlas.get_region("b");
+  tile_bank.register_tile(tile);
+
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  map.tiles[0] = 1;
+  g_state.tile_maps.push(map);
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(0),
+    pos: Vec2f32(10.0, 20.0),
+    size: Vec2f32(8.0, 8.0),
+  });
+  let mut sprite = CompSprite::new(EntityID(0), atlas.get_region("a").unwrap());
+  sprite.offset = Vec2f32(-1.0, -2.0);
+  sprite.flip_x = true;
+  g_state.comp_sprite.add_component(sprite);
+
+  let list = build_draw_list(&g_state, &tile_bank);
+  let white = RGBAf32::new(1.0, 1.0, 1.0, 1.0);
+  assert!(list == vec![
+    DrawCommand { layer: RENDER_LAYER_TILES, z: 0, screen_space: false, color: white, prim: DrawPrim::Sprite {
+      pos: Vec2f32(0.0, 0.0), size: Vec2f32(16.0, 16.0), region: atlas.get_region("b").unwrap(),
+      flip_x: false, flip_y: false, rotation: 0.0 } },
+    DrawCommand { layer: RENDER_LAYER_SPRITES, z: 0, screen_space: false, color: white, prim: DrawPrim::Sprite {
+      pos: Vec2f32(9.0, 18.0), size: Vec2f32(2.0, 2.0), region: atlas.get_region("a").unwrap(),
+      flip_x: true, flip_y: false, rotation: 0.0 } },
+  ]);
+}
+
+#[test]
+fn software_sprite_flip_tint_rotation() {
+  let white = RGBAf32::new(1.0, 1.0, 1.0, 1.0);
+  let r = draw(false, false, 0.0, white);
+  assert!(r.get_pixel(0, 0) == [255, 0, 0, 255] && r.get_pixel(1, 1) == [255, 0, 0, 255]);
+  assert!(r.get_pixel(2, 0) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(0, 2) == [0, 0, 255, 255]);
+  assert!(r.get_pixel(3, 3) == [255, 255, 255, 255]);
+
+  let r = draw(true, true, 0.0, white);
+  assert!(r.get_pixel(0, 0) == [255, 255, 255, 255]);
+  assert!(r.get_pixel(3, 3) == [255, 0, 0, 255]);
+
+  let r = draw(false, false, 0.0, RGBAf32::new(1.0, 0.5, 1.0, 1.0));
+  assert!(r.get_pixel(3, 3) == [255, 128, 255, 255]);
+
+  // A quarter turn clockwise moves the top left to the top right
+  let r = draw(false, false, PI / 2.0, white);
+  assert!(r.get_pixel(3, 0) == [255, 0, 0, 255]);
+  assert!(r.get_pixel(3, 3) == [0, 255, 0, 255]);
+  assert!(r.get_pixel(0, 0) == [0, 0, 255, 255]);
+}
+
+#[test]
+fn empty_atlases_and_grids() {
+  let mut atlas = TextureAtlas::from_rgba(0, 0, Vec::new());
+  assert!(atlas.get_pixel(0, 0).is_none());
+  assert!(atlas.add_grid("cell", 0, 2).is_err() && atlas.add_grid("cell", 2, 0).is_err());
+  assert!(atlas.add_grid("cell", 2, 2).is_ok() && atlas.get_region("cell_0").is_none());
+}
@@ -0,0 +1,138 @@
+extern crate engine;
+
+use engine::common::color::*;
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_render::*;
+use engine::renderer::atlas::*;
+use engine::renderer::draw_list::*;
+use engine::renderer::software::SoftwareRenderer;
+use engine::renderer::text::*;
+use engine::state::GameState;
+use engine::tile::TileBank;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Load the test BMFont into a 2x2 atlas. The font has 'A' (white, 3x4,
+/// advance 4), 'B' (red, 3x3 at an offset of (1, 1), advance 5), '?' (same
+/// image as 'A'), a space with an advance of 2, and kerning of -1 for "AB".
+fn load_font() -> (BitmapFont, TextureAtlas) {
+  let mut atlas = TextureAtlas::from_rgba(2, 2, vec![7; 16]);
+  let font = BitmapFont::load_bmfont(Path::new("tests/data/font.fnt"), &mut atlas).unwrap();
+  (font, atlas)
+}
+
+/// Position and size of each glyph sprite.
+fn rects(list: &[DrawCommand]) -> Vec<(Vec2f32, Vec2f32)> {
+  list.iter().map(|c| match c.prim {
+    DrawPrim::Sprite { pos, size, .. } => (pos, size),
+    DrawPrim::Quad { pos, size } => (pos, size),
+  }).collect()
+}
+
+#[test]
+fn loads_bmfont_into_atlas() {
+  let (font, atlas) = load_font();
+  // The page is added under the old atlas, which is kept
+  assert!(atlas.get_width() == 8 && atlas.get_height() == 6);
+  assert!(atlas.get_pixel(1, 1) == Some([7, 7, 7, 7]) && atlas.get_pixel(2, 0) == Some([0, 0, 0, 0]));
+  assert!(atlas.get_region("font.png") == Some(AtlasRegion { x: 0, y: 2, w: 8, h: 4 }));
+  assert!(atlas.get_pixel(4, 2) == Some([255, 0, 0, 255]));
+
+  assert!(font.get_line_height() == 5.0);
+  let b = font.get_glyph('B').unwrap();
+  assert!(b.region == AtlasRegion { x: 4, y: 2, w: 3, h: 3 });
+  assert!(b.offset == Vec2f32(1.0, 1.0) && b.advance == 5.0);
+  assert!(font.get_glyph('C').is_none());
+  assert!(font.get_kerning('A', 'B') == -1.0 && font.get_kerning('B', 'A') == 0.0);
+
+  assert!(BitmapFont::from_bmfont("common lineHeight=5 pages=2", AtlasRegion { x: 0, y: 0, w: 1, h: 1 }).is_err());
+  assert!(BitmapFont::from_bmfont("char id=65 x=0 y=0 width=3 height=4", AtlasRegion { x: 0, y: 0, w: 8, h: 4 }).is_err());
+}
+
+#[test]
+fn lays_out_with_kerning_and_scale() {
+  let (font, _) = load_font();
+  let mut style = TextStyle::new();
+  style.scale = 2.0;
+  style.color = RGBAf32::new(1.0, 0.0, 0.0, 0.5);
+  let list = font.build_draw_list("AB A", Vec2f32(10.0, 20.0), &style);
+  // The space isn't drawn. B is pulled back by the kerning.
+  assert!(rects(&list) == vec![
+    (Vec2f32(10.0, 20.0), Vec2f32(6.0, 8.0)),
+    (Vec2f32(18.0, 22.0), Vec2f32(6.0, 6.0)),
+    (Vec2f32(30.0, 20.0), Vec2f32(6.0, 8.0)),
+  ]);
+  assert!(list.iter().all(|c| c.color == style.color && c.layer == RENDER_LAYER_FOREGROUND && !c.screen_space));
+  assert!(font.measure("AB A", &style) == Vec2f32(28.0, 10.0));
+
+  // Unknown characters are drawn as '?'
+  let list = font.build_draw_list("Z", Vec2f32(0.0, 0.0), &TextStyle::new());
+  assert!(list.len() == 1);
+}
+
+#[test]
+fn wraps_and_aligns() {
+  let (font, _) = load_font();
+  let mut style = TextStyle::new();
+  style.wrap_width = Some(10.0);
+  // "A A" is exactly 10 wide, so fits
+  let list = font.build_draw_list("A A A", Vec2f32(0.0, 0.0), &style);
+  assert!(rects(&list).iter().map(|r| r.0).collect::<Vec<_>>()
+          == vec![Vec2f32(0.0, 0.0), Vec2f32(6.0, 0.0), Vec2f32(0.0, 5.0)]);
+  assert!(font.measure("A A A", &style) == Vec2f32(10.0, 10.0));
+
+  style.align = TextAlign::Right;
+  let list = font.build_draw_list("A A A", Vec2f32(0.0, 0.0), &style);
+  assert!(rects(&list)[2].0 == Vec2f32(6.0, 5.0));
+  style.align = TextAlign::Centre;
+  let list = font.build_draw_list("A A A", Vec2f32(0.0, 0.0), &style);
+  assert!(rects(&list)[2].0 == Vec2f32(3.0, 5.0));
+
+  // Without wrapping, lines are aligned within the widest, and newlines
+  // always break
+  style.wrap_width = None;
+  style.align = TextAlign::Right;
+  let list = font.build_draw_list("A A\nA", Vec2f32(0.0, 0.0), &style);
+  assert!(rects(&list)[2].0 == Vec2f32(6.0, 5.0));
+}
+
+#[test]
+fn renders_world_and_screen_text() {
+  // Grid font of '0' and '1', 2x2 cells, green and blue
+  let (g, b) = ([0, 255, 0, 255], [0, 0, 255, 255]);
+  let rows = [g, g, b, b, g, g, b, b];
+  let mut atlas = TextureAtlas::from_rgba(4, 2, rows.iter().flat_map(|p| p.iter().cloned()).collect());
+  let region = atlas.add_region("digits", 0, 0, 4, 2);
+  let font = Rc::new(BitmapFont::from_grid(region, 2, 2, '0').unwrap());
+  assert!(font.get_glyph('1').unwrap().region == AtlasRegion { x: 2, y: 0, w: 2, h: 2 });
+  assert!(font.get_glyph('2').is_none());
+  assert!(BitmapFont::from_grid(region, 0, 2, '0').is_err());
+  assert!(BitmapFont::from_grid(region, 2, 0, '0').is_err());
+
+  let mut g_state = GameState::new();
+  g_state.camera.set_pos(Vec2f32(100.0, 0.0));
+  for (id, pos) in [(0, Vec2f32(102.0, 0.0)), (1, Vec2f32(0.0, 6.0))].iter() {
+    g_state.comp_aabb.add_component(CompAABB {
+      entity_id: EntityID(*id),
+      pos: *pos,
+      size: Vec2f32(1.0, 1.0),
+    });
+  }
+  // World space text moves with the camera, HUD text doesn't
+  g_state.comp_text.add_component(CompText::new(EntityID(0), font.clone(), "10"));
+  let mut hud = CompText::new(EntityID(1), font.clone(), "01");
+  hud.style.layer = RENDER_LAYER_UI;
+  hud.style.screen_space = true;
+  g_state.comp_text.add_component(hud);
+
+  let mut r = SoftwareRenderer::new(8, 8);
+  r.set_atlas(&atlas);
+  r.render_game(&g_state, &TileBank::new());
+  assert!(r.get_pixel(2, 0) == b && r.get_pixel(4, 1) == g && r.get_pixel(6, 0) == [0, 0, 0, 255]);
+  assert!(r.get_pixel(0, 6) == g && r.get_pixel(3, 7) == b);
+
+  g_state.remove_entity(EntityID(0));
+  assert!(build_draw_list(&g_state, &TileBank::new()).len() == 2);
+}
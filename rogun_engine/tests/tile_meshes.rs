@@ -0,0 +1,101 @@
+extern crate engine;
+
+use engine::common::vec::Vec2f32;
+use engine::renderer::draw_list::*;
+use engine::renderer::tile_mesh::TileMeshCache;
+use engine::tile::*;
+
+/// View rectangle that sees every map.
+const VIEW_ALL: (Vec2f32, Vec2f32) = (Vec2f32(-1000.0, -1000.0), Vec2f32(1000.0, 1000.0));
+
+/// Update a cache whose meshes are the maps' draw lists, returning the
+/// positions of the maps that were rebuilt.
+fn update_in_view(cache: &mut TileMeshCache<Vec<DrawCommand>>, maps: &[TileMap16],
+                  tile_bank: &TileBank, view: (Vec2f32, Vec2f32)) -> Vec<Vec2f32> {
+  let mut rebuilt = Vec::new();
+  cache.update(maps, tile_bank, view, |t_map| {
+    rebuilt.push(t_map.world_pos);
+    build_tile_map_draw_list(t_map, tile_bank)
+  });
+  rebuilt
+}
+
+fn update(cache: &mut TileMeshCache<Vec<DrawCommand>>, maps: &[TileMap16], tile_bank: &TileBank) -> Vec<Vec2f32> {
+  update_in_view(cache, maps, tile_bank, VIEW_ALL)
+}
+
+fn maps() -> Vec<TileMap16> {
+  (0..3).map(|ii| {
+    let mut map = TileMap16::new();
+    map.world_pos = Vec2f32(ii as f32 * 256.0, 0.0);
+    map.tiles[ii] = 1;
+    map
+  }).collect()
+}
+
+#[test]
+fn only_changed_maps_are_rebuilt() {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(Tile::new(1));
+  let mut maps = maps();
+  let mut cache = TileMeshCache::new();
+
+  assert!(update(&mut cache, &maps, &tile_bank).len() == 3);
+  assert!(update(&mut cache, &maps, &tile_bank).is_empty());
+  assert!(cache.get_mesh(1).unwrap().len() == 1 && cache.get_mesh(3).is_none());
+
+  maps[1].tiles[5] = 1;
+  assert!(update(&mut cache, &maps, &tile_bank) == vec![Vec2f32(256.0, 0.0)]);
+  assert!(cache.get_mesh(1).unwrap().len() == 2);
+
+  maps[2].world_pos = Vec2f32(0.0, 256.0);
+  maps[0].tile_size = 8.0;
+  assert!(update(&mut cache, &maps, &tile_bank) == vec![Vec2f32(0.0, 0.0), Vec2f32(0.0, 256.0)]);
+  assert!(cache.get_rebuild_count() == 6);
+}
+
+#[test]
+fn tile_bank_changes_and_clear_rebuild_everything() {
+  let mut tile_bank = TileBank::new();
+  let mut maps = maps();
+  let mut cache = TileMeshCache::new();
+  update(&mut cache, &maps, &tile_bank);
+  assert!((0..3).all(|ii| cache.get_mesh(ii).unwrap().is_empty()));
+
+  tile_bank.register_tile(Tile::new(1));
+  assert!(update(&mut cache, &maps, &tile_bank).len() == 3);
+  assert!((0..3).all(|ii| cache.get_mesh(ii).unwrap().len() == 1));
+
+  cache.clear();
+  assert!(update(&mut cache, &maps, &tile_bank).len() == 3);
+
+  // Removing a map drops its mesh, adding one builds only the new map
+  maps.pop();
+  assert!(update(&mut cache, &maps, &tile_bank).is_empty());
+  assert!(cache.get_mesh(1).is_some() && cache.get_mesh(2).is_none());
+  maps.push(TileMap16::new());
+  assert!(update(&mut cache, &maps, &tile_bank).len() == 1);
+}
+
+#[test]
+fn maps_out_of_view_are_built_when_seen() {
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(Tile::new(1));
+  let mut maps = maps();
+  let mut cache = TileMeshCache::new();
+
+  // Only the first map is in view
+  let view = (Vec2f32(0.0, 0.0), Vec2f32(200.0, 200.0));
+  assert!(update_in_view(&mut cache, &maps, &tile_bank, view) == vec![Vec2f32(0.0, 0.0)]);
+  assert!(cache.get_mesh(0).is_some() && cache.get_mesh(1).is_none());
+
+  // Changing a map out of view drops its mesh without rebuilding it
+  assert!(update(&mut cache, &maps, &tile_bank).len() == 2);
+  maps[2].tiles[0] = 1;
+  assert!(update_in_view(&mut cache, &maps, &tile_bank, view).is_empty());
+  assert!(cache.get_mesh(2).is_none() && cache.get_mesh(1).is_some());
+
+  assert!(update(&mut cache, &maps, &tile_bank) == vec![Vec2f32(512.0, 0.0)]);
+  assert!(cache.get_mesh(2).unwrap().len() == 2);
+  assert!(cache.get_rebuild_count() == 4);
+}
@@ -0,0 +1,314 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::CompBody;
+use engine::physics::{PhysicsSystem, LAYER_TILES};
+use engine::physics::raycast::HitTarget;
+use engine::state::GameState;
+use engine::tile::{Tile, TileBank, TileMap16, TileShape};
+
+const GRAVITY: f32 = 500.0;
+const DT: f32 = 1.0 / 60.0;
+
+/// Shapes registered by tile_bank(), with tile IDs 1 upwards.
+const SHAPES: [TileShape; 10] = [
+  TileShape::Full, TileShape::OneWayTop, TileShape::HalfBottom, TileShape::HalfTop,
+  TileShape::Slope45Right, TileShape::Slope45Left,
+  TileShape::Slope22RightLow, TileShape::Slope22RightHigh,
+  TileShape::Slope22LeftLow, TileShape::Slope22LeftHigh,
+];
+
+/// Tile ID of a shape.
+fn id(shape: TileShape) -> u16 {
+  SHAPES.iter().position(|&s| s == shape).unwrap() as u16 + 1
+}
+
+fn tile_bank() -> TileBank {
+  let mut tile_bank = TileBank::new();
+  for &shape in SHAPES.iter() {
+    let mut tile = Tile::new(id(shape));
+    tile.shape = shape;
+    tile_bank.register_tile(tile);
+  }
+  tile_bank
+}
+
+/// Game state with a 16x16 tile map at the origin, with 16 unit tiles. The
+/// bottom row of the map is full tiles, with their tops at y = 240, then the
+/// given (x, y, shape) tiles are added.
+fn level<'a>(tiles: &[(usize, usize, TileShape)]) -> GameState<'a> {
+  let mut g_state = GameState::new();
+  let mut map = TileMap16::new();
+  for x in 0..16 {
+    map.tiles[15 * 16 + x] = id(TileShape::Full);
+  }
+  for &(x, y, shape) in tiles {
+    map.tiles[y * 16 + x] = id(shape);
+  }
+  g_state.tile_maps.push(map);
+  g_state
+}
+
+/// Add an 8x8 dynamic body at pos as entity 0.
+fn add_body(g_state: &mut GameState, pos: Vec2f32, vel: Vec2f32) {
+  common::add_box(g_state, 0, pos, Vec2f32(8.0, 8.0)).vel = vel;
+  g_state.comp_coll_aabb.get_component_mut(EntityID(0)).unwrap().layer = 1;
+}
+
+/// Step the physics once with gravity.
+fn step(physics: &mut PhysicsSystem, g_state: &mut GameState, tile_bank: &TileBank) {
+  g_state.comp_body.get_component_mut(EntityID(0)).unwrap().apply_force(Vec2f32(0.0, GRAVITY));
+  physics.update_physics(g_state, tile_bank, DT);
+}
+
+fn pos(g_state: &GameState) -> Vec2f32 {
+  g_state.comp_aabb.get_component(EntityID(0)).unwrap().pos
+}
+
+fn body<'a>(g_state: &'a mut GameState) -> &'a mut CompBody {
+  g_state.comp_body.get_component_mut(EntityID(0)).unwrap()
+}
+
+/// Walk a body standing on the floor at start_x with a walking speed, first
+/// in the direction of speed and then back, for the given number of frames
+/// each way. After every frame, the body must be on the ground with its foot
+/// (bottom centre) on floor(foot x).
+fn walk<F: Fn(f32) -> f32>(tiles: &[(usize, usize, TileShape)], start_x: f32, speed: f32,
+                           frames: u32, floor: F) {
+  let tile_bank = tile_bank();
+  let mut g_state = level(tiles);
+  add_body(&mut g_state, Vec2f32(start_x - 4.0, floor(start_x) - 8.0), Vec2f32(0.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  step(&mut physics, &mut g_state, &tile_bank);
+
+  for &dir in &[1.0, -1.0] {
+    for _ in 0..frames {
+      body(&mut g_state).vel.0 = speed * dir;
+      step(&mut physics, &mut g_state, &tile_bank);
+      let foot = pos(&g_state) + Vec2f32(4.0, 8.0);
+      assert!(body(&mut g_state).grounded);
+      assert!((foot.1 - floor(foot.0)).abs() < 0.01);
+    }
+  }
+  // Back where it started
+  assert!((pos(&g_state).0 + 4.0 - start_x).abs() < 0.01);
+}
+
+#[test]
+fn new_tiles_dont_collide() {
+  // The floor row is tile 1, left with Tile::new's shape
+  let mut tile_bank = TileBank::new();
+  tile_bank.register_tile(Tile::new(id(TileShape::Full)));
+  let mut g_state = level(&[]);
+  add_body(&mut g_state, Vec2f32(60.0, 220.0), Vec2f32(0.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..60 {
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!(pos(&g_state).1 > 256.0);
+}
+
+#[test]
+fn walk_up_and_down_45_degree_slopes() {
+  // Floor at 240, rising over x = 64 to 80 to a plateau at 224
+  let rising_right = [(4, 14, TileShape::Slope45Right), (5, 14, TileShape::Full),
+                      (6, 14, TileShape::Full), (7, 14, TileShape::Full)];
+  walk(&rising_right, 40.0, 60.0, 60, |x| 240.0 - (x - 64.0).max(0.0).min(16.0));
+
+  // Mirrored, rising left over x = 80 to 64
+  let rising_left = [(1, 14, TileShape::Full), (2, 14, TileShape::Full),
+                     (3, 14, TileShape::Full), (4, 14, TileShape::Slope45Left)];
+  walk(&rising_left, 104.0, -60.0, 60, |x| 224.0 + (x - 64.0).max(0.0).min(16.0));
+}
+
+#[test]
+fn walk_up_and_down_22_degree_slopes() {
+  // Floor at 240, rising over x = 64 to 96 to a plateau at 224
+  let rising_right = [(4, 14, TileShape::Slope22RightLow), (5, 14, TileShape::Slope22RightHigh),
+                      (6, 14, TileShape::Full), (7, 14, TileShape::Full)];
+  walk(&rising_right, 40.0, 60.0, 75, |x| 240.0 - (x - 64.0).max(0.0).min(32.0) / 2.0);
+
+  // Mirrored, rising left over x = 96 to 64
+  let rising_left = [(2, 14, TileShape::Full), (3, 14, TileShape::Full),
+                     (4, 14, TileShape::Slope22LeftHigh), (5, 14, TileShape::Slope22LeftLow)];
+  walk(&rising_left, 120.0, -60.0, 75, |x| 224.0 + (x - 64.0).max(0.0).min(32.0) / 2.0);
+}
+
+/// A row of one way tiles with their tops at y = 192, above the floor.
+fn one_way_level<'a>() -> GameState<'a> {
+  let tiles: Vec<_> = (0..16).map(|x| (x, 12, TileShape::OneWayTop)).collect();
+  level(&tiles)
+}
+
+#[test]
+fn land_on_one_way_tiles_from_above() {
+  let tile_bank = tile_bank();
+  let mut g_state = one_way_level();
+  add_body(&mut g_state, Vec2f32(40.0, 100.0), Vec2f32(0.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..60 {
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).1 - 184.0).abs() < 0.01);
+  assert!(body(&mut g_state).grounded);
+}
+
+#[test]
+fn jump_up_through_one_way_tiles() {
+  let tile_bank = tile_bank();
+  let mut g_state = one_way_level();
+  add_body(&mut g_state, Vec2f32(40.0, 232.0), Vec2f32(0.0, -300.0));
+  let mut physics = PhysicsSystem::new();
+  // Rises 90 units, well above the platform, then lands on it
+  let mut highest = 232.0f32;
+  for _ in 0..90 {
+    step(&mut physics, &mut g_state, &tile_bank);
+    highest = highest.min(pos(&g_state).1);
+  }
+  assert!(highest < 150.0);
+  assert!((pos(&g_state).1 - 184.0).abs() < 0.01);
+  assert!(body(&mut g_state).grounded);
+}
+
+#[test]
+fn drop_through_one_way_tiles() {
+  let tile_bank = tile_bank();
+  let mut g_state = one_way_level();
+  add_body(&mut g_state, Vec2f32(40.0, 184.0), Vec2f32(0.0, 0.0));
+  let mut physics = PhysicsSystem::new();
+  for _ in 0..10 {
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).1 - 184.0).abs() < 0.01);
+
+  body(&mut g_state).drop_through = true;
+  for _ in 0..60 {
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).1 - 232.0).abs() < 0.01);
+  assert!(body(&mut g_state).grounded);
+}
+
+#[test]
+fn half_tiles_collide_with_their_solid_half() {
+  let tile_bank = tile_bank();
+  let mut physics = PhysicsSystem::new();
+
+  // Land on the bottom half of a tile, at y = 232
+  let mut g_state = level(&[(2, 14, TileShape::HalfBottom)]);
+  add_body(&mut g_state, Vec2f32(36.0, 100.0), Vec2f32(0.0, 0.0));
+  for _ in 0..60 {
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).1 - 224.0).abs() < 0.01);
+
+  // Walk into the side of a bottom half from the floor, and get stopped
+  let mut g_state = level(&[(5, 14, TileShape::HalfBottom)]);
+  add_body(&mut g_state, Vec2f32(36.0, 232.0), Vec2f32(0.0, 0.0));
+  for _ in 0..60 {
+    body(&mut g_state).vel.0 = 60.0;
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).0 - 72.0).abs() < 0.01);
+
+  // Walk under a top half, which leaves an 8 unit gap above the floor
+  let mut g_state = level(&[(5, 14, TileShape::HalfTop)]);
+  add_body(&mut g_state, Vec2f32(36.0, 232.0), Vec2f32(0.0, 0.0));
+  for _ in 0..60 {
+    body(&mut g_state).vel.0 = 60.0;
+    step(&mut physics, &mut g_state, &tile_bank);
+  }
+  assert!((pos(&g_state).0 - 96.0).abs() < 0.01);
+  assert!((pos(&g_state).1 - 232.0).abs() < 0.01);
+
+  // Jump into the bottom of a top half, and hit its underside at y = 168
+  let mut g_state = level(&[(2, 10, TileShape::HalfTop)]);
+  add_body(&mut g_state, Vec2f32(36.0, 232.0), Vec2f32(0.0, -300.0));
+  let mut highest = 232.0f32;
+  for _ in 0..60 {
+    step(&mut physics, &mut g_state, &tile_bank);
+    highest = highest.min(pos(&g_state).1);
+  }
+  assert!((highest - 168.0).abs() < 0.01);
+}
+
+fn near(a: Vec2f32, b: Vec2f32) -> bool {
+  (a - b).len() < 0.001
+}
+
+#[test]
+fn rays_hit_slope_surfaces() {
+  let tile_bank = tile_bank();
+  let physics = PhysicsSystem::new();
+  let diag = 0.5f32.sqrt();
+  let down = Vec2f32(0.0, 1.0);
+
+  // 45 degree slope over x = 64 to 80, rising right from 240 to 224
+  let g_state = level(&[(4, 14, TileShape::Slope45Right)]);
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(72.0, 100.0), down,
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(hit.target == HitTarget::Tile { map: 0, x: 4, y: 14 });
+  assert!(near(hit.point, Vec2f32(72.0, 232.0)));
+  assert!(near(hit.normal, Vec2f32(-diag, -diag)));
+  assert!((hit.dist - 132.0).abs() < 0.001);
+
+  // From the side, and from inside the empty part of the tile
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(0.0, 236.0), Vec2f32(1.0, 0.0),
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(68.0, 236.0)));
+  assert!(near(hit.normal, Vec2f32(-diag, -diag)));
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(72.0, 226.0), down,
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(72.0, 232.0)) && (hit.dist - 6.0).abs() < 0.001);
+
+  // Mirrored
+  let g_state = level(&[(4, 14, TileShape::Slope45Left)]);
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(72.0, 100.0), down,
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(72.0, 232.0)));
+  assert!(near(hit.normal, Vec2f32(diag, -diag)));
+
+  // 22 degree slope, rising 8 units over the low tile
+  let g_state = level(&[(4, 14, TileShape::Slope22RightLow)]);
+  let hit = physics.raycast(&g_state, &tile_bank, Vec2f32(72.0, 100.0), down,
+                            1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(72.0, 236.0)));
+  let n = Vec2f32(-1.0, -2.0) * (1.0 / 5.0f32.sqrt());
+  assert!(near(hit.normal, n));
+}
+
+#[test]
+fn shape_casts_hit_slope_surfaces() {
+  let tile_bank = tile_bank();
+  let physics = PhysicsSystem::new();
+  let diag = 0.5f32.sqrt();
+  let size = Vec2f32(8.0, 8.0);
+  let down = Vec2f32(0.0, 1.0);
+
+  // An 8x8 box over x = 68 to 76 lands with its bottom right corner on the
+  // slope, at y = 228
+  let g_state = level(&[(4, 14, TileShape::Slope45Right)]);
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(68.0, 100.0), size, down,
+                               1000.0, LAYER_TILES).unwrap();
+  assert!(hit.target == HitTarget::Tile { map: 0, x: 4, y: 14 });
+  assert!(near(hit.point, Vec2f32(68.0, 220.0)));
+  assert!(near(hit.normal, Vec2f32(-diag, -diag)));
+  assert!((hit.dist - 120.0).abs() < 0.001);
+
+  // Mirrored, landing on its bottom left corner
+  let g_state = level(&[(4, 14, TileShape::Slope45Left)]);
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(68.0, 100.0), size, down,
+                               1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(68.0, 220.0)));
+  assert!(near(hit.normal, Vec2f32(diag, -diag)));
+
+  // Sliding sideways into the slope
+  let g_state = level(&[(4, 14, TileShape::Slope45Right)]);
+  let hit = physics.shape_cast(&g_state, &tile_bank, Vec2f32(0.0, 228.0), size,
+                               Vec2f32(1.0, 0.0), 1000.0, LAYER_TILES).unwrap();
+  assert!(near(hit.point, Vec2f32(60.0, 228.0)));
+  assert!(near(hit.normal, Vec2f32(-diag, -diag)));
+}
@@ -0,0 +1,137 @@
+extern crate engine;
+
+mod common;
+
+use engine::common::vec::Vec2f32;
+use engine::entity::EntityID;
+use engine::entity::component_core::*;
+use engine::entity::component_collision::*;
+use engine::physics::{PhysicsSystem, LAYER_DEFAULT, MASK_ALL};
+use engine::physics::trigger::{TriggerEvent, TriggerEventKind};
+use engine::state::GameState;
+use engine::tile::TileBank;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Add a 32x32 trigger at (100, 0) as entity 1.
+fn add_trigger(g_state: &mut GameState) {
+  g_state.comp_aabb.add_component(CompAABB {
+    entity_id: EntityID(1),
+    pos: Vec2f32(100.0, 0.0),
+    size: Vec2f32(32.0, 32.0),
+  });
+  g_state.comp_coll_aabb.add_component(CompCollAABB {
+    entity_id: EntityID(1),
+    offset: Vec2f32(0.0, 0.0),
+    size: Vec2f32(32.0, 32.0),
+    solid: false,
+    layer: LAYER_DEFAULT,
+    mask: MASK_ALL,
+    callback: None,
+  });
+}
+
+/// Add an 8x8 solid box moving at vel as entity 0.
+fn add_mover(g_state: &mut GameState, pos: Vec2f32, vel: Vec2f32) {
+  common::add_box(g_state, 0, pos, Vec2f32(8.0, 8.0)).vel = vel;
+}
+
+fn count(events: &[TriggerEvent], kind: TriggerEventKind) -> usize {
+  events.iter().filter(|e| e.kind == kind).count()
+}
+
+/// Callback counts for passing_through_gives_one_enter_and_one_exit. Each
+/// test has its own, as tests run in parallel.
+static PASS_ENTERS: AtomicUsize = AtomicUsize::new(0);
+static PASS_EXITS: AtomicUsize = AtomicUsize::new(0);
+
+fn pass_callback(trigger: EntityID, other: EntityID, kind: TriggerEventKind) {
+  assert!(trigger == EntityID(1) && other == EntityID(0));
+  match kind {
+    TriggerEventKind::Enter => PASS_ENTERS.fetch_add(1, Ordering::SeqCst),
+    TriggerEventKind::Exit => PASS_EXITS.fetch_add(1, Ordering::SeqCst),
+    TriggerEventKind::Stay => panic!("Callbacks aren't called for stay"),
+  };
+}
+
+#[test]
+fn passing_through_gives_one_enter_and_one_exit() {
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_trigger(&mut g_state);
+  g_state.comp_coll_aabb.get_component_mut(EntityID(1)).unwrap().callback = Some(&pass_callback);
+  // Crosses the trigger's 40 units (including its own width) in 20 steps
+  add_mover(&mut g_state, Vec2f32(80.0, 8.0), Vec2f32(120.0, 0.0));
+
+  let mut physics = PhysicsSystem::new();
+  let mut events = Vec::new();
+  let mut stays = 0;
+  for _ in 0..60 {
+    physics.update_physics(&mut g_state, &tile_bank, DT);
+    events.extend_from_slice(physics.trigger_events());
+    stays = stays.max(count(physics.trigger_events(), TriggerEventKind::Stay));
+  }
+
+  assert!(count(&events, TriggerEventKind::Enter) == 1);
+  assert!(count(&events, TriggerEventKind::Exit) == 1);
+  assert!(count(&events, TriggerEventKind::Stay) > 10);
+  assert!(stays == 1);
+  assert!(PASS_ENTERS.load(Ordering::SeqCst) == 1 && PASS_EXITS.load(Ordering::SeqCst) == 1);
+
+  // Enter comes first, then every stay, then the exit
+  assert!(events[0].kind == TriggerEventKind::Enter);
+  assert!(events[events.len() - 1].kind == TriggerEventKind::Exit);
+}
+
+#[test]
+fn removing_an_overlapping_entity_gives_one_exit() {
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_trigger(&mut g_state);
+  add_mover(&mut g_state, Vec2f32(110.0, 8.0), Vec2f32(0.0, 0.0));
+
+  let mut physics = PhysicsSystem::new();
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  assert!(count(physics.trigger_events(), TriggerEventKind::Enter) == 1);
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  assert!(count(physics.trigger_events(), TriggerEventKind::Stay) == 1);
+
+  g_state.remove_entity(EntityID(0));
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  let events = physics.trigger_events();
+  assert!(events.len() == 1);
+  assert!(events[0].kind == TriggerEventKind::Exit);
+  assert!(events[0].trigger == EntityID(1) && events[0].other == EntityID(0));
+
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  assert!(physics.trigger_events().is_empty());
+}
+
+static REMOVED_EXITS: AtomicUsize = AtomicUsize::new(0);
+
+fn removed_callback(_: EntityID, _: EntityID, kind: TriggerEventKind) {
+  if kind == TriggerEventKind::Exit { REMOVED_EXITS.fetch_add(1, Ordering::SeqCst); }
+}
+
+#[test]
+fn removing_the_trigger_gives_one_exit() {
+  let tile_bank = TileBank::new();
+  let mut g_state = GameState::new();
+  add_trigger(&mut g_state);
+  g_state.comp_coll_aabb.get_component_mut(EntityID(1)).unwrap().callback = Some(&removed_callback);
+  add_mover(&mut g_state, Vec2f32(110.0, 8.0), Vec2f32(0.0, 0.0));
+
+  let mut physics = PhysicsSystem::new();
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  g_state.remove_entity(EntityID(1));
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  let events = physics.trigger_events();
+  assert!(events.len() == 1 && events[0].kind == TriggerEventKind::Exit);
+
+  // The trigger's gone, so there's no callback to call
+  assert!(REMOVED_EXITS.load(Ordering::SeqCst) == 0);
+
+  physics.update_physics(&mut g_state, &tile_bank, DT);
+  assert!(physics.trigger_events().is_empty());
+}
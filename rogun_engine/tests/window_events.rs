@@ -0,0 +1,47 @@
+extern crate engine;
+extern crate glium;
+
+use engine::input::InputSystem;
+use engine::input::input_source::ScriptedInput;
+use engine::state::GameState;
+use glium::glutin::{Event, VirtualKeyCode, MouseButton};
+
+/// Run a frame of input with no game state to control.
+fn step(input: &mut InputSystem, script: &mut ScriptedInput) {
+  input.process_input(&mut GameState::new(), script, 1.0 / 60.0);
+}
+
+#[test]
+fn close_requests_quit() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.push_event(1, Event::Closed);
+  step(&mut input, &mut script);
+  assert!(!input.is_quit_requested());
+  step(&mut input, &mut script);
+  assert!(input.is_quit_requested());
+}
+
+#[test]
+fn resize_reported_for_one_frame() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.push_event(0, Event::Resized(1024, 768));
+  step(&mut input, &mut script);
+  assert!(input.get_resize() == Some((1024, 768)));
+  step(&mut input, &mut script);
+  assert!(input.get_resize().is_none());
+}
+
+#[test]
+fn focus_loss_releases_everything() {
+  let mut input = InputSystem::new();
+  let mut script = ScriptedInput::new();
+  script.key_press(0, VirtualKeyCode::D);
+  script.mouse_press(0, MouseButton::Left);
+  script.push_event(5, Event::Focused(false));
+  for _ in 0..6 { step(&mut input, &mut script); }
+  assert!(input.is_key_down(VirtualKeyCode::D).is_none());
+  assert!(input.is_key_just_released(VirtualKeyCode::D).is_some());
+  assert!(input.is_mouse_button_down(MouseButton::Left).is_none());
+}
@@ -5,20 +5,25 @@ fn setup_g_state(engine: &mut engine::LibState) {
   use engine::entity::component_core::*;
   use engine::entity::component_render::*;
   use engine::entity::component_control::*;
-  use engine::entity::component_collision::*;
   use engine::entity::*;
   use engine::common::vec::*;
   use engine::common::color::*;
   use engine::tile::*;
+  use engine::input::input_map::Binding;
   use glium::glutin::VirtualKeyCode;
 
   {
     // Edit tile bank, insert tile with id = 1
     let mut tile = Tile::new(1);
     tile.color = RGBf32::new(0.0, 1.0, 0.0);
+    tile.shape = TileShape::Full;
     engine.register_tile(tile);
   }
 
+  // Bind the movement actions used by the controllers
+  engine.input_system.input_map.bind("move_left", Binding::Key(VirtualKeyCode::A));
+  engine.input_system.input_map.bind("move_right", Binding::Key(VirtualKeyCode::D));
+
   let g_state = &mut engine.curr_g_state.as_mut().unwrap();
 
   g_state.comp_aabb.add_component(CompAABB {
@@ -32,22 +37,22 @@ fn setup_g_state(engine: &mut engine::LibState) {
     color: RGBf32::new(1.0, 0.0, 0.0),
   });
 
-  g_state.comp_body.add_component(CompBody {
-    entity_id: EntityID(0),
-    vel: Vec2f32(0.0, 0.0),
-    acc: Vec2f32(0.0, 0.0),
-    force: Vec2f32(0.0, 0.0),
-    mass: 1.0,
-    max_speed: 100.0,
-  });
+  let mut body = CompBody::new(EntityID(0));
+  body.max_speed = 100.0;
+  g_state.comp_body.add_component(body);
 
   g_state.comp_lr_controller.add_component(CompLRController {
     entity_id: EntityID(0),
-    key_l : VirtualKeyCode::A,
-    key_r : VirtualKeyCode::D,
+    action_l : "move_left".to_owned(),
+    action_r : "move_right".to_owned(),
     force: 100.0,
   });
 
+  // Follow the player, letting it move a little before the camera catches up
+  g_state.camera.target = Some(EntityID(0));
+  g_state.camera.smoothing = 5.0;
+  g_state.camera.dead_zone = Vec2f32(100.0, 100.0);
+
   let mut map = TileMap16::new();
   for ii in 0..map.tiles.len() {
     if ii % 2 == 0  { continue; }
@@ -60,7 +65,5 @@ fn main() {
   let mut engine = engine::init().unwrap();
   setup_g_state(&mut engine);
 
-  loop {
-    engine.update();
-  }
+  while engine.update() {}
 }